@@ -1,6 +1,7 @@
-use std::fmt::{self, Display};
-use std::iter::Iterator;
-use std::str::Chars;
+use core::error::Error;
+use core::fmt::{self, Display};
+use core::iter::Iterator;
+use core::str::{Chars, FromStr};
 
 use crate::board::Piece;
 use crate::location::{Coords, File as Lt, Rank as Nt};
@@ -21,49 +22,83 @@ enum Token {
     Castle,
     /// -0 (-O)
     Long,
+    /// -- (Z0)
+    Null,
     /// =
     Promote,
+    /// e.p.
+    EnPassant,
+    /// !
+    Good,
+    /// ?
+    Bad,
 }
 
 struct TokenStream<'a> {
+    s: &'a str,
     chars: Chars<'a>,
-    peeked: Option<Token>,
+    notation: &'a Notation,
+    peeked: Option<(usize, Token)>,
+    /// Where the token last taken from the stream starts, or the end of the input if there were none left
+    last: usize,
 }
 
 impl<'a> TokenStream<'a> {
-    fn new(s: &'a str) -> Self {
+    fn new(s: &'a str, notation: &'a Notation) -> Self {
         TokenStream {
+            s,
             chars: s.chars(),
+            notation,
             peeked: None,
+            last: 0,
         }
     }
     fn peek(&mut self) -> Option<Token> {
-        self.peeked = self.next();
-        self.peeked
+        if self.peeked.is_none() {
+            self.peeked = self.read();
+        }
+        self.peeked.map(|(_, t)| t)
     }
-    fn set_to_peek(&mut self, token: Token) {
-        assert!(self.peeked.is_none(), "cannot append on peeked stream");
-        self.peeked = Some(token);
+    /// Takes the next token if `f` accepts it
+    fn next_if<T>(&mut self, f: impl FnOnce(Token) -> Option<T>) -> Option<T> {
+        let t = f(self.peek()?)?;
+        self.next();
+        Some(t)
     }
-}
-
-impl<'a> Iterator for TokenStream<'a> {
-    type Item = Token;
-
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Takes the next token, which has to be accepted by `f`
+    fn expect<T>(&mut self, expected: Expected, f: impl FnOnce(Token) -> Option<T>) -> Result<T, ParseMoveError> {
+        match self.next().and_then(f) {
+            Some(t) => Ok(t),
+            None => Err(self.error(expected)),
+        }
+    }
+    /// An error at the token last taken
+    fn error(&self, expected: Expected) -> ParseMoveError {
+        ParseMoveError {
+            offset: self.last,
+            expected,
+        }
+    }
+    /// An error at the next token
+    fn unexpected(&mut self, expected: Expected) -> ParseMoveError {
+        self.next();
+        self.error(expected)
+    }
+    fn read(&mut self) -> Option<(usize, Token)> {
         use self::Token::*;
 
-        let peeked = self.peeked.take();
-        if peeked.is_some() {
-            return peeked;
+        let rest = self.chars.as_str().trim_start();
+        let offset = self.s.len() - rest.len();
+        self.chars = rest.chars();
+        let c = self.chars.next()?;
+        if let Some(piece) = self.notation.piece(c) {
+            return Some((offset, Capital(piece)));
         }
-
-        Some(match self.chars.next()? {
-            'R' => Capital(Piece::Rook),
-            'N' => Capital(Piece::Knight),
-            'B' => Capital(Piece::Bishop),
-            'Q' => Capital(Piece::Queen),
-            'K' => Capital(Piece::King),
+        let token = match c {
+            'e' if self.chars.as_str().starts_with(".p.") => {
+                self.chars.nth(2);
+                EnPassant
+            }
             l @ 'a'..='h' => Letter(Lt::from_char(l).unwrap()),
             n @ '1'..='8' => Number(Nt::from_char(n).unwrap()),
             'x' => Capture,
@@ -75,19 +110,92 @@ impl<'a> Iterator for TokenStream<'a> {
             },
             '-' => match self.chars.next() {
                 Some('0' | 'O') => Long,
+                Some('-') => Null,
+                _ => Invalid,
+            },
+            'Z' => match self.chars.next() {
+                Some('0') => Null,
                 _ => Invalid,
             },
             '=' => Promote,
-            c if c.is_whitespace() => self.next()?,
+            '!' => Good,
+            '?' => Bad,
             _ => Invalid,
+        };
+        Some((offset, token))
+    }
+}
+
+impl<'a> Iterator for TokenStream<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.peeked.take().or_else(|| self.read());
+        self.last = next.map_or(self.s.len(), |(offset, _)| offset);
+        next.map(|(_, t)| t)
+    }
+}
+
+/// The letters standing for the pieces, used for both reading and writing moves
+///
+/// The letters must not be files or any of the other characters used in moves, like `x` or `O`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Notation {
+    pub king: char,
+    pub queen: char,
+    pub rook: char,
+    pub bishop: char,
+    pub knight: char,
+}
+
+impl Notation {
+    pub const ENGLISH: Self = Notation {
+        king: 'K',
+        queen: 'Q',
+        rook: 'R',
+        bishop: 'B',
+        knight: 'N',
+    };
+    /// Konge, dronning, tårn, løber and springer
+    pub const DANISH: Self = Notation {
+        king: 'K',
+        queen: 'D',
+        rook: 'T',
+        bishop: 'L',
+        knight: 'S',
+    };
+
+    /// The letter for a piece, pawns have none
+    pub const fn letter(&self, piece: Piece) -> Option<char> {
+        Some(match piece {
+            Piece::Pawn => return None,
+            Piece::King => self.king,
+            Piece::Queen => self.queen,
+            Piece::Rook => self.rook,
+            Piece::Bishop => self.bishop,
+            Piece::Knight => self.knight,
         })
     }
+    /// The piece a letter stands for
+    pub fn piece(&self, letter: char) -> Option<Piece> {
+        [Piece::King, Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight]
+            .into_iter()
+            .find(|&p| self.letter(p) == Some(letter))
+    }
+}
+
+impl Default for Notation {
+    fn default() -> Self {
+        Notation::ENGLISH
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Move {
     pub move_type: MoveType,
     pub king_threat: KingThreat,
+    /// The evaluation suffix written after the move, like `!?`
+    pub annotation: Option<Annotation>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -101,13 +209,13 @@ pub enum Mover {
 
 impl Mover {
     pub const fn is_pawn(&self) -> bool {
-        match self {
+        matches!(
+            self,
             Mover::Piece(Piece::Pawn)
-            | Mover::PieceAt(Piece::Pawn, _)
-            | Mover::PieceAtLetter(Piece::Pawn, _)
-            | Mover::PieceAtNumber(Piece::Pawn, _) => true,
-            _ => false,
-        }
+                | Mover::PieceAt(Piece::Pawn, _)
+                | Mover::PieceAtLetter(Piece::Pawn, _)
+                | Mover::PieceAtNumber(Piece::Pawn, _)
+        )
     }
 }
 
@@ -115,6 +223,8 @@ impl Mover {
 pub enum MoveType {
     ShortCastle,
     LongCastle,
+    /// Passing the turn without moving, written `--`
+    Null,
     Regular {
         mover: Mover,
         captures: bool,
@@ -130,11 +240,99 @@ pub enum KingThreat {
     CheckMate,
 }
 
+/// A suffix judging a move, numbered like the matching NAGs in PGN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Annotation {
+    /// !
+    Good = 1,
+    /// ?
+    Mistake = 2,
+    /// !!
+    Brilliant = 3,
+    /// ??
+    Blunder = 4,
+    /// !?
+    Interesting = 5,
+    /// ?!
+    Dubious = 6,
+}
+
+impl Annotation {
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Annotation::Good => "!",
+            Annotation::Mistake => "?",
+            Annotation::Brilliant => "!!",
+            Annotation::Blunder => "??",
+            Annotation::Interesting => "!?",
+            Annotation::Dubious => "?!",
+        }
+    }
+    /// The annotation written as `s`, like `?!`
+    pub fn from_symbol(s: &str) -> Option<Self> {
+        use self::Annotation::*;
+        [Good, Mistake, Brilliant, Blunder, Interesting, Dubious]
+            .into_iter()
+            .find(|a| a.symbol() == s)
+    }
+    fn from_ts(ts: &mut TokenStream) -> Option<Self> {
+        use self::Token::{Bad, Good};
+        let suffix = |t| matches!(t, Good | Bad).then_some(t);
+        let first = ts.next_if(suffix)?;
+        let second = ts.next_if(suffix);
+        Some(match (first, second) {
+            (Good, None) => Annotation::Good,
+            (Bad, None) => Annotation::Mistake,
+            (Good, Some(Good)) => Annotation::Brilliant,
+            (Bad, Some(Bad)) => Annotation::Blunder,
+            (Good, Some(_)) => Annotation::Interesting,
+            (_, Some(_)) => Annotation::Dubious,
+            _ => unreachable!(),
+        })
+    }
+}
+
+impl Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// Serialized in algebraic notation, like `Nxf3+`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Move {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Move {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = alloc::string::String::deserialize(deserializer)?;
+        Move::from_str(&s).map_err(|e| serde::de::Error::custom(format_args!("invalid move {s:?}, {e}")))
+    }
+}
+
 impl Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.move_type {
+        self.display(&Notation::ENGLISH).fmt(f)
+    }
+}
+
+/// A [`Move`] written with the piece letters of a [`Notation`], see [`Move::display`]
+pub struct MoveDisplay<'a> {
+    mv: &'a Move,
+    notation: &'a Notation,
+}
+
+impl Display for MoveDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let piece = |p| PieceLetter(self.notation.letter(p));
+        match self.mv.move_type {
             MoveType::ShortCastle => write!(f, "O-O")?,
             MoveType::LongCastle => write!(f, "O-O-O")?,
+            MoveType::Null => write!(f, "--")?,
             MoveType::Regular {
                 mover,
                 captures,
@@ -142,185 +340,212 @@ impl Display for Move {
                 promotes,
             } => {
                 match mover {
-                    Mover::Piece(p) => write!(f, "{}", p)?,
-                    Mover::PieceAtNumber(p, n) => write!(f, "{}{}", p, n)?,
-                    Mover::PieceAtLetter(p, l) => write!(f, "{}{}", p, l)?,
-                    Mover::PieceAt(p, cs) => write!(f, "{}{}", p, cs)?,
+                    Mover::Piece(p) => write!(f, "{}", piece(p))?,
+                    Mover::PieceAtNumber(p, n) => write!(f, "{}{}", piece(p), n)?,
+                    Mover::PieceAtLetter(p, l) => write!(f, "{}{}", piece(p), l)?,
+                    Mover::PieceAt(p, cs) => write!(f, "{}{}", piece(p), cs)?,
                 }
                 if captures {
                     write!(f, "x")?;
                 }
                 write!(f, "{}", destination)?;
                 if let Some(p) = promotes {
-                    write!(f, "={}", p)?;
+                    write!(f, "={}", piece(p))?;
                 }
             }
         }
-        match self.king_threat {
-            KingThreat::None => Ok(()),
-            KingThreat::Check => write!(f, "+"),
-            KingThreat::CheckMate => write!(f, "#"),
+        match self.mv.king_threat {
+            KingThreat::None => (),
+            KingThreat::Check => write!(f, "+")?,
+            KingThreat::CheckMate => write!(f, "#")?,
+        }
+        if let Some(annotation) = self.mv.annotation {
+            write!(f, "{annotation}")?;
+        }
+        Ok(())
+    }
+}
+
+struct PieceLetter(Option<char>);
+
+impl Display for PieceLetter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(c) => write!(f, "{c}"),
+            None => Ok(()),
         }
     }
 }
 
 impl MoveType {
-    fn parse_regular(piece: Piece, ts: &mut TokenStream) -> Option<Self> {
+    fn from_ts(ts: &mut TokenStream) -> Result<Self, ParseMoveError> {
         use self::Token::*;
-        match (ts.next()?, ts.next()?) {
-            // Pl -> ln
-            (Letter(l), Letter(l2)) => match ts.next()? {
-                Number(n) => Some(MoveType::Regular {
-                    mover: Mover::PieceAtLetter(piece, l),
-                    captures: false,
-                    destination: Coords::new(l2, n),
-                    promotes: Self::parse_promotion(ts),
-                }),
-                _ => None,
-            },
-            // Pn l
-            (Number(n), Letter(l)) => match ts.next()? {
-                // Pn -> ln
-                Number(n2) => Some(MoveType::Regular {
-                    mover: Mover::PieceAtNumber(piece, n),
-                    captures: false,
-                    destination: Coords::new(l, n2),
-                    promotes: Self::parse_promotion(ts),
-                }),
-                _ => None,
-            },
-            // Pl.n
-            (Letter(l), Number(n)) => match ts.peek() {
-                // Pln -> ln
-                Some(Letter(l2)) => {
-                    ts.peeked = None;
-                    match ts.next() {
-                        Some(Number(n2)) => Some(MoveType::Regular {
-                            mover: Mover::PieceAt(piece, Coords::new(l, n)),
-                            captures: false,
-                            destination: Coords::new(l2, n2),
-                            promotes: Self::parse_promotion(ts),
-                        }),
-                        _ => None,
-                    }
-                }
-                // Pln x ln
-                Some(Capture) => {
-                    ts.peeked = None;
-                    Some(MoveType::Regular {
-                        mover: Mover::PieceAt(piece, Coords::new(l, n)),
-                        captures: true,
-                        destination: Self::parse_destination(ts)?,
-                        promotes: Self::parse_promotion(ts),
-                    })
-                }
-                // P -> ln
-                _ => Some(MoveType::Regular {
-                    mover: Mover::Piece(piece),
-                    captures: false,
-                    destination: Coords::new(l, n),
-                    promotes: Self::parse_promotion(ts),
-                }),
-            },
-            // at letter capturing -> LN
-            (Letter(l), Capture) => Some(MoveType::Regular {
-                mover: Mover::PieceAtLetter(piece, l),
-                captures: true,
-                destination: Self::parse_destination(ts)?,
-                promotes: Self::parse_promotion(ts),
-            }),
-            // at number capturing -> LN
-            (Number(n), Capture) => Some(MoveType::Regular {
-                mover: Mover::PieceAtNumber(piece, n),
-                captures: true,
-                destination: Self::parse_destination(ts)?,
-                promotes: Self::parse_promotion(ts),
-            }),
-            // Capture -> LN
-            (Capture, Letter(l2)) => match ts.next() {
-                Some(Number(n2)) => Some(MoveType::Regular {
-                    mover: Mover::Piece(piece),
-                    captures: true,
-                    destination: Coords::new(l2, n2),
-                    promotes: Self::parse_promotion(ts),
-                }),
-                _ => None,
-            },
+        let piece = match ts.peek() {
+            Some(Castle) => {
+                ts.next();
+                return Ok(match ts.next_if(|t| matches!(t, Long).then_some(())) {
+                    Some(()) => MoveType::LongCastle,
+                    None => MoveType::ShortCastle,
+                });
+            }
+            Some(Null) => {
+                ts.next();
+                return Ok(MoveType::Null);
+            }
+            Some(Capital(piece)) => {
+                ts.next();
+                piece
+            }
+            Some(Letter(_) | Number(_) | Capture) => Piece::Pawn,
+            _ => return Err(ts.unexpected(Expected::Move)),
+        };
+
+        let file = ts.next_if(|t| match t {
+            Letter(l) => Some(l),
             _ => None,
-        }
-    }
-    fn parse_destination(ts: &mut TokenStream) -> Option<Coords> {
-        match (ts.next()?, ts.next()?) {
-            (Token::Letter(l), Token::Number(n)) => Some(Coords::new(l, n)),
+        });
+        let rank = ts.next_if(|t| match t {
+            Number(n) => Some(n),
             _ => None,
-        }
-    }
-    fn parse_promotion(ts: &mut TokenStream) -> Option<Piece> {
-        match ts.peek() {
-            Some(Token::Promote) => {
-                ts.peeked = None;
-                match ts.next() {
-                    Some(Token::Capital(p)) => Some(p),
-                    _ => None,
-                }
+        });
+        let captures = ts.next_if(|t| matches!(t, Capture).then_some(())).is_some();
+        let (mover, destination) = if captures || matches!(ts.peek(), Some(Letter(_))) {
+            // What came before the destination says where the piece moves from
+            let mover = match (file, rank) {
+                (None, None) => Mover::Piece(piece),
+                (Some(l), None) => Mover::PieceAtLetter(piece, l),
+                (None, Some(n)) => Mover::PieceAtNumber(piece, n),
+                (Some(l), Some(n)) => Mover::PieceAt(piece, Coords::new(l, n)),
+            };
+            (mover, Self::parse_destination(ts)?)
+        } else {
+            match (file, rank) {
+                (Some(l), Some(n)) => (Mover::Piece(piece), Coords::new(l, n)),
+                (Some(_), None) => return Err(ts.unexpected(Expected::Rank)),
+                (None, _) => return Err(ts.unexpected(Expected::Square)),
             }
-            _ => None,
-        }
+        };
+
+        Ok(MoveType::Regular {
+            mover,
+            captures,
+            destination,
+            promotes: Self::parse_promotion(ts)?,
+        })
     }
-    fn from_ts(ts: &mut TokenStream) -> Option<Self> {
+    fn parse_destination(ts: &mut TokenStream) -> Result<Coords, ParseMoveError> {
         use self::Token::*;
-        match ts.next()? {
-            Capital(moving_piece) => Self::parse_regular(moving_piece, ts),
-            t @ Letter(_) | t @ Number(_) | t @ Capture => {
-                ts.set_to_peek(t);
-                Self::parse_regular(Piece::Pawn, ts)
-            }
-            Castle => match ts.peek() {
-                Some(Long) => {
-                    ts.next();
-                    Some(MoveType::LongCastle)
-                }
-                _ => Some(MoveType::ShortCastle),
-            },
+        let l = ts.expect(Expected::Square, |t| match t {
+            Letter(l) => Some(l),
+            _ => None,
+        })?;
+        let n = ts.expect(Expected::Rank, |t| match t {
+            Number(n) => Some(n),
             _ => None,
+        })?;
+        Ok(Coords::new(l, n))
+    }
+    fn parse_promotion(ts: &mut TokenStream) -> Result<Option<Piece>, ParseMoveError> {
+        use self::Token::*;
+        if ts.next_if(|t| matches!(t, Promote).then_some(())).is_none() {
+            return Ok(None);
         }
+        ts.expect(Expected::Promotion, |t| match t {
+            Capital(p) => Some(Some(p)),
+            _ => None,
+        })
     }
 }
 
 impl Move {
-    pub fn from_str(s: &str) -> Option<Self> {
+    /// Reads a move in algebraic notation, leniently accepting things like `0-0`, `Ng1f3`, `++` for mate and whitespace
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, ParseMoveError> {
+        Move::parse_with(s, &Notation::ENGLISH)
+    }
+    /// Reads a move written with the piece letters of `notation`, as leniently as [`Move::from_str`]
+    pub fn parse_with(s: &str, notation: &Notation) -> Result<Self, ParseMoveError> {
         use self::Token::*;
-        let mut ts = TokenStream::new(s);
+        let mut ts = TokenStream::new(s, notation);
 
         let move_type = MoveType::from_ts(&mut ts)?;
+        // The marker some books write after en passant captures says nothing the position doesn't
+        if let MoveType::Regular { captures: true, .. } = move_type {
+            ts.next_if(|t| matches!(t, EnPassant).then_some(()));
+        }
 
-        Some(match ts.peek() {
-            Some(Mate) => Move {
-                move_type,
-                king_threat: KingThreat::CheckMate,
-            },
+        let king_threat = match ts.peek() {
+            Some(Mate) => {
+                ts.next();
+                KingThreat::CheckMate
+            }
             Some(Check) => {
-                let _ = ts.next();
+                ts.next();
 
                 if matches!(ts.peek(), Some(Check)) {
                     ts.next();
-                    Move {
-                        move_type,
-                        king_threat: KingThreat::CheckMate,
-                    }
+                    KingThreat::CheckMate
                 } else {
-                    Move {
-                        move_type,
-                        king_threat: KingThreat::Check,
-                    }
+                    KingThreat::Check
                 }
             }
-            _ => Move {
-                move_type,
-                king_threat: KingThreat::None,
-            },
+            _ => KingThreat::None,
+        };
+
+        let annotation = Annotation::from_ts(&mut ts);
+        if ts.peek().is_some() {
+            return Err(ts.unexpected(Expected::End));
+        }
+
+        Ok(Move {
+            move_type,
+            king_threat,
+            annotation,
         })
     }
+    /// Parses a move only if it is written in standard SAN, exactly as [`Display`] would write it
+    ///
+    /// Unlike [`Move::from_str`] this rejects castling with zeros, whitespace, `++` for mate,
+    /// annotations like `!?`, trailing characters, promotions without `=` and pawn moves that aren't written as `e4` or `exd5`.
+    /// Whether capture marks and disambiguation fit the position is checked by `Game::replay_strict`.
+    pub fn parse_strict(s: &str) -> Result<Self, ParseMoveError> {
+        let mv = Move::from_str(s)?;
+        let non_standard = |offset| ParseMoveError {
+            offset,
+            expected: Expected::Standard,
+        };
+        if mv.annotation.is_some() {
+            return Err(non_standard(s.find(['!', '?']).unwrap_or(0)));
+        }
+        if let MoveType::Regular {
+            mover, captures, promotes, ..
+        } = mv.move_type
+        {
+            let standard_mover = match mover {
+                Mover::Piece(p) => p != Piece::Pawn || !captures,
+                Mover::PieceAtLetter(Piece::Pawn, _) => captures,
+                _ => !mover.is_pawn(),
+            };
+            let standard_promotion = match promotes {
+                None => true,
+                Some(p) => mover.is_pawn() && !matches!(p, Piece::Pawn | Piece::King),
+            };
+            if !standard_mover || !standard_promotion {
+                return Err(non_standard(0));
+            }
+        }
+
+        let mut rest = Written(s);
+        if fmt::write(&mut rest, format_args!("{mv}")).is_ok() && rest.0.is_empty() {
+            Ok(mv)
+        } else {
+            Err(non_standard(s.len() - rest.0.len()))
+        }
+    }
+    /// Writes the move with the piece letters of `notation`
+    pub fn display<'a>(&'a self, notation: &'a Notation) -> MoveDisplay<'a> {
+        MoveDisplay { mv: self, notation }
+    }
     pub fn promotion(&self) -> Option<Piece> {
         match self.move_type {
             MoveType::Regular { promotes, .. } => promotes,
@@ -328,3 +553,64 @@ impl Move {
         }
     }
 }
+impl FromStr for Move {
+    type Err = ParseMoveError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Move::from_str(s)
+    }
+}
+
+/// Checks formatted output against a string, consuming it as it matches
+struct Written<'a>(&'a str);
+
+impl fmt::Write for Written<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 = self.0.strip_prefix(s).ok_or(fmt::Error)?;
+        Ok(())
+    }
+}
+
+/// The error from parsing a move in algebraic or coordinate notation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseMoveError {
+    /// The byte in the input where something else was expected, the length of the input if it ended too soon
+    pub offset: usize,
+    pub expected: Expected,
+}
+
+/// What should have been in a move where parsing it failed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Expected {
+    /// A piece, a square or castling
+    Move,
+    Square,
+    Rank,
+    /// The piece to promote to
+    Promotion,
+    /// The end of the move
+    End,
+    /// The move written like in standard SAN, see [`Move::parse_strict`]
+    Standard,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Expected::Move => "a piece, a square or castling",
+            Expected::Square => "a square",
+            Expected::Rank => "a rank",
+            Expected::Promotion => "a piece to promote to",
+            Expected::End => "the end of the move",
+            Expected::Standard => "standard algebraic notation",
+        })
+    }
+}
+
+impl Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} at byte {}", self.expected, self.offset)
+    }
+}
+
+impl Error for ParseMoveError {}
+
@@ -0,0 +1,92 @@
+//! Saving finished games to a PGN file so they aren't lost when the program closes
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{board::Colour, config::ArchiveConfig, pgn::{write_game, Tags}, player::GameRunner};
+
+/// A PGN file that games are appended to
+#[derive(Debug, Clone)]
+pub struct Archive {
+    path: PathBuf,
+}
+
+impl Archive {
+    pub fn new(path: PathBuf) -> Self {
+        Archive { path }
+    }
+    /// The archive set up in the config, if saving games is enabled
+    pub fn from_config(config: &ArchiveConfig) -> Option<Self> {
+        config.archive_path().map(Archive::new)
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Appends the game with the players, their settings and the time it was saved as tags
+    pub fn append(&self, event: &str, runner: &GameRunner) -> io::Result<()> {
+        let (date, time) = utc_date_time(SystemTime::now());
+        let mut tags = Tags::new()
+            .with("Event", event)
+            .with("Site", "talv")
+            .with("Date", date.clone())
+            .with("Round", "-")
+            .with("White", runner.player(Colour::White).name())
+            .with("Black", runner.player(Colour::Black).name())
+            .with("UTCDate", date)
+            .with("UTCTime", time);
+        for (colour, tag) in [(Colour::White, "WhiteSettings"), (Colour::Black, "BlackSettings")] {
+            if let Some(settings) = runner.player(colour).settings() {
+                tags.set(tag, settings);
+            }
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut w = BufWriter::new(file);
+        write_game(&mut w, &tags, runner.game(), runner.ending())?;
+        w.flush()
+    }
+}
+
+/// The date and time formatted for the PGN `UTCDate` and `UTCTime` tags
+fn utc_date_time(time: SystemTime) -> (String, String) {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Converts days since the epoch to a proleptic Gregorian date, counting years from March so leap days come last
+    let days = days as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = era * 400 + year_of_era + (month <= 2) as i64;
+
+    (
+        format!("{year}.{month:02}.{day:02}"),
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_utc_date_time() {
+        assert_eq!(utc_date_time(UNIX_EPOCH), ("1970.01.01".to_owned(), "00:00:00".to_owned()));
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3661);
+        assert_eq!(utc_date_time(leap_day), ("2000.02.29".to_owned(), "01:01:01".to_owned()));
+        let new_year = UNIX_EPOCH + Duration::from_secs(1_704_067_199);
+        assert_eq!(utc_date_time(new_year), ("2023.12.31".to_owned(), "23:59:59".to_owned()));
+    }
+}
@@ -0,0 +1,75 @@
+use std::{env::args, fs};
+
+use talv::{bots::bot1::EvalParams, openings::OpeningSuite, player::{Bot1Player, Ending, GameRunner, Step}, prelude::*};
+
+const MAX_NODES: usize = 1_000_000;
+
+fn main() {
+    let mut args = args().skip(1);
+    let (Some(path), first_depth, second_depth) = (args.next(), args.next(), args.next()) else {
+        eprintln!("Usage: bot_match <suite.epd> [first depth] [second depth] [first params.toml] [second params.toml]");
+        return;
+    };
+    let first_depth = first_depth.and_then(|d| d.parse().ok()).unwrap_or(4);
+    let second_depth = second_depth.and_then(|d| d.parse().ok()).unwrap_or(3);
+    let (Some(first_params), Some(second_params)) = (load_params(args.next()), load_params(args.next())) else {
+        return;
+    };
+
+    let suite = match fs::read_to_string(&path).map(|s| OpeningSuite::parse(&s)) {
+        Ok(Ok(suite)) => suite,
+        Ok(Err(e)) => {
+            eprintln!("{e}");
+            return;
+        }
+        Err(e) => {
+            eprintln!("Could not read {path}: {e}");
+            return;
+        }
+    };
+
+    // Score of the first bot, counting in half points
+    let mut half_points = 0;
+    let mut games = 0;
+    for pairing in suite.pairings() {
+        let first = Box::new(Bot1Player::new(first_depth, MAX_NODES).with_params(first_params.clone()));
+        let second = Box::new(Bot1Player::new(second_depth, MAX_NODES).with_params(second_params.clone()));
+        let (white, black) = match pairing.first_plays {
+            Colour::White => (first, second),
+            Colour::Black => (second, first),
+        };
+
+        let mut runner = GameRunner::new(pairing.opening.game.clone(), white, black);
+        let Step::Ended(ending) = runner.run(|_, _| ()) else {
+            unreachable!("bots don't interrupt")
+        };
+
+        half_points += match ending {
+            Ending::Checkmate { winner } | Ending::Resignation { winner } if winner == pairing.first_plays => 2,
+            Ending::Checkmate { .. } | Ending::Resignation { .. } => 0,
+            Ending::Stalemate | Ending::DrawClaimed | Ending::DrawByAgreement => 1,
+        };
+        games += 1;
+        println!("{} (depth {first_depth} as {:?}): {ending}", pairing.opening.name, pairing.first_plays);
+    }
+
+    println!("Depth {first_depth} scored {}/{games} against depth {second_depth}", half_points as f32 / 2.);
+}
+
+/// Reads evaluation weights from a TOML file, or gives the default ones if there's no file
+fn load_params(path: Option<String>) -> Option<EvalParams> {
+    let Some(path) = path else {
+        return Some(EvalParams::default());
+    };
+    match fs::read_to_string(&path).map(|s| EvalParams::from_toml(&s)) {
+        Ok(Ok(params)) => Some(params),
+        Ok(Err(e)) => {
+            eprintln!("Invalid parameters in {path}: {e}");
+            None
+        }
+        Err(e) => {
+            eprintln!("Could not read {path}: {e}");
+            None
+        }
+    }
+}
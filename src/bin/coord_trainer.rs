@@ -0,0 +1,47 @@
+use std::io::{stdin, stdout, Write};
+
+use talv::{location::{Coords, FileRange, RankRange}, trainer::CoordinateTrainer};
+
+fn main() {
+    let mut trainer = CoordinateTrainer::new();
+    let mut input = String::new();
+
+    println!("Name the marked square, or press enter to stop.");
+    loop {
+        print_board(trainer.target());
+        print!("Square: ");
+        stdout().flush().unwrap();
+
+        input.clear();
+        stdin().read_line(&mut input).unwrap();
+        if input.trim().is_empty() {
+            break;
+        }
+        let Some(answer) = Coords::from_str(input.trim()) else {
+            println!("That's not a square");
+            continue;
+        };
+
+        let target = trainer.target();
+        if trainer.answer(answer) {
+            println!("Right!");
+        } else {
+            println!("Wrong, it was {target}");
+        }
+    }
+
+    print!("{}/{} right", trainer.correct(), trainer.attempts().len());
+    if let Some(average) = trainer.average_time() {
+        print!(", {:.2} seconds on average", average.as_secs_f32());
+    }
+    println!();
+}
+
+fn print_board(marked: Coords) {
+    for r in RankRange::full().rev() {
+        for f in FileRange::full() {
+            print!("{} ", if Coords::new(f, r) == marked { 'X' } else { '.' });
+        }
+        println!();
+    }
+}
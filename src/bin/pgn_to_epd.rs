@@ -0,0 +1,125 @@
+use std::{
+    collections::HashSet,
+    env::args,
+    fs::File,
+    io::{stdin, stdout, BufRead, BufReader, BufWriter, Write},
+};
+
+use talv::{pgn::{PgnResult, PgnReader}, prelude::*};
+
+const USAGE: &str = "Usage: pgn_to_epd [--fen] [--result] [--moves] [--dedup] [--min-ply N] [--max-ply N] [games.pgn]
+
+Prints one EPD line per position in the games, reading from standard input if no file is given.
+  --fen        print full FEN strings (with clocks) instead of EPD positions
+  --result     add the result of the game as a c9 opcode
+  --moves      add the move played from the position as an sm opcode
+  --dedup      print every position only once
+  --min-ply N  skip positions before ply N
+  --max-ply N  skip positions after ply N";
+
+#[derive(Default)]
+struct Options {
+    fen: bool,
+    result: bool,
+    moves: bool,
+    dedup: bool,
+    min_ply: usize,
+    max_ply: Option<usize>,
+    path: Option<String>,
+}
+
+fn parse_options() -> Option<Options> {
+    let mut options = Options::default();
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--fen" => options.fen = true,
+            "--result" => options.result = true,
+            "--moves" => options.moves = true,
+            "--dedup" => options.dedup = true,
+            "--min-ply" => options.min_ply = args.next()?.parse().ok()?,
+            "--max-ply" => options.max_ply = Some(args.next()?.parse().ok()?),
+            s if s.starts_with("--") || options.path.is_some() => return None,
+            _ => options.path = Some(arg),
+        }
+    }
+    Some(options)
+}
+
+fn main() {
+    let Some(options) = parse_options() else {
+        eprintln!("{USAGE}");
+        return;
+    };
+    let input: Box<dyn BufRead> = match &options.path {
+        Some(path) => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("Could not open {path}: {e}");
+                return;
+            }
+        },
+        None => Box::new(stdin().lock()),
+    };
+    let mut out = BufWriter::new(stdout().lock());
+    let mut seen = HashSet::new();
+
+    for (i, pgn) in PgnReader::new(input).enumerate() {
+        let pgn = match pgn {
+            Ok(pgn) => pgn,
+            Err(e) => {
+                eprintln!("Could not read game {}: {e}", i + 1);
+                return;
+            }
+        };
+        let start = match pgn.tag("FEN") {
+            Some(fen) => Game::from_fen(fen),
+            None => Some(Game::new()),
+        };
+        let Some(mut game) = start else {
+            eprintln!("Game {} has an invalid FEN tag, skipping it", i + 1);
+            continue;
+        };
+        let result = pgn.result.or_else(|| pgn.tag("Result").and_then(PgnResult::from_str)).unwrap_or(PgnResult::Unknown);
+
+        for ply in 0..=pgn.moves.len() {
+            // The move played from this position, if any
+            let mv = match pgn.moves.get(ply) {
+                Some(san) => match AlgebraicMove::from_str(san).ok().and_then(|mv| game.check_move(mv)) {
+                    Some(mv) => Some(mv),
+                    None => {
+                        eprintln!("Game {} has an illegal move {san} at ply {ply}, skipping the rest of it", i + 1);
+                        break;
+                    }
+                },
+                None => None,
+            };
+
+            let in_range = ply >= options.min_ply && options.max_ply.is_none_or(|max| ply <= max);
+            if in_range && (!options.dedup || seen.insert(*game.board_state())) {
+                let mut line = if options.fen {
+                    game.display_fen().to_string()
+                } else {
+                    game.board_state().display_fen().to_string()
+                };
+                if let (true, Some(mv)) = (options.moves, mv) {
+                    let san = game.san(mv.from, mv.unto, mv.promotion).expect("move was checked");
+                    line.push_str(&format!(" sm {san};"));
+                }
+                if options.result {
+                    line.push_str(&format!(" c9 \"{result}\";"));
+                }
+                if writeln!(out, "{line}").is_err() {
+                    // Most likely the output was closed early, e.g. by `head`
+                    return;
+                }
+            }
+
+            let Some(mv) = mv else { break };
+            if let Err(e) = game.make_move(mv.from, mv.unto, mv.promotion) {
+                eprintln!("Game {} has an illegal move {} at ply {ply} ({e}), skipping the rest of it", i + 1, pgn.moves[ply]);
+                break;
+            }
+        }
+    }
+}
@@ -1,97 +1,110 @@
-use std::io::{stdin, stdout, Write};
+use std::{env::args, io::stdin};
 
-use talv::{algebraic::Move, board::Colour, bots::bot1, game::Game, movegen::{any_legal_moves, get_all_moves}};
+use talv::{archive::Archive, config::Config, game::{Clk, TimeControl}, player::{Bot1Player, ConsolePlayer, GameRunner, MoveInfo, Step}, prelude::*};
 
 fn main() {
-    let mut game;
-
+    // Only kept track of for the clocks in the saved game, nobody loses on time
+    let time_control = match args().nth(1) {
+        Some(arg) => match TimeControl::from_str(&arg) {
+            Some(time_control) => Some(time_control),
+            None => {
+                eprintln!("Invalid time control {arg}, expected seconds like 300+5");
+                return;
+            }
+        },
+        None => None,
+    };
     let mut input = String::new();
 
     println!("Input position (FEN) or press enter for new game:");
     stdin().read_line(&mut input).unwrap();
-    if input.trim().is_empty() {
-        game = Game::new();
+    let mut game = if input.trim().is_empty() {
+        Game::new()
     } else {
-        game = match Game::from_fen(input.trim()) {
+        match Game::from_fen(input.trim()) {
             Some(game) => game,
             None => {
                 eprintln!("Invalid FEN string");
                 return;
             }
         }
-    }
-    input.clear();
+    };
 
-    loop {
-        game.print_game();
-        if game.is_checked(game.side_to_move()) {
-            println!("Check! ");
-            if !any_legal_moves(game.board_state()) {
-                println!("Mate! {:?} won.", !game.side_to_move());
-                break;
-            }
-        }
+    game.set_time_control(time_control);
 
-        if game.draw_claimable() {
-            println!("Draw");
-            break;
-        }
-
-        match game.side_to_move() {
-            Colour::Black => {
-                let (e, moves) = bot1::get_moves_ranked(game.board_state(), 6, usize::MAX);
-                println!("Eval: {e}");
-                print!("Ranked moves: ");
-                for (from, to, p) in &moves {
-                    print!("{from}{to}");
-                    if let Some(p) = p {
-                        print!("={p}");
-                    }
-                    print!(" ");
-                }
-                println!();
-                let (from, unto, pr) = moves[0];
-                game.make_move(from, unto, pr).then_some(()).unwrap();
+    let mut bot = Bot1Player::new(6, usize::MAX);
+    match Config::load().map(|config| config.book.load()) {
+        Ok(Ok(Some(book))) => bot = bot.with_book(book),
+        Ok(Ok(None)) => (),
+        Ok(Err(e)) => eprintln!("The bot won't use an opening book, {e}"),
+        Err(e) => eprintln!("The bot won't use an opening book, {e}"),
+    }
+    let mut runner = GameRunner::new(game, Box::<ConsolePlayer>::default(), Box::new(bot));
+    print_position(runner.game());
+    let last_step = runner.run(|runner, step| match step {
+        Step::Moved(outcome) => {
+            let game = runner.game();
+            if let Some(info) = runner.player(outcome.mover).last_move_info() {
+                print_move_info(game, &info);
             }
-            Colour::White => {
-                print!("Possible moves: ");
-                for (from, to, prm) in get_all_moves(game.board_state()) {
-                    let p = game.board_state().get(from);
-                    print!("{p}{from}{to}");
-                    if let Some(p) = prm {
-                        print!("={p}");
-                    }
-                    print!(" ");
-                }
-                println!();
-                print!("Move: ");
-                stdout().flush().unwrap();
-
-                stdin().read_line(&mut input).unwrap();
-
-                if input.trim().is_empty() {
-                    break;
-                }
+            if let Some(emt) = outcome.display_emt() {
+                println!("Thought for {emt}");
+            }
+            if let Some(clock) = game.current_ply().checked_sub(1).and_then(|ply| game.clock(ply)) {
+                println!("Time left {}", Clk(clock));
+            }
+            print_position(game);
+        }
+        Step::Illegal { error, .. } => println!("Illegal!! {error}"),
+        Step::Ended(ending) => println!("{ending}"),
+        Step::Waiting | Step::Interrupted => (),
+    });
 
-                if let Some(mv) = Move::from_str(input.trim()) {
-                    println!("Valid {}", mv);
+    if let Step::Ended(_) = last_step {
+        save_game(&runner);
+    }
+    if last_step == Step::Interrupted {
+        println!(
+            "Game was interrupted. Use the following FEN line to continue the game later:\n{}",
+            runner.game().display_fen()
+        );
+    }
+}
 
-                    if let Some((f, t, prm)) = game.check_move(mv) {
-                        if !game.make_move(f, t, prm) {
-                            println!("Illegal!!");
-                        }
-                    } else {
-                        println!("Incorrect {}", mv);
-                    }
-                }
+fn save_game(runner: &GameRunner) {
+    let archive = match Config::load() {
+        Ok(config) => Archive::from_config(&config.archive),
+        Err(e) => {
+            eprintln!("The game won't be saved, {e}");
+            return;
+        }
+    };
+    if let Some(archive) = archive {
+        match archive.append("Casual game", runner) {
+            Ok(()) => println!("Saved the game to {}", archive.path().display()),
+            Err(e) => eprintln!("Could not save the game to {}: {e}", archive.path().display()),
+        }
+    }
+}
 
-                input.clear();
-            }
+/// Prints what the bot found when choosing the last move of `game`
+fn print_move_info(game: &Game, info: &MoveInfo) {
+    match info {
+        MoveInfo::Book => println!("Book move"),
+        MoveInfo::Search { eval, pv } => {
+            // The line starts from the position before the move
+            let mut before = game.clone();
+            before.seek(game.current_ply().saturating_sub(1));
+            let line: Vec<_> = before.san_line(pv).iter().map(|san| san.to_string()).collect();
+            println!("Eval: {eval}, line: {}", line.join(" "));
         }
+        MoveInfo::Playouts { visits, score } => println!("Visits: {visits}, expected score: {score:.2}"),
     }
+}
 
-    println!(
-        "Game was interrupted. Use the following FEN line to continue the game later:\n{}",
-        game.display_fen()
-    );
+fn print_position(game: &Game) {
+    game.print_game();
+    if game.is_checked(game.side_to_move()) {
+        println!("Check! ");
+    }
 }
@@ -1,72 +1,96 @@
-use std::io::{stdin, stdout, Write};
+use std::{env::args, fs, io::stdin};
 
-use talv::{algebraic::Move, game::Game, movegen::get_all_moves};
+use talv::{archive::Archive, config::Config, openings::OpeningSuite, player::{ConsolePlayer, GameRunner, Step}, prelude::*};
 
 fn main() {
-    let mut game;
-
+    if let Some(path) = args().nth(1) {
+        play_suite(&path);
+        return;
+    }
     let mut input = String::new();
 
     println!("Input position (FEN) or press enter for new game:");
     stdin().read_line(&mut input).unwrap();
-    if input.trim().is_empty() {
-        game = Game::new();
+    let game = if input.trim().is_empty() {
+        Game::new()
     } else {
-        game = match Game::from_fen(input.trim()) {
+        match Game::from_fen(input.trim()) {
             Some(game) => game,
             None => {
                 eprintln!("Invalid FEN string");
                 return;
             }
         }
-    }
-    input.clear();
+    };
 
-    loop {
-        game.print_game();
-        if game.is_checked(game.side_to_move()) {
-            println!("Check! ");
-        }
-        if game.is_checked(!game.side_to_move()) {
-            println!("Illegal check! ");
+    play(game, "Casual game");
+}
+
+/// Plays every opening of the suite once, there's no point in swapping colours when the same people play both sides
+fn play_suite(path: &str) {
+    let suite = match fs::read_to_string(path).map(|s| OpeningSuite::parse(&s)) {
+        Ok(Ok(suite)) => suite,
+        Ok(Err(e)) => {
+            eprintln!("{e}");
+            return;
         }
-        print!("Possible moves: ");
-        for (from, to, prm) in get_all_moves(game.board_state()) {
-            let p = game.board_state().get(from).into_piece().unwrap();
-            print!("{p}{from}{to}");
-            if let Some(p) = prm {
-                print!("={p}");
-            }
-            print!(" ");
+        Err(e) => {
+            eprintln!("Could not read {path}: {e}");
+            return;
         }
-        println!();
-        print!("Move: ");
-        stdout().flush().unwrap();
-
-        stdin().read_line(&mut input).unwrap();
-
-        if input.trim().is_empty() {
-            break;
+    };
+    for opening in suite.openings() {
+        println!("{}", opening.name);
+        if play(opening.game.clone(), &opening.name) == Step::Interrupted {
+            return;
         }
+    }
+}
 
-        let mv = Move::from_str(input.trim());
+fn play(game: Game, event: &str) -> Step {
+    let mut runner = GameRunner::new(game, Box::<ConsolePlayer>::default(), Box::<ConsolePlayer>::default());
+    print_position(runner.game());
+    let last_step = runner.run(|runner, step| match step {
+        Step::Moved(_) => print_position(runner.game()),
+        Step::Illegal { error, .. } => println!("Illegal!! {error}"),
+        Step::Ended(ending) => println!("{ending}"),
+        Step::Waiting | Step::Interrupted => (),
+    });
 
-        if let Some(mv) = mv {
-            println!("Valid {}", mv);
+    if let Step::Ended(_) = last_step {
+        save_game(&runner, event);
+    }
+    if last_step == Step::Interrupted {
+        println!(
+            "Game was interrupted. Use the following FEN line to continue the game later:\n{}",
+            runner.game().display_fen()
+        );
+    }
+    last_step
+}
 
-            if let Some((f, t, prm)) = game.check_move(mv) {
-                if !game.make_move(f, t, prm) {
-                    println!("Illegal!!");
-                }
-            } else {
-                println!("Incorrect {}", mv);
-            }
+fn save_game(runner: &GameRunner, event: &str) {
+    let archive = match Config::load() {
+        Ok(config) => Archive::from_config(&config.archive),
+        Err(e) => {
+            eprintln!("The game won't be saved, {e}");
+            return;
         }
+    };
+    if let Some(archive) = archive {
+        match archive.append(event, runner) {
+            Ok(()) => println!("Saved the game to {}", archive.path().display()),
+            Err(e) => eprintln!("Could not save the game to {}: {e}", archive.path().display()),
+        }
+    }
+}
 
-        input.clear();
+fn print_position(game: &Game) {
+    game.print_game();
+    if game.is_checked(game.side_to_move()) {
+        println!("Check! ");
+    }
+    if game.is_checked(!game.side_to_move()) {
+        println!("Illegal check! ");
     }
-    println!(
-        "Game was interrupted. Use the following FEN line to continue the game later:\n{}",
-        game.display_fen()
-    );
 }
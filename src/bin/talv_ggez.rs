@@ -1,19 +1,89 @@
-use std::{env, path::PathBuf};
+use std::{env, mem, path::PathBuf};
 
+use analysis::{Analysis, Review};
 use ggez::{
-    conf::{WindowMode, WindowSetup}, event::{EventHandler, MouseButton}, graphics::{self, Canvas, Color, DrawMode, DrawParam, Image, Mesh, Rect}, Context, ContextBuilder, GameError
+    conf::{WindowMode, WindowSetup}, event::{EventHandler, MouseButton}, glam::Vec2, graphics::{self, Canvas, Color, DrawMode, DrawParam, Image, Mesh, MeshBuilder, Rect, Text}, input::keyboard::{KeyCode, KeyInput}, Context, ContextBuilder, GameError
 };
-use player::{Bot1, HumanPlayer, Player};
-use talv::{board::{Colour, Field, Piece}, game::Game, location::{Coords, File, FileRange, Rank, RankRange}, movegen::any_legal_moves};
+use talv::{archive::Archive, board::{Colour, Field, Piece}, bots::{baseline::{GreedyCaptureBot, RandomBot}, bot1::{CurrMove, OpeningBook, Score, Skill}}, config::Config, game::Game, location::{Coords, File, FileRange, Rank, RankRange}, movegen::Move, player::{Bot1Player, Bot2Player, Ending, GameRunner, HumanPlayer, Player, Step, UciEnginePlayer}, trainer::CoordinateTrainer};
 
 const FIELD_SIZE: f32 = 60.;
+/// Height of the status bar under the board
+const STATUS_HEIGHT: f32 = 30.;
 const TRANSPARENT: Color = Color {
     a: 0.5,
     .. Color::WHITE
 };
+/// Width of the analysis arrow for the best move
+const ARROW_MAX_WIDTH: f32 = 0.2 * FIELD_SIZE;
+/// Width of the analysis arrow for a move that loses a lot compared to the best one
+const ARROW_MIN_WIDTH: f32 = 0.05 * FIELD_SIZE;
+/// Number of analysis arrows shown when showing the top moves
+const TOP_MOVES: usize = 3;
+const ARROW_COLOUR: Color = Color {
+    r: 0.1,
+    g: 0.6,
+    b: 0.2,
+    a: 0.7,
+};
+/// Width of the move list to the right of the board
+const MOVE_LIST_WIDTH: f32 = 3. * FIELD_SIZE;
+/// Height of a row in the move list
+const MOVE_ROW_HEIGHT: f32 = 18.;
+/// The moves in the move list other than the one the shown position came from
+const MOVE_LIST_COLOUR: Color = Color {
+    r: 0.6,
+    g: 0.6,
+    b: 0.6,
+    a: 1.,
+};
+/// Height of the evaluation graph shown under the status bar after the game
+const GRAPH_HEIGHT: f32 = 80.;
+/// Evaluations beyond this many pawns are drawn at the edge of the graph
+const GRAPH_RANGE: f32 = 5.;
+const GRAPH_BACKGROUND: Color = Color {
+    r: 0.15,
+    g: 0.15,
+    b: 0.15,
+    a: 1.,
+};
+/// Marks the square asked for in the coordinate trainer after a right answer
+const RIGHT_COLOUR: Color = Color {
+    r: 0.1,
+    g: 0.7,
+    b: 0.2,
+    a: 0.5,
+};
+/// Marks the square asked for in the coordinate trainer after a wrong answer
+const WRONG_COLOUR: Color = Color {
+    r: 0.8,
+    g: 0.1,
+    b: 0.1,
+    a: 0.5,
+};
+/// Behind the text saying how the game ended
+const OVERLAY_COLOUR: Color = Color {
+    r: 0.,
+    g: 0.,
+    b: 0.,
+    a: 0.7,
+};
+/// Marks the squares the picked up piece can move to
+const TARGET_COLOUR: Color = Color {
+    r: 0.2,
+    g: 0.4,
+    b: 0.8,
+    a: 0.4,
+};
+/// Laid over the board when it's not a human's turn
+const LOCKED_COLOUR: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 0.3,
+};
 
-#[path = "talv_ggez/player.rs"]
-mod player;
+#[path = "talv_ggez/analysis.rs"]
+mod analysis;
 
 fn main() {
     let mut b = ContextBuilder::new("talv", "Falch");
@@ -25,7 +95,7 @@ fn main() {
     }
 
     let (mut ctx, event_loop) = b
-        .window_mode(WindowMode::default().dimensions(8. * FIELD_SIZE, 8. * FIELD_SIZE))
+        .window_mode(WindowMode::default().dimensions(8. * FIELD_SIZE + MOVE_LIST_WIDTH, 8. * FIELD_SIZE + STATUS_HEIGHT + GRAPH_HEIGHT))
         .window_setup(WindowSetup::default().title("talv"))
         .build()
         .unwrap();
@@ -42,48 +112,184 @@ fn main() {
     ggez::event::run(ctx, event_loop, game_state)
 }
 
+fn load_archive() -> Option<Archive> {
+    match Config::load() {
+        Ok(config) => Archive::from_config(&config.archive),
+        Err(e) => {
+            eprintln!("Games won't be saved, {e}");
+            None
+        }
+    }
+}
+
+fn load_book() -> Option<OpeningBook> {
+    let config = Config::load().ok()?;
+    match config.book.load() {
+        Ok(book) => book,
+        Err(e) => {
+            eprintln!("The bot won't use an opening book, {e}");
+            None
+        }
+    }
+}
+
 fn parse_player(s: &str) -> Box<dyn Player> {
+    // `1:5` is bot 1 at skill level 5
+    if let Some(level) = s.strip_prefix("1:").and_then(|level| level.parse().ok()) {
+        let bot = Bot1Player::default().with_skill(Skill::new(level));
+        return match load_book() {
+            Some(book) => Box::new(bot.with_book(book)),
+            None => Box::new(bot),
+        };
+    }
+    if let Some(path) = s.strip_prefix("uci:") {
+        return match UciEnginePlayer::spawn(path) {
+            Ok(engine) => Box::new(engine),
+            Err(e) => {
+                eprintln!("Could not start the engine {path}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
     match s {
-        "1" => Box::new(Bot1::new()),
+        "1" => match load_book() {
+            Some(book) => Box::new(Bot1Player::default().with_book(book)),
+            None => Box::<Bot1Player>::default(),
+        },
+        "2" => Box::<Bot2Player>::default(),
+        "random" => Box::<RandomBot>::default(),
+        "greedy" => Box::<GreedyCaptureBot>::default(),
         "-" => Box::new(HumanPlayer::default()),
         _ => unimplemented!(),
     }
 }
 
 struct GameState {
-    chess_game: Game,
+    runner: GameRunner,
+    /// The position the game started from, to go back through the history with
+    start: Game,
+    /// A position from the history being looked at instead of the current one, and its ply
+    viewed: Option<(usize, Game)>,
+    review: Option<Review>,
+    /// Half points of the players that started as white and black respectively
+    score: [u32; 2],
+    /// Whether the player that started as white plays white in the current game
+    first_is_white: bool,
+    /// Set while the game is paused for the coordinate trainer
+    trainer: Option<CoordinateTrainer>,
+    /// Where finished games are saved, if anywhere
+    archive: Option<Archive>,
     board_image: Image,
     pieces_image: Image,
     recent_mesh: Mesh,
     recent_move: Option<(Coords, Coords)>,
-    black_player: Box<dyn Player>,
-    white_player: Box<dyn Player>,
+    analysis_mode: bool,
+    show_top_moves: bool,
+    analysis: Option<Analysis>,
 }
 
 impl GameState {
     fn new(ctx: &mut Context, fen: Option<&str>, white_player: Box<dyn Player>, black_player: Box<dyn Player>) -> Result<Self, GameError> {
+        let game = fen.and_then(Game::from_fen).unwrap_or_default();
         Ok(GameState {
             board_image: Image::from_path(ctx, "/board.png")?,
             pieces_image: Image::from_path(ctx, "/pieces.png")?,
             recent_mesh: Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 0., FIELD_SIZE, FIELD_SIZE), Color::from_rgba_u32(0xfce2057f))?,
-            chess_game: fen.and_then(|s| Game::from_fen(s)).unwrap_or_else(Game::new),
+            start: game.clone(),
+            viewed: None,
+            review: None,
+            score: [0; 2],
+            first_is_white: true,
+            trainer: None,
+            archive: load_archive(),
+            runner: GameRunner::new(game, white_player, black_player),
             recent_move: None,
-            white_player,
-            black_player,
+            analysis_mode: false,
+            show_top_moves: false,
+            analysis: None,
         })
     }
 
-    fn get_player(&self) -> &dyn Player {
-        match self.chess_game.side_to_move() {
-            Colour::White => &*self.white_player,
-            Colour::Black => &*self.black_player,
+    fn chess_game(&self) -> &Game {
+        self.runner.game()
+    }
+    /// The game as shown on the board, which is in the past if a position from the history is being viewed
+    fn shown_game(&self) -> &Game {
+        match &self.viewed {
+            Some((_, game)) => game,
+            None => self.chess_game(),
         }
     }
-    fn get_player_mut(&mut self) -> &mut dyn Player {
-        match self.chess_game.side_to_move() {
-            Colour::White => &mut *self.white_player,
-            Colour::Black => &mut *self.black_player,
+    fn shown_ply(&self) -> usize {
+        match &self.viewed {
+            Some((ply, _)) => *ply,
+            None => self.chess_game().history().len(),
+        }
+    }
+    /// Every position of the game so far, starting with the one it started from
+    fn positions(&self) -> Vec<Game> {
+        let mut game = self.start.clone();
+        let mut positions = vec![game.clone()];
+        for entry in self.chess_game().history() {
+            game.make_history_move(entry).expect("history only has legal moves");
+            positions.push(game.clone());
+        }
+        positions
+    }
+    /// Shows the position after `ply` half-moves, or the current one if it is that far
+    fn view(&mut self, ply: usize) {
+        let history = self.chess_game().history();
+        if ply >= history.len() {
+            self.viewed = None;
+            return;
+        }
+        let mut game = self.start.clone();
+        for entry in &history[..ply] {
+            game.make_history_move(entry).expect("history only has legal moves");
         }
+        self.viewed = Some((ply, game));
+    }
+    /// The window title, which shows the match score once a game has finished
+    fn title(&self) -> String {
+        match self.score {
+            [0, 0] => "talv".to_owned(),
+            [first, second] => format!("talv ({} - {})", first as f32 / 2., second as f32 / 2.),
+        }
+    }
+    fn record_result(&mut self, ending: Ending) {
+        let first = if self.first_is_white { Colour::White } else { Colour::Black };
+        match ending {
+            Ending::Checkmate { winner } | Ending::Resignation { winner } if winner == first => self.score[0] += 2,
+            Ending::Checkmate { .. } | Ending::Resignation { .. } => self.score[1] += 2,
+            Ending::Stalemate | Ending::DrawClaimed | Ending::DrawByAgreement => {
+                self.score[0] += 1;
+                self.score[1] += 1;
+            }
+        }
+    }
+    /// Starts a new game from the same position with the colours swapped
+    fn rematch(&mut self) {
+        let placeholder = GameRunner::new(Game::new(), Box::<HumanPlayer>::default(), Box::<HumanPlayer>::default());
+        let (mut white, mut black) = mem::replace(&mut self.runner, placeholder).into_players();
+        white.new_game();
+        black.new_game();
+        self.runner = GameRunner::new(self.start.clone(), black, white);
+        self.first_is_white = !self.first_is_white;
+        self.viewed = None;
+        self.review = None;
+        self.recent_move = None;
+        self.analysis = None;
+    }
+    /// The side played by a human, preferring the side to move if both are
+    fn human_side(&self) -> Option<Colour> {
+        let side = self.chess_game().side_to_move();
+        [side, !side].into_iter().find(|&c| self.runner.player(c).is_human())
+    }
+    fn get_player(&self) -> &dyn Player {
+        self.runner.current_player()
+    }
+    fn get_player_mut(&mut self) -> &mut dyn Player {
+        self.runner.current_player_mut()
     }
 }
 
@@ -94,7 +300,75 @@ fn xy_to_coords(x: f32, y: f32) -> Option<Coords> {
     Some(Coords::new(f, r))
 }
 
+#[inline]
+fn coords_to_centre(coords: Coords) -> Vec2 {
+    let (x, y) = coords.i8_tuple();
+    Vec2::new((x as f32 + 0.5) * FIELD_SIZE, (7.5 - y as f32) * FIELD_SIZE)
+}
+
+/// Arrow width for a move evaluated at `eval` when the best move is evaluated at `best`
+///
+/// The best move gets the widest arrow and the arrow gets thinner the more the move loses.
+fn arrow_width(best: Score, eval: Score) -> f32 {
+    let loss = (best.pawns() - eval.pawns()).max(0.);
+    ARROW_MIN_WIDTH + (ARROW_MAX_WIDTH - ARROW_MIN_WIDTH) / (1. + 2. * loss)
+}
+fn arrow_mesh(ctx: &Context, from: Coords, unto: Coords, width: f32) -> Result<Mesh, GameError> {
+    let start = coords_to_centre(from);
+    let end = coords_to_centre(unto);
+    let direction = (end - start).normalize();
+    let normal = direction.perp();
+    let head_base = end - 2. * width * direction;
+
+    let mut mb = MeshBuilder::new();
+    mb.line(&[start, head_base], width, ARROW_COLOUR)?
+        .polygon(
+            DrawMode::fill(),
+            &[end, head_base + width * normal, head_base - width * normal],
+            ARROW_COLOUR,
+        )?;
+    Ok(Mesh::from_data(ctx, mb.build()))
+}
+
 impl EventHandler for GameState {
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> Result<(), GameError> {
+        match input.keycode {
+            Some(KeyCode::A) => {
+                self.analysis_mode = !self.analysis_mode;
+                if !self.analysis_mode {
+                    self.analysis = None;
+                    ctx.gfx.set_window_title(&self.title());
+                }
+            }
+            Some(KeyCode::M) => self.show_top_moves = !self.show_top_moves,
+            Some(KeyCode::C) => println!("{}", self.chess_game().move_list().with_width(80)),
+            Some(KeyCode::N) if self.runner.ending().is_some() => self.rematch(),
+            Some(KeyCode::T) => {
+                self.trainer = match self.trainer {
+                    Some(_) => None,
+                    None => Some(CoordinateTrainer::new()),
+                };
+            }
+            Some(KeyCode::R) => {
+                if let Some(side) = self.human_side() {
+                    self.runner.resign(side);
+                }
+            }
+            Some(KeyCode::D) => {
+                if let Some(side) = self.human_side() {
+                    if !self.runner.offer_draw(side) && self.runner.ending().is_none() {
+                        println!("{side:?} offers a draw");
+                    }
+                }
+            }
+            Some(KeyCode::Left) if self.review.is_some() => self.view(self.shown_ply().saturating_sub(1)),
+            Some(KeyCode::Right) if self.review.is_some() => self.view(self.shown_ply() + 1),
+            Some(KeyCode::Escape) => ctx.request_quit(),
+            _ => (),
+        }
+
+        Ok(())
+    }
     fn mouse_button_down_event(
             &mut self,
             _ctx: &mut Context,
@@ -105,9 +379,27 @@ impl EventHandler for GameState {
         if btn != MouseButton::Left {
             return Ok(());
         }
+        if let Some(trainer) = &mut self.trainer {
+            if let Some(coords) = xy_to_coords(x, y) {
+                trainer.answer(coords);
+            }
+            return Ok(());
+        }
+        if let Some(review) = &self.review {
+            // Jump to the position clicked on in the graph
+            if y >= 8. * FIELD_SIZE + STATUS_HEIGHT {
+                let last = review.positions().saturating_sub(1);
+                let ply = (x / (8. * FIELD_SIZE) * last as f32).round() as usize;
+                self.view(ply.min(last));
+            }
+            return Ok(());
+        }
+        if !self.get_player().is_human() {
+            return Ok(());
+        }
         let Some(coords) = xy_to_coords(x, y) else { return Ok(()) };
         // FIXME
-        let bs = self.chess_game.board_state().clone();
+        let bs = *self.chess_game().board_state();
         self.get_player_mut().start_interaction(&bs, coords);
 
         Ok(())
@@ -119,47 +411,105 @@ impl EventHandler for GameState {
             x: f32,
             y: f32,
         ) -> Result<(), GameError> {
-        if btn != MouseButton::Left {
+        if btn != MouseButton::Left || self.trainer.is_some() {
+            return Ok(());
+        }
+        if !self.get_player().is_human() {
             return Ok(());
         }
         let Some(coords) = xy_to_coords(x, y) else { return Ok(()) };
         // FIXME
-        let bs = self.chess_game.board_state().clone();
+        let bs = *self.chess_game().board_state();
         self.get_player_mut().end_interaction(&bs, coords);
 
         Ok(())
     }
 
     fn update(&mut self, ctx: &mut Context) -> Result<(), GameError> {
-        let no_moves = !any_legal_moves(self.chess_game.board_state());
-        if self.chess_game.is_checked(self.chess_game.side_to_move()) && no_moves {
-            println!("Check-mate! {:?} wins.", !self.chess_game.side_to_move());
-            ctx.request_quit();
+        // The game waits while training
+        if self.trainer.is_some() {
             return Ok(());
         }
-        if self.chess_game.draw_claimable() || no_moves {
-            println!("Draw");
-            ctx.request_quit();
-            return Ok(());
+        match self.runner.step() {
+            Step::Moved(outcome) => {
+                let mv = outcome.mv;
+                self.recent_move = Some((mv.from, mv.unto));
+            }
+            Step::Ended(ending) => {
+                if self.review.is_none() {
+                    println!("{ending}");
+                    self.record_result(ending);
+                    if let Some(archive) = &self.archive {
+                        if let Err(e) = archive.append("Casual game", &self.runner) {
+                            eprintln!("Could not save the game to {}: {e}", archive.path().display());
+                        }
+                    }
+                    ctx.gfx.set_window_title(&self.title());
+                    self.review = Some(Review::start(self.positions()));
+                }
+            }
+            Step::Waiting | Step::Illegal { .. } | Step::Interrupted => (),
         }
 
-        // FIXME
-        let bs = self.chess_game.board_state().clone();
-        if let Some((from, unto, promotion)) = self.get_player_mut().make_move(&bs) {
-            if self.chess_game.make_move(from, unto, promotion) {
-                self.recent_move = Some((from, unto));
+        if let Some(review) = &mut self.review {
+            review.poll();
+        }
+
+        if self.analysis_mode {
+            let bs = *self.shown_game().board_state();
+            match &mut self.analysis {
+                Some(analysis) if *analysis.state() == bs => analysis.poll(),
+                analysis => *analysis = Some(Analysis::start(bs)),
+            }
+            if let Some(analysis) = &self.analysis {
+                let mut title = self.title();
+                if let (Some(depth), Some(eval), Some(tt)) = (analysis.depth(), analysis.eval(), analysis.tt_stats()) {
+                    let [this, previous, older] = tt.ages;
+                    title.push_str(&format!(
+                        " - depth {depth}: {eval} (hash {}%, {} hits, {} misses, {} overwrites, ages {this}/{previous}/{older}",
+                        tt.hashfull() / 10,
+                        tt.hits,
+                        tt.misses,
+                        tt.overwrites
+                    ));
+                    if let Some(nps) = analysis.nps() {
+                        title.push_str(&format!(", {} kn/s", nps / 1000));
+                    }
+                    title.push(')');
+                }
+                let line = self.shown_game().san_line(analysis.pv());
+                if !line.is_empty() {
+                    let line: Vec<_> = line.iter().map(|san| san.to_string()).collect();
+                    title.push_str(&format!(" {}", line.join(" ")));
+                }
+                if let Some(CurrMove { depth, mv, number, total }) = analysis.current() {
+                    title.push_str(&format!(" - searching depth {depth} {}{} ({number}/{total})", mv.from, mv.unto));
+                }
+                ctx.gfx.set_window_title(&title);
             }
         }
 
         Ok(())
     }
     fn draw(&mut self, ctx: &mut Context) -> Result<(), GameError> {
-        let mut canvas = graphics::Canvas::from_frame(ctx, None);
+        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
         // Draw board background
         canvas.draw(&self.board_image, DrawParam::new());
 
+        if let Some(trainer) = &self.trainer {
+            draw_trainer(ctx, &mut canvas, trainer)?;
+            return canvas.finish(ctx);
+        }
+
         // Draw last move
-        if let Some((f, t)) = self.recent_move {
+        let recent_move = match self.viewed {
+            Some((ply, _)) => ply.checked_sub(1).map(|i| {
+                let entry = self.chess_game().history()[i];
+                (entry.from, entry.unto)
+            }),
+            None => self.recent_move,
+        };
+        if let Some((f, t)) = recent_move {
             for coords in [f, t] {
                 let (x, y) = coords.i8_tuple();
                 let x = x as f32 * FIELD_SIZE;
@@ -169,31 +519,170 @@ impl EventHandler for GameState {
             }
         }
 
+        // Draw where the picked up piece can go
+        if let Some((_, from)) = self.get_player().get_interaction() {
+            for Move { unto, .. } in self.shown_game().board_state().legal_moves_from(from) {
+                let (x, y) = unto.i8_tuple();
+                canvas.draw(
+                    &Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(x as f32 * FIELD_SIZE, (7 - y) as f32 * FIELD_SIZE, FIELD_SIZE, FIELD_SIZE), TARGET_COLOUR)?,
+                    DrawParam::new(),
+                );
+            }
+        }
+
         // Draw pieces
         for (r, y) in RankRange::full().rev().zip(0..) {
             for f in FileRange::full() {
                 let x = f.i8() as f32 * FIELD_SIZE;
                 let y = y as f32 * FIELD_SIZE;
-                match self.chess_game.board_state().get(Coords::new(f, r)) {
+                match self.shown_game().board_state().get(Coords::new(f, r)) {
                     Field::Empty => (),
                     Field::Occupied(c, p) => draw_piece(&mut canvas, &self.pieces_image, x, y, None, c, p),
                 }
             } 
         }
 
+        // Draw analysis arrows
+        if let Some(analysis) = &self.analysis {
+            let shown_moves = if self.show_top_moves { TOP_MOVES } else { 1 };
+            let lines = analysis.lines();
+            if let Some(best) = lines.first() {
+                // Draw the best move last so it ends up on top
+                for line in lines.iter().take(shown_moves).rev() {
+                    let Some(&Move { from: f, unto: t, .. }) = line.pv.first() else { continue };
+                    canvas.draw(&arrow_mesh(ctx, f, t, arrow_width(best.eval, line.eval))?, DrawParam::new());
+                }
+            }
+        }
+
+        // Lock the board while waiting for a non-human player
+        if !self.get_player().is_human() && self.runner.ending().is_none() {
+            canvas.draw(
+                &Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 0., 8. * FIELD_SIZE, 8. * FIELD_SIZE), LOCKED_COLOUR)?,
+                DrawParam::new(),
+            );
+        }
+
+        // Show how the game ended over the board
+        if let (Some(ending), None) = (self.runner.ending(), &self.viewed) {
+            canvas.draw(
+                &Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 3.5 * FIELD_SIZE, 8. * FIELD_SIZE, FIELD_SIZE), OVERLAY_COLOUR)?,
+                DrawParam::new(),
+            );
+            let mut text = Text::new(ending.to_string());
+            text.set_scale(0.4 * FIELD_SIZE);
+            let size = text.measure(ctx)?;
+            canvas.draw(&text, DrawParam::new().dest([4. * FIELD_SIZE - 0.5 * size.x, 4. * FIELD_SIZE - 0.5 * size.y]));
+        }
+
+        // Draw turn indicator
+        let mut status = match (self.runner.ending(), self.shown_game().side_to_move()) {
+            (Some(ending), _) if self.viewed.is_none() => format!("{ending} Press N for a rematch."),
+            (_, Colour::White) => "White to move".to_owned(),
+            (_, Colour::Black) => "Black to move".to_owned(),
+        };
+        if self.viewed.is_some() {
+            status.push_str(&format!(" (after {} half-moves)", self.shown_ply()));
+        } else if self.runner.ending().is_none() {
+            if let Some(side) = self.runner.draw_offer() {
+                status.push_str(&format!(" ({side:?} offers a draw)"));
+            } else if let Some(CurrMove { depth, number, total, .. }) = self.get_player().progress() {
+                status.push_str(&format!(" (thinking at depth {depth}, move {number}/{total})"));
+            } else if !self.get_player().is_human() {
+                status.push_str(" (thinking)");
+            }
+        }
+        canvas.draw(
+            Text::new(status).set_scale(0.6 * STATUS_HEIGHT),
+            DrawParam::new().dest([0.2 * STATUS_HEIGHT, 8. * FIELD_SIZE + 0.2 * STATUS_HEIGHT]),
+        );
+
+        if let Some(review) = &self.review {
+            draw_graph(ctx, &mut canvas, review, self.shown_ply())?;
+        }
+        draw_move_list(&mut canvas, self.chess_game(), self.shown_ply());
+
         // Draw moving piece
-        if let Some(p) = self.get_player().get_interaction() {
+        if let Some((p, _)) = self.get_player().get_interaction() {
             let pos = ctx.mouse.position();
             let x = pos.x - 0.5 * FIELD_SIZE;
             let y = pos.y - 0.5 * FIELD_SIZE;
 
-            draw_piece(&mut canvas, &self.pieces_image, x, y, Some(TRANSPARENT), self.chess_game.side_to_move(), p);
+            draw_piece(&mut canvas, &self.pieces_image, x, y, Some(TRANSPARENT), self.chess_game().side_to_move(), p);
         }
 
         canvas.finish(ctx)
     }
 }
 
+/// Draws an empty board with the last answer marked and the square to find in the status bar
+fn draw_trainer(ctx: &Context, canvas: &mut Canvas, trainer: &CoordinateTrainer) -> Result<(), GameError> {
+    if let Some(attempt) = trainer.attempts().last() {
+        let colour = if attempt.is_correct() { RIGHT_COLOUR } else { WRONG_COLOUR };
+        let (x, y) = attempt.target.i8_tuple();
+        let rect = Rect::new(x as f32 * FIELD_SIZE, (7 - y) as f32 * FIELD_SIZE, FIELD_SIZE, FIELD_SIZE);
+        canvas.draw(&Mesh::new_rectangle(ctx, DrawMode::fill(), rect, colour)?, DrawParam::new());
+    }
+
+    let mut status = format!("Click {} ({}/{} right", trainer.target(), trainer.correct(), trainer.attempts().len());
+    if let Some(average) = trainer.average_time() {
+        status.push_str(&format!(", {:.2}s on average", average.as_secs_f32()));
+    }
+    status.push(')');
+    canvas.draw(
+        Text::new(status).set_scale(0.6 * STATUS_HEIGHT),
+        DrawParam::new().dest([0.2 * STATUS_HEIGHT, 8. * FIELD_SIZE + 0.2 * STATUS_HEIGHT]),
+    );
+
+    Ok(())
+}
+
+/// Draws the moves of the game next to the board with how long each took, scrolled so the shown move is in view
+fn draw_move_list(canvas: &mut Canvas, game: &Game, shown_ply: usize) {
+    let start = game.start();
+    let black_first = start.side_to_move() == Colour::Black;
+    let rows = ((8. * FIELD_SIZE + STATUS_HEIGHT + GRAPH_HEIGHT) / MOVE_ROW_HEIGHT) as usize;
+    let move_list = game.move_list();
+    let moves = move_list.moves().iter().zip(game.history()).enumerate();
+    for (row, (i, (mv, entry))) in moves.skip(shown_ply.saturating_sub(rows)).take(rows).enumerate() {
+        let ply = i + black_first as usize;
+        let number = start.fullmove_number() + ply as u64 / 2;
+        let mut text = if ply.is_multiple_of(2) { format!("{number}. {mv}") } else { format!("{number}... {mv}") };
+        if let Some(think_time) = entry.think_time {
+            text.push_str(&format!(" ({:.1}s)", think_time.as_secs_f32()));
+        }
+        let colour = if i + 1 == shown_ply { Color::WHITE } else { MOVE_LIST_COLOUR };
+        canvas.draw(
+            Text::new(text).set_scale(0.8 * MOVE_ROW_HEIGHT),
+            DrawParam::new().dest([8. * FIELD_SIZE + 0.2 * STATUS_HEIGHT, row as f32 * MOVE_ROW_HEIGHT]).color(colour),
+        );
+    }
+}
+
+/// Draws the evaluations of a reviewed game with a marker at the shown position
+fn draw_graph(ctx: &Context, canvas: &mut Canvas, review: &Review, shown_ply: usize) -> Result<(), GameError> {
+    let top = 8. * FIELD_SIZE + STATUS_HEIGHT;
+    let width = 8. * FIELD_SIZE;
+    let last = review.positions().saturating_sub(1).max(1) as f32;
+    let ply_x = |ply: usize| ply as f32 / last * width;
+    let eval_y = |eval: Score| {
+        let eval = eval.pawns().clamp(-GRAPH_RANGE, GRAPH_RANGE);
+        top + 0.5 * GRAPH_HEIGHT * (1. - eval / GRAPH_RANGE)
+    };
+
+    let mut mb = MeshBuilder::new();
+    mb.rectangle(DrawMode::fill(), Rect::new(0., top, width, GRAPH_HEIGHT), GRAPH_BACKGROUND)?
+        .line(&[Vec2::new(0., eval_y(Score::ZERO)), Vec2::new(width, eval_y(Score::ZERO))], 1., Color::from_rgb(90, 90, 90))?
+        .line(&[Vec2::new(ply_x(shown_ply), top), Vec2::new(ply_x(shown_ply), top + GRAPH_HEIGHT)], 2., Color::from_rgb(252, 226, 5))?;
+    let points: Vec<_> = review.evals().iter().enumerate().map(|(ply, &eval)| Vec2::new(ply_x(ply), eval_y(eval))).collect();
+    if points.len() >= 2 {
+        mb.line(&points, 2., Color::WHITE)?;
+    }
+    canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::new());
+
+    Ok(())
+}
+
 fn draw_piece(canvas: &mut Canvas, pieces_image: &Image, x: f32, y: f32, color: Option<Color>, c: Colour, p: Piece) {
     const SIXTH: f32 = 1./6.;
 
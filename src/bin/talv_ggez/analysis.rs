@@ -0,0 +1,171 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, mpsc::{channel, Receiver}, Arc};
+
+use talv::{board::Colour, boardstate::BoardState, bots::bot1::{self, CurrMove, EvalParams, Move, RankedLine, Score, SearchEvent, SearchOptions, TtStats}, game::{Game, GameResult}};
+
+const ANALYSIS_DEPTH: usize = 8;
+const ANALYSIS_NODES: usize = 500_000;
+/// How many of the best moves get their own evaluation
+const ANALYSIS_LINES: usize = 3;
+
+/// Runs the engine on a position in the background and keeps the latest result
+pub struct Analysis {
+    state: BoardState,
+    updates: Receiver<Message>,
+    latest: Option<Update>,
+    current: Option<CurrMove>,
+    /// Stops the search once the analysis is dropped
+    stop: Arc<AtomicBool>,
+}
+
+enum Message {
+    Progress(CurrMove),
+    Update(Update),
+}
+
+struct Update {
+    depth: usize,
+    eval: Score,
+    tt_stats: TtStats,
+    nps: Option<u64>,
+    lines: Vec<RankedLine>,
+}
+
+impl Analysis {
+    pub fn start(state: BoardState) -> Self {
+        let (sender, updates) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let options = SearchOptions::new(ANALYSIS_DEPTH, ANALYSIS_NODES).with_threads(threads).with_multipv(ANALYSIS_LINES).with_stop(stop.clone());
+        std::thread::spawn(move || {
+            bot1::get_moves_ranked_with_info(&state, &options, &EvalParams::default(), |event| {
+                let message = match event {
+                    SearchEvent::CurrMove(curr_move) => Message::Progress(curr_move),
+                    SearchEvent::Iteration(info) => Message::Update(Update {
+                        depth: info.depth,
+                        eval: info.eval,
+                        tt_stats: info.tt_stats.clone(),
+                        nps: info.nps(),
+                        lines: info.lines.to_vec(),
+                    }),
+                };
+                // If the receiver is gone, the position changed and nobody cares about this anymore
+                let _ = sender.send(message);
+            });
+        });
+
+        Analysis {
+            state,
+            updates,
+            latest: None,
+            current: None,
+            stop,
+        }
+    }
+    pub fn state(&self) -> &BoardState {
+        &self.state
+    }
+    /// Takes in any new results from the search
+    pub fn poll(&mut self) {
+        while let Ok(message) = self.updates.try_recv() {
+            match message {
+                Message::Progress(curr_move) => self.current = Some(curr_move),
+                Message::Update(update) => self.latest = Some(update),
+            }
+        }
+    }
+    /// The root move currently being searched
+    pub fn current(&self) -> Option<CurrMove> {
+        self.current
+    }
+    pub fn depth(&self) -> Option<usize> {
+        self.latest.as_ref().map(|u| u.depth)
+    }
+    pub fn eval(&self) -> Option<Score> {
+        self.latest.as_ref().map(|u| u.eval)
+    }
+    /// How the transposition table is filled and used, sampled at the last iteration
+    pub fn tt_stats(&self) -> Option<&TtStats> {
+        self.latest.as_ref().map(|u| &u.tt_stats)
+    }
+    /// How fast the search is going in nodes per second
+    pub fn nps(&self) -> Option<u64> {
+        self.latest.as_ref().and_then(|u| u.nps)
+    }
+    /// The best moves found so far with their evaluations, best first
+    pub fn lines(&self) -> &[RankedLine] {
+        self.latest.as_ref().map(|u| &u.lines[..]).unwrap_or(&[])
+    }
+    /// The line the engine expects, starting with the best move
+    pub fn pv(&self) -> &[Move] {
+        self.lines().first().map(|line| &line.pv[..]).unwrap_or(&[])
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+const REVIEW_DEPTH: usize = 4;
+const REVIEW_NODES: usize = 100_000;
+
+/// Evaluates every position of a finished game in the background, in order
+pub struct Review {
+    evals: Vec<Score>,
+    positions: usize,
+    updates: Receiver<Score>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Review {
+    /// `positions` are the positions of the game, starting with the one it started from
+    pub fn start(positions: Vec<Game>) -> Self {
+        let (sender, updates) = channel();
+        let count = positions.len();
+        let stop = Arc::new(AtomicBool::new(false));
+        let options = SearchOptions::new(REVIEW_DEPTH, REVIEW_NODES).with_stop(stop.clone());
+        std::thread::spawn(move || {
+            for game in positions {
+                let side = game.side_to_move();
+                let eval = match game.result() {
+                    // The side to move has been checkmated
+                    GameResult::WhiteWins(_) | GameResult::BlackWins(_) => -Score::MATE,
+                    GameResult::Draw(_) => Score::ZERO,
+                    GameResult::Ongoing => bot1::get_moves_ranked(game.board_state(), &options).eval,
+                };
+                let white_eval = match side {
+                    Colour::White => eval,
+                    Colour::Black => -eval,
+                };
+                if sender.send(white_eval).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Review {
+            evals: Vec::with_capacity(count),
+            positions: count,
+            updates,
+            stop,
+        }
+    }
+    pub fn poll(&mut self) {
+        self.evals.extend(self.updates.try_iter());
+    }
+    /// Number of positions in the game
+    pub fn positions(&self) -> usize {
+        self.positions
+    }
+    /// Evaluations from white's perspective of the positions evaluated so far
+    pub fn evals(&self) -> &[Score] {
+        &self.evals
+    }
+}
+
+impl Drop for Review {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,7 @@
+use std::io::{stdin, stdout, BufReader};
+
+fn main() {
+    if let Err(e) = talv::uci::run(BufReader::new(stdin()), stdout().lock()) {
+        eprintln!("Lost the connection to the GUI: {e}");
+    }
+}
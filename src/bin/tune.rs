@@ -0,0 +1,66 @@
+use std::{env::args, fs};
+
+use talv::{
+    bots::{
+        bot1::EvalParams,
+        tune::{changes, error, find_scale, LabelledPosition, Tuner},
+    },
+    epd::Epd,
+};
+
+const USAGE: &str = "Usage: tune <positions.epd> [start params.toml] [rounds]
+
+Tunes the evaluation weights on positions labelled with the results of their games, like the ones
+`pgn_to_epd --result` prints, and prints the tuned weights as TOML.";
+
+fn main() {
+    let mut args = args().skip(1);
+    let (Some(path), params_path, rounds) = (args.next(), args.next(), args.next()) else {
+        eprintln!("{USAGE}");
+        return;
+    };
+    let params = match params_path {
+        None => EvalParams::default(),
+        Some(path) => match fs::read_to_string(&path).map(|s| EvalParams::from_toml(&s)) {
+            Ok(Ok(params)) => params,
+            Ok(Err(e)) => {
+                eprintln!("Invalid parameters in {path}: {e}");
+                return;
+            }
+            Err(e) => {
+                eprintln!("Could not read {path}: {e}");
+                return;
+            }
+        },
+    };
+    let rounds = rounds.and_then(|r| r.parse().ok()).unwrap_or(100);
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Could not read {path}: {e}");
+            return;
+        }
+    };
+    let mut positions = Vec::new();
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        match Epd::parse(line).ok().as_ref().and_then(LabelledPosition::from_epd) {
+            Some(pos) => positions.push(pos),
+            None => eprintln!("Line {} isn't a position with a result, skipping it", i + 1),
+        }
+    }
+    if positions.is_empty() {
+        eprintln!("No positions to tune on");
+        return;
+    }
+
+    let scale = find_scale(&positions, &params);
+    eprintln!("{} positions, scale {scale:.3}, error {:.6}", positions.len(), error(&positions, &params, scale));
+    let tuned = Tuner::new(scale).with_max_rounds(rounds).tune(&positions, &params, |round, _, error| {
+        eprintln!("Round {round}: error {error:.6}");
+    });
+    for (name, old, new) in changes(&params, &tuned) {
+        eprintln!("{name}: {old} -> {new}");
+    }
+    print!("{}", tuned.to_toml());
+}
@@ -1,10 +1,11 @@
-use std::{
-    fmt::{self, Display}, ops::Not
+use core::{
+    error::Error, fmt::{self, Display}, ops::Not, str::FromStr
 };
 
 use crate::location::Coords;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Piece {
     Pawn = 1,
@@ -28,6 +29,58 @@ impl Piece {
             _ => unreachable!()
         }
     }
+    /// The conventional value of the piece in pawns, with the king counted as nothing
+    pub const fn points(self) -> u32 {
+        match self {
+            Self::Pawn => 1,
+            Self::Knight | Self::Bishop => 3,
+            Self::Rook => 5,
+            Self::Queen => 9,
+            Self::King => 0,
+        }
+    }
+}
+
+/// How many of each piece one side has, see [`crate::boardstate::BoardState::material`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MaterialCount {
+    pub pawns: u8,
+    pub knights: u8,
+    pub bishops: u8,
+    pub rooks: u8,
+    pub queens: u8,
+}
+
+impl MaterialCount {
+    /// Counts another piece, kings aren't counted
+    pub fn add(&mut self, piece: Piece) {
+        match piece {
+            Piece::Pawn => self.pawns += 1,
+            Piece::Knight => self.knights += 1,
+            Piece::Bishop => self.bishops += 1,
+            Piece::Rook => self.rooks += 1,
+            Piece::Queen => self.queens += 1,
+            Piece::King => (),
+        }
+    }
+    /// How many there are of `piece`, every side has exactly one king
+    pub fn count(&self, piece: Piece) -> u8 {
+        match piece {
+            Piece::Pawn => self.pawns,
+            Piece::Knight => self.knights,
+            Piece::Bishop => self.bishops,
+            Piece::Rook => self.rooks,
+            Piece::Queen => self.queens,
+            Piece::King => 1,
+        }
+    }
+    /// The total value using [`Piece::points`], 39 in the starting position
+    pub fn points(&self) -> u32 {
+        [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+            .into_iter()
+            .map(|p| self.count(p) as u32 * p.points())
+            .sum()
+    }
 }
 
 impl Display for Piece {
@@ -48,7 +101,35 @@ impl Display for Piece {
     }
 }
 
+impl FromStr for Piece {
+    type Err = ParsePieceError;
+    /// Reads a piece letter in either case, the pawn is `P` unlike when displayed
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.to_ascii_uppercase() {
+            "P" => Ok(Piece::Pawn),
+            "R" => Ok(Piece::Rook),
+            "N" => Ok(Piece::Knight),
+            "B" => Ok(Piece::Bishop),
+            "Q" => Ok(Piece::Queen),
+            "K" => Ok(Piece::King),
+            _ => Err(ParsePieceError),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParsePieceError;
+
+impl Display for ParsePieceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a piece letter")
+    }
+}
+
+impl Error for ParsePieceError {}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Colour {
     White,
     Black,
@@ -65,7 +146,40 @@ impl Not for Colour {
     }
 }
 
+impl Display for Colour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Colour::White => write!(f, "white"),
+            Colour::Black => write!(f, "black"),
+        }
+    }
+}
+
+impl FromStr for Colour {
+    type Err = ParseColourError;
+    /// Reads the colour written out or as the single letter used in FEN, in either case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.to_ascii_lowercase() {
+            "white" | "w" => Ok(Colour::White),
+            "black" | "b" => Ok(Colour::Black),
+            _ => Err(ParseColourError),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseColourError;
+
+impl Display for ParseColourError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a colour")
+    }
+}
+
+impl Error for ParseColourError {}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Field {
     Empty,
     Occupied(Colour, Piece),
@@ -168,6 +282,15 @@ pub struct Board([u8; 32]);
 
 impl Board {
     pub const EMPTY: Self = Self([0; 32]);
+    /// The board as two squares per byte, with a1 in the low nibble of the first byte
+    pub const fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+    /// Reads a board from [`Board::to_bytes`], `None` if a nibble isn't a field
+    pub fn from_bytes(bytes: [u8; 32]) -> Option<Self> {
+        let valid = |nibble: u8| nibble & 0b111 != 0b111 && nibble != 0b1000;
+        bytes.iter().all(|&b| valid(b & 0xf) && valid(b >> 4)).then_some(Board(bytes))
+    }
     #[inline]
     fn interpret_coords(coords: Coords) -> (usize, bool) {
         let b = coords.into_u8();
@@ -197,6 +320,96 @@ impl Board {
         }
         get
     }
+    /// The board with every piece moved to `f` of its square
+    fn map_squares(&self, f: impl Fn(Coords) -> Coords) -> Self {
+        let mut board = Board::EMPTY;
+        for cs in Coords::full_range() {
+            board.set(f(cs), self.get(cs));
+        }
+        board
+    }
+    /// The board reflected in the line between the d- and e-files, so a1 and h1 change places
+    pub fn mirror_horizontal(&self) -> Self {
+        self.map_squares(Coords::flip_file)
+    }
+    /// The board reflected in the line between the fourth and fifth ranks, so a1 and a8 change places
+    pub fn mirror_vertical(&self) -> Self {
+        self.map_squares(Coords::flip_rank)
+    }
+    /// The board turned around as seen from the other side, so a1 and h8 change places
+    pub fn rotate_180(&self) -> Self {
+        self.map_squares(|cs| cs.flip_rank().flip_file())
+    }
+    /// The board with every white piece made black and vice versa
+    pub fn swap_colours(&self) -> Self {
+        let mut board = *self;
+        for cs in Coords::full_range() {
+            if let Field::Occupied(c, p) = self.get(cs) {
+                board.set(cs, Field::Occupied(!c, p));
+            }
+        }
+        board
+    }
+}
+
+/// Serialized as the ranks from the eighth to the first, using FEN letters for the pieces and `.` for empty squares
+#[cfg(feature = "serde")]
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use crate::location::{FileRange, RankRange};
+        use alloc::{string::String, vec::Vec};
+
+        let letter = |field| match field {
+            Field::Empty => '.',
+            Field::Occupied(c, p) => {
+                let letter = match p {
+                    Piece::Pawn => 'P',
+                    Piece::Rook => 'R',
+                    Piece::Knight => 'N',
+                    Piece::Bishop => 'B',
+                    Piece::Queen => 'Q',
+                    Piece::King => 'K',
+                };
+                match c {
+                    Colour::White => letter,
+                    Colour::Black => letter.to_ascii_lowercase(),
+                }
+            }
+        };
+        let ranks: Vec<String> = RankRange::full()
+            .rev()
+            .map(|n| FileRange::full().map(|l| letter(self.get(Coords::new(l, n)))).collect())
+            .collect();
+        ranks.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use alloc::string::String;
+        use serde::de::Error;
+
+        let ranks = <[String; 8]>::deserialize(deserializer)?;
+        let mut board = Board::EMPTY;
+        for (rank, y) in ranks.iter().zip((0..8).rev()) {
+            if rank.chars().count() != 8 {
+                return Err(D::Error::custom(format_args!("rank {rank:?} doesn't have eight squares")));
+            }
+            for (c, x) in rank.chars().zip(0..) {
+                let field = match c {
+                    '.' => Field::Empty,
+                    c => {
+                        let piece = Piece::from_str(c.encode_utf8(&mut [0; 4])).map_err(|_| D::Error::custom(format_args!("invalid piece {c:?}")))?;
+                        let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+                        Field::Occupied(colour, piece)
+                    }
+                };
+                board.set(Coords::from_u8_tuple(x, y).unwrap(), field);
+            }
+        }
+        Ok(board)
+    }
 }
 
 pub const START: Board = Board([
@@ -210,18 +423,164 @@ pub const START: Board = Board([
     BR.or(BN), BB.or(BQ), BK.or(BB), BN.or(BR),
 ]);
 
+/// Displays the board with the default [`BoardDisplay`] settings
 impl Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Board(board) = self;
-        writeln!(f, " abcdefgh")?;
-        for (i, row) in (0..8).map(|i| 8 - i).zip(board.chunks_exact(4).rev()) {
-            write!(f, "{}", i)?;
-            for bits in row {
-                let (o1, o2) = (Field::from_bits(bits & 0xf), Field::from_bits(bits >> 4));
-                write!(f, "{o1}{o2}")?;
+        self.display().fmt(f)
+    }
+}
+
+impl Board {
+    /// A text rendering of the board, by default with Unicode glyphs from white's side and coordinates around it
+    pub const fn display(&self) -> BoardDisplay<'_> {
+        BoardDisplay {
+            board: self,
+            ascii: false,
+            perspective: Colour::White,
+            coordinates: true,
+            ansi_colours: false,
+        }
+    }
+}
+
+/// A text rendering of a board, made with [`Board::display`]
+#[derive(Debug, Clone, Copy)]
+pub struct BoardDisplay<'a> {
+    board: &'a Board,
+    ascii: bool,
+    perspective: Colour,
+    coordinates: bool,
+    ansi_colours: bool,
+}
+
+const ANSI_LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const ANSI_DARK_SQUARE: &str = "\x1b[48;5;137m";
+const ANSI_WHITE_PIECE: &str = "\x1b[1;97m";
+const ANSI_BLACK_PIECE: &str = "\x1b[1;30m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+impl BoardDisplay<'_> {
+    /// Uses FEN letters and `.` for empty squares instead of Unicode glyphs, which some terminals show poorly
+    pub fn with_ascii(self, ascii: bool) -> Self {
+        BoardDisplay { ascii, ..self }
+    }
+    /// Shows the board with `perspective`'s pieces at the bottom
+    pub fn with_perspective(self, perspective: Colour) -> Self {
+        BoardDisplay { perspective, ..self }
+    }
+    /// Labels the files above and below and the ranks on both sides
+    pub fn with_coordinates(self, coordinates: bool) -> Self {
+        BoardDisplay { coordinates, ..self }
+    }
+    /// Colours the squares and pieces with ANSI escape codes, making each square three characters wide
+    pub fn with_ansi_colours(self, ansi_colours: bool) -> Self {
+        BoardDisplay { ansi_colours, ..self }
+    }
+    fn write_files(&self, f: &mut fmt::Formatter<'_>, files: &[i8]) -> fmt::Result {
+        write!(f, " ")?;
+        for &x in files {
+            let letter = (b'a' + x as u8) as char;
+            if self.ansi_colours {
+                write!(f, " {letter} ")?;
+            } else {
+                write!(f, "{letter}")?;
+            }
+        }
+        writeln!(f)
+    }
+    fn write_field(&self, f: &mut fmt::Formatter<'_>, field: Field) -> fmt::Result {
+        if !self.ascii {
+            return write!(f, "{field}");
+        }
+        let letter = match field {
+            Field::Empty => '.',
+            Field::Occupied(_, Piece::Pawn) => 'P',
+            Field::Occupied(_, Piece::Rook) => 'R',
+            Field::Occupied(_, Piece::Knight) => 'N',
+            Field::Occupied(_, Piece::Bishop) => 'B',
+            Field::Occupied(_, Piece::Queen) => 'Q',
+            Field::Occupied(_, Piece::King) => 'K',
+        };
+        match field {
+            Field::Occupied(Colour::Black, _) => write!(f, "{}", letter.to_ascii_lowercase()),
+            _ => write!(f, "{letter}"),
+        }
+    }
+}
+
+impl Display for BoardDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (files, ranks) = match self.perspective {
+            Colour::White => ([0, 1, 2, 3, 4, 5, 6, 7], [7, 6, 5, 4, 3, 2, 1, 0]),
+            Colour::Black => ([7, 6, 5, 4, 3, 2, 1, 0], [0, 1, 2, 3, 4, 5, 6, 7]),
+        };
+
+        if self.coordinates {
+            self.write_files(f, &files)?;
+        }
+        for y in ranks {
+            if self.coordinates {
+                write!(f, "{}", y + 1)?;
             }
-            writeln!(f, " {}", i)?;
+            for x in files {
+                let cs = Coords::from_u8_tuple(x, y).unwrap();
+                let field = self.board.get(cs);
+                if self.ansi_colours {
+                    let square = match cs.colour() {
+                        Colour::White => ANSI_LIGHT_SQUARE,
+                        Colour::Black => ANSI_DARK_SQUARE,
+                    };
+                    let piece = match field {
+                        Field::Occupied(Colour::Black, _) => ANSI_BLACK_PIECE,
+                        _ => ANSI_WHITE_PIECE,
+                    };
+                    write!(f, "{square}{piece} ")?;
+                    self.write_field(f, field)?;
+                    write!(f, " {ANSI_RESET}")?;
+                } else {
+                    self.write_field(f, field)?;
+                }
+            }
+            if self.coordinates {
+                write!(f, " {}", y + 1)?;
+            }
+            writeln!(f)?;
+        }
+        if self.coordinates {
+            self.write_files(f, &files)?;
         }
-        writeln!(f, " abcdefgh")
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror() {
+        let board = START.mirror_horizontal();
+        assert_eq!(board.get(Coords::D1), WK);
+        assert_eq!(board.get(Coords::E8), BQ);
+        assert_eq!(START.mirror_vertical().get(Coords::E8), WK);
+        assert_eq!(START.mirror_vertical().swap_colours(), START);
+        assert_eq!(START.rotate_180(), START.mirror_horizontal().mirror_vertical());
+        assert_eq!(START.rotate_180().get(Coords::D8), WK);
+        assert_eq!(START.rotate_180().rotate_180(), START);
+    }
+
+    #[test]
+    fn test_board_display() {
+        assert_eq!(
+            START.to_string(),
+            " abcdefgh\n8♜♞♝♛♚♝♞♜ 8\n7♟♟♟♟♟♟♟♟ 7\n6         6\n5         5\n4         4\n3         3\n2♙♙♙♙♙♙♙♙ 2\n1♖♘♗♕♔♗♘♖ 1\n abcdefgh\n"
+        );
+        assert_eq!(
+            START.display().with_ascii(true).with_perspective(Colour::Black).with_coordinates(false).to_string(),
+            "RNBKQBNR\nPPPPPPPP\n........\n........\n........\n........\npppppppp\nrnbkqbnr\n"
+        );
+        let coloured = START.display().with_ascii(true).with_ansi_colours(true).to_string();
+        assert!(coloured.starts_with("  a  b  c"));
+        assert!(coloured.contains(&format!("1{ANSI_DARK_SQUARE}{ANSI_WHITE_PIECE} R {ANSI_RESET}{ANSI_LIGHT_SQUARE}")));
     }
 }
@@ -1,21 +1,62 @@
-use std::fmt::{self, Display};
+use alloc::vec::Vec;
+use core::{error::Error, fmt::{self, Display}, hash::{Hash, Hasher}, iter, str::FromStr};
 
 use super::board::*;
-use super::location::{Coords, File, FileRange, Rank, RankRange};
+use super::movegen::{self, Move};
+use super::polyglot;
+use super::location::{squares_in, Coords, Direction, File, FileRange, Line, Rank, RankRange, RAYS};
+
+/// The length of [`BoardState::to_bytes`]
+pub const BYTES_LEN: usize = 34;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct CastlesAllowed {
     pub(crate) short: bool,
     pub(crate) long: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Serialized through [`PositionBuilder`], so deserializing checks the position
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "PositionBuilder", try_from = "PositionBuilder"))]
 pub struct BoardState {
     pub(crate) board: Board,
     pub side_to_move: Colour,
     pub(crate) black_castling: CastlesAllowed,
     pub(crate) white_castling: CastlesAllowed,
     pub(crate) en_passant_target: Option<Coords>,
+    /// Not part of the position, so it's left out of comparisons and hashing
+    pub(crate) check_cache: CheckCache,
+}
+
+/// The pieces giving check to one side, saved when a move is made since it has to be found anyway
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CheckCache {
+    side: Colour,
+    checkers: u64,
+}
+
+impl PartialEq for BoardState {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.side_to_move == other.side_to_move
+            && self.black_castling == other.black_castling
+            && self.white_castling == other.white_castling
+            && self.en_passant_target == other.en_passant_target
+    }
+}
+
+impl Eq for BoardState {}
+
+impl Hash for BoardState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.side_to_move.hash(state);
+        self.black_castling.hash(state);
+        self.white_castling.hash(state);
+        self.en_passant_target.hash(state);
+    }
 }
 
 impl Default for BoardState {
@@ -24,6 +65,55 @@ impl Default for BoardState {
     }
 }
 
+/// Why a move was rejected
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    NoPieceOnSquare,
+    /// The piece belongs to the side not to move
+    WrongColour,
+    /// The piece can't move like that, or something is in the way
+    IllegalPieceMovement,
+    LeavesKingInCheck,
+    /// A pawn reaching the last rank has to promote
+    MissingPromotion,
+    /// Promoting to a king or a pawn, or promoting without reaching the last rank
+    IllegalPromotion,
+    /// Castling without the right to, through pieces, out of check or through an attacked square
+    IllegalCastle,
+    /// The game has already been decided by resignation or agreement
+    GameOver,
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::NoPieceOnSquare => write!(f, "there is no piece on that square"),
+            MoveError::WrongColour => write!(f, "that piece belongs to the other side"),
+            MoveError::IllegalPieceMovement => write!(f, "that piece can't move there"),
+            MoveError::LeavesKingInCheck => write!(f, "that would leave the king in check"),
+            MoveError::MissingPromotion => write!(f, "the pawn has to promote"),
+            MoveError::IllegalPromotion => write!(f, "that promotion isn't allowed"),
+            MoveError::IllegalCastle => write!(f, "castling isn't allowed there"),
+            MoveError::GameOver => write!(f, "the game is over"),
+        }
+    }
+}
+
+/// What [`BoardState::unmake_move`] needs to take back a move, as returned by [`BoardState::make_move_undoable`]
+#[derive(Debug, Clone, Copy)]
+pub struct Undo {
+    from: Coords,
+    unto: Coords,
+    mover: Field,
+    taken: Field,
+    /// Differs from `unto` for en passant captures
+    taken_at: Coords,
+    black_castling: CastlesAllowed,
+    white_castling: CastlesAllowed,
+    en_passant_target: Option<Coords>,
+    check_cache: CheckCache,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Success {
     Capture,
@@ -47,9 +137,14 @@ impl BoardState {
                 long: true,
             },
             en_passant_target: None,
+            // Nobody is in check in the starting position
+            check_cache: CheckCache {
+                side: Colour::White,
+                checkers: 0,
+            },
         }
     }
-    /// Reads a board state from the first four fields of a FEN string
+    /// Reads a board state from the first four fields of a FEN string, `None` if it isn't valid FEN or a legal position
     pub fn from_fen(s: &str) -> Option<Self> {
         let mut fields = s.split_whitespace();
 
@@ -182,28 +277,129 @@ impl BoardState {
             s => Some(Coords::from_str(s)?),
         };
 
-        Some(BoardState {
+        let mut state = BoardState {
             board,
             side_to_move,
             black_castling,
             white_castling,
             en_passant_target,
-        })
+            ..BoardState::new()
+        };
+        state.validate().ok()?;
+        state.update_check_cache();
+        Some(state)
     }
-    pub fn in_check(&self, side: Colour) -> bool {
-        let king = self.find_king(side);
+    /// Checks that the position could come up in a game, as far as can be told without its history
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for colour in [Colour::White, Colour::Black] {
+            let kings = Coords::full_range().filter(|&cs| self.board.get(cs) == Field::Occupied(colour, Piece::King)).count();
+            if kings != 1 {
+                return Err(PositionError::KingCount(colour));
+            }
+
+            let (castling, home_rank) = match colour {
+                Colour::White => (self.white_castling, 0),
+                Colour::Black => (self.black_castling, 7),
+            };
+            let on_home_rank = |file, piece| self.board.get(Coords::from_u8_tuple(file, home_rank).unwrap()) == Field::Occupied(colour, piece);
+            let king_home = on_home_rank(4, Piece::King);
+            if (castling.short && !(king_home && on_home_rank(7, Piece::Rook))) || (castling.long && !(king_home && on_home_rank(0, Piece::Rook))) {
+                return Err(PositionError::InvalidCastling(colour));
+            }
+        }
+
+        if let Some(cs) = Coords::full_range().find(|&cs| matches!(cs.r(), Rank::N1 | Rank::N8) && matches!(self.board.get(cs), Field::Occupied(_, Piece::Pawn))) {
+            return Err(PositionError::PawnOnBackRank(cs));
+        }
 
-        self.is_threatened(king, !side)
+        if let Some(target) = self.en_passant_target {
+            // The pawn that just moved went from behind the target to in front of it, seen from its side
+            let forward = match self.side_to_move {
+                Colour::White => -1,
+                Colour::Black => 1,
+            };
+            let target_rank = match self.side_to_move {
+                Colour::White => 5,
+                Colour::Black => 2,
+            };
+            let plausible = target.r().i8() == target_rank
+                && self.board.get(target).is_empty()
+                && target.add(0, -forward).is_some_and(|start| self.board.get(start).is_empty())
+                && target.add(0, forward).is_some_and(|pawn| self.board.get(pawn) == Field::Occupied(!self.side_to_move, Piece::Pawn));
+            if !plausible {
+                return Err(PositionError::InvalidEnPassant);
+            }
+        }
+
+        if self.is_attacked(self.find_king(!self.side_to_move), self.side_to_move) {
+            return Err(PositionError::OpponentInCheck);
+        }
+        Ok(())
     }
-    fn is_threatened(&self, spot: Coords, by_side: Colour) -> bool {
-        for cs in Coords::full_range() {
-            if self.is_pseudo_legal(by_side, cs, spot) {
-                return true;
+    /// Whether `side` is in check, which is free for the side to move after a move has been made
+    pub fn in_check(&self, side: Colour) -> bool {
+        self.checkers_mask(side) != 0
+    }
+    /// The squares of the pieces giving check to `side`'s king, there are at most two
+    pub fn checkers(&self, side: Colour) -> impl Iterator<Item = Coords> {
+        squares_in(self.checkers_mask(side))
+    }
+    fn checkers_mask(&self, side: Colour) -> u64 {
+        if self.check_cache.side == side {
+            self.check_cache.checkers
+        } else {
+            self.attackers_mask(self.find_king(side), !side)
+        }
+    }
+    fn update_check_cache(&mut self) {
+        let side = self.side_to_move;
+        self.check_cache = CheckCache {
+            side,
+            checkers: self.attackers_mask(self.find_king(side), !side),
+        };
+    }
+    /// Whether any piece of `by_side` attacks `spot`, whatever is standing on it
+    ///
+    /// Pins are ignored, so a pinned piece still attacks the squares it could move to if it weren't pinned.
+    pub fn is_attacked(&self, spot: Coords, by_side: Colour) -> bool {
+        self.attackers_mask(spot, by_side) != 0
+    }
+    /// The squares of the pieces of `by_side` that attack `spot`, see [`Self::is_attacked`]
+    pub fn attackers(&self, spot: Coords, by_side: Colour) -> impl Iterator<Item = Coords> {
+        squares_in(self.attackers_mask(spot, by_side))
+    }
+    /// The pieces of `by_side` that attack `spot`, found by looking outwards from `spot`
+    fn attackers_mask(&self, spot: Coords, by_side: Colour) -> u64 {
+        let mut mask = 0;
+        let mut add_if = |cs: Option<Coords>, pieces: &[Piece]| {
+            if let Some(cs) = cs {
+                if matches!(self.board.get(cs), Field::Occupied(c, p) if c == by_side && pieces.contains(&p)) {
+                    mask |= 1 << cs.into_u8();
+                }
+            }
+        };
+
+        for cs in squares_in(movegen::KNIGHT_ATTACKS[spot]) {
+            add_if(Some(cs), &[Piece::Knight]);
+        }
+        for cs in squares_in(movegen::KING_ATTACKS[spot]) {
+            add_if(Some(cs), &[Piece::King]);
+        }
+        // Pawns attack forwards, so they are found where a pawn of the other colour would attack from the square
+        for cs in squares_in(movegen::pawn_attacks(!by_side, spot)) {
+            add_if(Some(cs), &[Piece::Pawn]);
+        }
+
+        for (directions, slider) in [(Direction::STRAIGHT, Piece::Rook), (Direction::DIAGONAL, Piece::Bishop)] {
+            for dir in directions {
+                let first_piece = spot.ray(dir).find(|&cs| self.board.get(cs).is_occupied());
+                add_if(first_piece, &[slider, Piece::Queen]);
             }
         }
-        false
+
+        mask
     }
-    fn find_king(&self, c: Colour) -> Coords {
+    pub(crate) fn find_king(&self, c: Colour) -> Coords {
         for cs in Coords::full_range() {
             match self.board.get(cs) {
                 Field::Occupied(pc, Piece::King) if pc == c => return cs,
@@ -212,30 +408,33 @@ impl BoardState {
         }
         unreachable!("no king");
     }
-    pub fn make_move(&mut self, from: Coords, unto: Coords, promotion: Option<Piece>) -> Result<Success, ()> {
+    /// Makes a move that is legal apart from possibly leaving the mover's own king in check
+    pub fn make_move(&mut self, from: Coords, unto: Coords, promotion: Option<Piece>) -> Result<Success, MoveError> {
+        let piece = match self.board.get(from) {
+            Field::Empty => return Err(MoveError::NoPieceOnSquare),
+            Field::Occupied(c, _) if c != self.side_to_move => return Err(MoveError::WrongColour),
+            Field::Occupied(_, p) => p,
+        };
+        let dist = unto.sub(from);
+        let castles = piece == Piece::King && dist.0.abs() == 2;
         if !self.is_pseudo_legal(self.side_to_move, from, unto) {
-            return Err(())
+            return Err(if castles { MoveError::IllegalCastle } else { MoveError::IllegalPieceMovement });
         }
         // Check promotion
-        let legal_promotion;
-        if self.board.get(from).into_piece() == Some(Piece::Pawn) {
-            legal_promotion = match promotion {
-                None => unto.r() != Rank::N1 && unto.r() != Rank::N8,
-                Some(Piece::King | Piece::Pawn) => false,
-                Some(_) => unto.r() == Rank::N1 || unto.r() == Rank::N8,
-            };
-        } else {
-            legal_promotion = promotion.is_none();
-        }
-        if !legal_promotion {
-            return Err(())
+        if piece == Piece::Pawn {
+            let last_rank = unto.r() == Rank::N1 || unto.r() == Rank::N8;
+            match promotion {
+                None if last_rank => return Err(MoveError::MissingPromotion),
+                Some(Piece::King | Piece::Pawn) => return Err(MoveError::IllegalPromotion),
+                Some(_) if !last_rank => return Err(MoveError::IllegalPromotion),
+                _ => (),
+            }
+        } else if promotion.is_some() {
+            return Err(MoveError::IllegalPromotion);
         }
         // Check castling
-        let dist = unto.sub(from);
-        if dist.0.abs() == 2 && self.board.get(from).into_piece() == Some(Piece::King) {
-            if self.in_check(self.side_to_move) || self.is_threatened(from.add(dist.0/2, 0).unwrap(), !self.side_to_move) {
-                return Err(());
-            }
+        if castles && (self.in_check(self.side_to_move) || self.is_attacked(from.add(dist.0/2, 0).unwrap(), !self.side_to_move)) {
+            return Err(MoveError::IllegalCastle);
         }
 
         let mover = self.board.set(from, Field::Empty);
@@ -300,6 +499,7 @@ impl BoardState {
             }
         }
 
+        self.update_check_cache();
         let check = self.in_check(self.side_to_move);
 
         if taken.is_occupied() {
@@ -313,6 +513,59 @@ impl BoardState {
             })
         }
     }
+    /// Passes the turn to the other side without moving, which can't be done in check
+    pub fn make_null_move(&mut self) -> Result<(), MoveError> {
+        if self.in_check(self.side_to_move) {
+            return Err(MoveError::LeavesKingInCheck);
+        }
+        self.side_to_move = !self.side_to_move;
+        self.en_passant_target = None;
+        self.update_check_cache();
+        Ok(())
+    }
+    /// Makes a move like [`BoardState::make_move`], also returning how to take it back again without having copied the whole state
+    pub fn make_move_undoable(&mut self, from: Coords, unto: Coords, promotion: Option<Piece>) -> Result<(Success, Undo), MoveError> {
+        let mover = self.board.get(from);
+        let taken_at = match self.en_passant_target {
+            Some(target) if unto == target && mover.into_piece() == Some(Piece::Pawn) => Coords::new(unto.f(), from.r()),
+            _ => unto,
+        };
+        let undo = Undo {
+            from,
+            unto,
+            mover,
+            taken: self.board.get(taken_at),
+            taken_at,
+            black_castling: self.black_castling,
+            white_castling: self.white_castling,
+            en_passant_target: self.en_passant_target,
+            check_cache: self.check_cache,
+        };
+        let success = self.make_move(from, unto, promotion)?;
+        Ok((success, undo))
+    }
+    /// Takes back the last move made, which has to be the one `undo` was returned for
+    pub fn unmake_move(&mut self, undo: Undo) {
+        let Undo { from, unto, mover, taken, taken_at, .. } = undo;
+        self.board.set(unto, Field::Empty);
+        self.board.set(taken_at, taken);
+        self.board.set(from, mover);
+        if mover.into_piece() == Some(Piece::King) && unto.sub(from).0.abs() == 2 {
+            let (rook_home, rook_now) = if unto.f() == File::G {
+                (Coords::new(File::H, unto.r()), Coords::new(File::F, unto.r()))
+            } else {
+                (Coords::new(File::A, unto.r()), Coords::new(File::D, unto.r()))
+            };
+            let rook = self.board.set(rook_now, Field::Empty);
+            self.board.set(rook_home, rook);
+        }
+
+        self.side_to_move = !self.side_to_move;
+        self.black_castling = undo.black_castling;
+        self.white_castling = undo.white_castling;
+        self.en_passant_target = undo.en_passant_target;
+        self.check_cache = undo.check_cache;
+    }
     fn update_allowed_castles(&mut self, mover: Field, pos: Coords) {
         let (ac, brn) = match self.side_to_move {
             Colour::Black => (&mut self.black_castling, Rank::N8),
@@ -386,9 +639,9 @@ impl BoardState {
 
                 (l == 2 && n == 1) || (l == 1 && n == 2)
             }
-            Piece::Bishop => self.check_along(from, unto, |x, y| x == y),
-            Piece::Queen => self.check_along(from, unto, |x, y| x == y || x == 0 || y == 0),
-            Piece::Rook => self.check_along(from, unto, |x, y| x == 0 || y == 0),
+            Piece::Bishop => self.check_along(from, unto, false, true),
+            Piece::Queen => self.check_along(from, unto, true, true),
+            Piece::Rook => self.check_along(from, unto, true, false),
             Piece::King => {
                 let (dl, dn) = unto.sub(from);
                 let (al, an) = (dl.abs(), dn.abs());
@@ -406,48 +659,394 @@ impl BoardState {
                             && self.board.get(from.add(1, 0).unwrap()).is_empty())
                             || (ac.long
                                 && dl == -2
-                                && self.board.get(from.add(-1, 0).unwrap()).is_empty()))
+                                && self.board.get(from.add(-1, 0).unwrap()).is_empty()
+                                && self.board.get(from.add(-3, 0).unwrap()).is_empty()))
                 } else {
                     false
                 }
             }
         }
     }
-    fn check_along<F: FnOnce(i8, i8) -> bool>(&self, from: Coords, unto: Coords, f: F) -> bool {
-        let (dl, dn) = unto.sub(from);
-        let (al, an) = (dl.abs(), dn.abs());
-        let distance = al.max(an);
-
-        if f(al, an) {
-            let dl = dl.signum();
-            let dn = dn.signum();
+    /// Checks that `from` and `unto` lie on an allowed line with nothing in between
+    fn check_along(&self, from: Coords, unto: Coords, straight: bool, diagonal: bool) -> bool {
+        let ray = RAYS[from][unto];
 
-            let (l, n) = from.i8_tuple();
-
-            for i in 1..distance {
-                let coords = Coords::from_u8_tuple(l + i * dl, n + i * dn);
+        let allowed = match ray.line {
+            Line::None => false,
+            Line::Straight => straight,
+            Line::Diagonal => diagonal,
+        };
 
-                let is_free = coords.map(|to| self.board.get(to).is_empty());
-                match is_free {
-                    Some(true) => (),
-                    _ => return false,
-                }
+        allowed && squares_in(ray.between).all(|cs| self.board.get(cs).is_empty())
+    }
+    /// The squares a piece passes through on its way from `from` to `unto`, ending with `unto`
+    ///
+    /// Knights jump straight to `unto`, and castling is followed by the path of the rook
+    pub fn move_path(&self, from: Coords, unto: Coords) -> impl Iterator<Item = Coords> {
+        let (dl, dn) = unto.sub(from);
+        let castling_rook = if dl.abs() == 2 && dn == 0 && self.board.get(from).into_piece() == Some(Piece::King) {
+            match dl.signum() {
+                1 => Some((Coords::new(File::H, from.r()), unto.add(-1, 0).unwrap())),
+                _ => Some((Coords::new(File::A, from.r()), unto.add(1, 0).unwrap())),
             }
-            true
         } else {
-            false
+            None
+        };
+
+        line_path(from, unto).chain(castling_rook.into_iter().flat_map(|(from, unto)| line_path(from, unto)))
+    }
+    /// The same position with the colours swapped and the board mirrored vertically, so it's the same game for the other side
+    pub fn flipped(&self) -> BoardState {
+        BoardState {
+            board: self.board.mirror_vertical().swap_colours(),
+            side_to_move: !self.side_to_move,
+            black_castling: self.white_castling,
+            white_castling: self.black_castling,
+            en_passant_target: self.en_passant_target.map(Coords::flip_rank),
+            check_cache: CheckCache {
+                side: !self.check_cache.side,
+                checkers: flip_mask(self.check_cache.checkers),
+            },
         }
     }
-    pub const fn display_fen(&self) -> BoardStateFen {
-        BoardStateFen { inner: self }
+    /// The position as seen by the side to move, such that positions that only differ by colour are equal
+    pub fn canonical(&self) -> Canonical {
+        match self.side_to_move {
+            Colour::White => Canonical { state: *self, flipped: false },
+            Colour::Black => Canonical { state: self.flipped(), flipped: true },
+        }
+    }
+    pub const fn display_fen(&self) -> BoardStateFen<'_> {
+        BoardStateFen {
+            inner: self,
+            style: FenStyle::new(),
+        }
+    }
+    /// Whether the side to move has a legal en passant capture
+    pub fn can_capture_en_passant(&self) -> bool {
+        let Some(target) = self.en_passant_target else { return false };
+        let back = match self.side_to_move {
+            Colour::White => -1,
+            Colour::Black => 1,
+        };
+        [target.add(-1, back), target.add(1, back)]
+            .into_iter()
+            .flatten()
+            .filter(|&from| self.board.get(from) == Field::Occupied(self.side_to_move, Piece::Pawn))
+            .any(|from| self.legal_moves_from(from).iter().any(|mv| mv.unto == target))
     }
     pub fn get(&self, coords: Coords) -> Field {
         self.board.get(coords)
     }
+    /// The hash of the position used by Polyglot opening books
+    pub fn polyglot_key(&self) -> u64 {
+        polyglot::key(self)
+    }
+    /// The legal moves of the piece on `from`, e.g. to show where a picked up piece can go
+    ///
+    /// There are none if the square is empty or the piece isn't the side to move's.
+    pub fn legal_moves_from(&self, from: Coords) -> Vec<Move> {
+        movegen::legal_moves_from(self, from)
+    }
+    /// How many of each piece `colour` has left
+    pub fn material(&self, colour: Colour) -> MaterialCount {
+        let mut material = MaterialCount::default();
+        for cs in Coords::full_range() {
+            match self.board.get(cs) {
+                Field::Occupied(c, p) if c == colour => material.add(p),
+                _ => (),
+            }
+        }
+        material
+    }
+    /// The squares of `colour`'s pieces of the kind `piece`
+    pub fn piece_squares(&self, colour: Colour, piece: Piece) -> impl Iterator<Item = Coords> + '_ {
+        Coords::full_range().filter(move |&cs| self.board.get(cs) == Field::Occupied(colour, piece))
+    }
+    /// A compact encoding of the position for storing many of them, see [`BoardState::from_bytes`]
+    ///
+    /// The first 32 bytes are the board from [`Board::to_bytes`]. Then comes a byte with the side to move in bit 0
+    /// (set for black) and the castling rights `KQkq` in bits 1 to 4, and last the en passant target's `Coords`
+    /// number or `0xff` if there is none.
+    pub fn to_bytes(&self) -> [u8; BYTES_LEN] {
+        let mut bytes = [0; BYTES_LEN];
+        bytes[..32].copy_from_slice(&self.board.to_bytes());
+        bytes[32] = (self.side_to_move == Colour::Black) as u8
+            | (self.white_castling.short as u8) << 1
+            | (self.white_castling.long as u8) << 2
+            | (self.black_castling.short as u8) << 3
+            | (self.black_castling.long as u8) << 4;
+        bytes[33] = self.en_passant_target.map_or(0xff, Coords::into_u8);
+        bytes
+    }
+    /// Reads a position from [`BoardState::to_bytes`], `None` if the bytes aren't a valid position
+    pub fn from_bytes(bytes: [u8; BYTES_LEN]) -> Option<Self> {
+        let mut board_bytes = [0; 32];
+        board_bytes.copy_from_slice(&bytes[..32]);
+        let flags = bytes[32];
+        if flags >> 5 != 0 {
+            return None;
+        }
+        let side_to_move = if flags & 1 == 0 { Colour::White } else { Colour::Black };
+        let en_passant_target = match bytes[33] {
+            0xff => None,
+            i => Some(Coords::from_u8(i)?),
+        };
+        PositionBuilder {
+            board: Board::from_bytes(board_bytes)?,
+            side_to_move,
+            en_passant_target,
+            white_castling: CastlesAllowed {
+                short: flags & 1 << 1 != 0,
+                long: flags & 1 << 2 != 0,
+            },
+            black_castling: CastlesAllowed {
+                short: flags & 1 << 3 != 0,
+                long: flags & 1 << 4 != 0,
+            },
+        }
+        .build()
+        .ok()
+    }
+    /// A mask of the occupied squares where bit `i` is `Coords` number `i`, e.g. for [`crate::movegen::attacks_from`]
+    pub fn occupancy(&self) -> u64 {
+        Coords::full_range()
+            .filter(|&cs| self.board.get(cs).is_occupied())
+            .fold(0, |mask, cs| mask | 1 << cs.into_u8())
+    }
+}
+
+/// A position with white to move, made from one that might have had black to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Canonical {
+    pub state: BoardState,
+    /// Whether the original position had black to move and was flipped
+    pub flipped: bool,
+}
+
+impl Canonical {
+    /// Translates a move between the original and the canonical position, in either direction
+    pub fn translate(&self, mv: Move) -> Move {
+        if self.flipped {
+            Move {
+                from: mv.from.flip_rank(),
+                unto: mv.unto.flip_rank(),
+                ..mv
+            }
+        } else {
+            mv
+        }
+    }
+}
+
+/// Mirrors a set of squares across the middle of the board like [`Coords::flip_rank`]
+const fn flip_mask(mask: u64) -> u64 {
+    mask.swap_bytes()
+}
+
+/// Steps along the straight or diagonal line from `from` to `unto`, or jumps if they're not on one
+fn line_path(from: Coords, unto: Coords) -> impl Iterator<Item = Coords> {
+    let (dl, dn) = unto.sub(from);
+    let (sl, sn) = if dl == 0 || dn == 0 || dl.abs() == dn.abs() {
+        (dl.signum(), dn.signum())
+    } else {
+        (dl, dn)
+    };
+    iter::successors(Some(from), move |&cs| if cs == unto { None } else { cs.add(sl, sn) }).skip(1)
+}
+
+/// Displays the first four fields of the FEN, like [`BoardState::display_fen`]
+impl Display for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_fen().fmt(f)
+    }
+}
+
+impl FromStr for BoardState {
+    type Err = FenError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BoardState::from_fen(s).ok_or(FenError)
+    }
+}
+
+/// The error from reading an invalid FEN string
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FenError;
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid FEN")
+    }
+}
+
+impl Error for FenError {}
+
+/// Sets up a position piece by piece, as an alternative to writing out a FEN string
+///
+/// It starts out as an empty board with white to move and no castling rights.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionBuilder {
+    board: Board,
+    side_to_move: Colour,
+    en_passant_target: Option<Coords>,
+    white_castling: CastlesAllowed,
+    black_castling: CastlesAllowed,
+}
+
+impl Default for PositionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<BoardState> for PositionBuilder {
+    fn from(state: BoardState) -> Self {
+        PositionBuilder {
+            board: state.board,
+            side_to_move: state.side_to_move,
+            black_castling: state.black_castling,
+            white_castling: state.white_castling,
+            en_passant_target: state.en_passant_target,
+        }
+    }
+}
+
+impl TryFrom<PositionBuilder> for BoardState {
+    type Error = PositionError;
+    fn try_from(builder: PositionBuilder) -> Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl PositionBuilder {
+    pub const fn new() -> Self {
+        let no_castling = CastlesAllowed {
+            short: false,
+            long: false,
+        };
+        PositionBuilder {
+            board: Board::EMPTY,
+            side_to_move: Colour::White,
+            black_castling: no_castling,
+            white_castling: no_castling,
+            en_passant_target: None,
+        }
+    }
+    /// Puts a piece on `square`, replacing whatever was there
+    pub fn with_piece(self, square: Coords, colour: Colour, piece: Piece) -> Self {
+        self.with_field(square, Field::Occupied(colour, piece))
+    }
+    pub fn with_field(mut self, square: Coords, field: Field) -> Self {
+        self.board.set(square, field);
+        self
+    }
+    pub fn with_side_to_move(self, side_to_move: Colour) -> Self {
+        PositionBuilder { side_to_move, ..self }
+    }
+    /// Which ways `colour` may still castle, the king and rooks have to be on their starting squares for it
+    pub fn with_castling(mut self, colour: Colour, short: bool, long: bool) -> Self {
+        let castling = CastlesAllowed { short, long };
+        match colour {
+            Colour::White => self.white_castling = castling,
+            Colour::Black => self.black_castling = castling,
+        }
+        self
+    }
+    /// The square a pawn skipped over with a double step on the last move, if any
+    pub fn with_en_passant(self, en_passant_target: Option<Coords>) -> Self {
+        PositionBuilder { en_passant_target, ..self }
+    }
+    /// Checks that the position makes sense and turns it into a board state
+    pub fn build(self) -> Result<BoardState, PositionError> {
+        let mut state = BoardState {
+            board: self.board,
+            side_to_move: self.side_to_move,
+            black_castling: self.black_castling,
+            white_castling: self.white_castling,
+            en_passant_target: self.en_passant_target,
+            ..BoardState::new()
+        };
+        state.validate()?;
+        state.update_check_cache();
+        Ok(state)
+    }
+}
+
+/// Why a position can't come up in a game
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PositionError {
+    /// The side doesn't have exactly one king
+    KingCount(Colour),
+    /// The side not to move is in check, so its king could be taken
+    OpponentInCheck,
+    /// The side may castle even though its king or rook has left its starting square
+    InvalidCastling(Colour),
+    /// The en passant target isn't a square a pawn could just have skipped over
+    InvalidEnPassant,
+    /// A pawn is on the first or eighth rank, where it would already have been promoted or can't have come from
+    PawnOnBackRank(Coords),
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionError::KingCount(colour) => write!(f, "{colour} must have exactly one king"),
+            PositionError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            PositionError::InvalidCastling(colour) => write!(f, "{colour} can't castle with the king and rooks where they are"),
+            PositionError::InvalidEnPassant => write!(f, "no pawn can just have skipped over the en passant square"),
+            PositionError::PawnOnBackRank(cs) => write!(f, "there can't be a pawn on {cs}"),
+        }
+    }
+}
+
+impl Error for PositionError {}
+
+/// How to write the parts of a FEN string that programs disagree about
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct FenStyle {
+    /// Only write the en passant square when the side to move can actually capture on it, like polyglot and lichess do
+    pub legal_en_passant_only: bool,
+    pub clocks: FenClocks,
+}
+
+/// How the halfmove clock and fullmove number at the end of a FEN string are written
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FenClocks {
+    /// The clocks as numbers, left out if they aren't known
+    #[default]
+    Numbers,
+    /// `- -` in place of the clocks
+    Dashes,
+    /// Only the first four fields
+    Omitted,
+}
+
+impl FenStyle {
+    pub const fn new() -> Self {
+        FenStyle {
+            legal_en_passant_only: false,
+            clocks: FenClocks::Numbers,
+        }
+    }
+    pub const fn with_legal_en_passant_only(self, legal_en_passant_only: bool) -> Self {
+        FenStyle { legal_en_passant_only, ..self }
+    }
+    pub const fn with_clocks(self, clocks: FenClocks) -> Self {
+        FenStyle { clocks, ..self }
+    }
 }
 
 pub struct BoardStateFen<'a> {
     inner: &'a BoardState,
+    style: FenStyle,
+}
+
+impl BoardStateFen<'_> {
+    /// Writes the FEN in `style`, with dashes being the only clocks there can be for a board state
+    pub const fn with_style(self, style: FenStyle) -> Self {
+        BoardStateFen { style, ..self }
+    }
 }
 
 impl Display for BoardStateFen<'_> {
@@ -510,10 +1109,14 @@ impl Display for BoardStateFen<'_> {
             write!(f, "-")?;
         }
 
-        if let Some(en_passant_target) = self.inner.en_passant_target {
-            write!(f, " {en_passant_target}")
-        } else {
-            write!(f, " -")
+        match self.inner.en_passant_target {
+            Some(en_passant_target) if !self.style.legal_en_passant_only || self.inner.can_capture_en_passant() => write!(f, " {en_passant_target}")?,
+            _ => write!(f, " -")?,
+        }
+
+        match self.style.clocks {
+            FenClocks::Dashes => write!(f, " - -"),
+            FenClocks::Numbers | FenClocks::Omitted => Ok(()),
         }
     }
 }
@@ -530,4 +1133,209 @@ mod tests {
 
         assert_eq!(start_from_fen, BoardState::new());
     }
+
+    #[test]
+    fn test_fen_style() {
+        let legal_only = FenStyle::new().with_legal_en_passant_only(true);
+        let fen = |s| BoardState::from_fen(s).unwrap().display_fen().with_style(legal_only).to_string();
+        assert_eq!(fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3"), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq -");
+        assert_eq!(fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6"), "4k3/8/8/3pP3/8/8/8/4K3 w - d6");
+        // Taking would leave the king on the fifth rank open to the rook
+        assert_eq!(fen("4k3/8/8/K2pP2r/8/8/8/8 w - d6"), "4k3/8/8/K2pP2r/8/8/8/8 w - -");
+
+        let state = BoardState::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6").unwrap();
+        assert_eq!(state.display_fen().to_string(), "4k3/8/8/3pP3/8/8/8/4K3 w - d6");
+        assert_eq!(state.display_fen().with_style(legal_only.with_clocks(FenClocks::Dashes)).to_string(), "4k3/8/8/3pP3/8/8/8/4K3 w - d6 - -");
+    }
+
+    #[test]
+    fn test_unmake_move() {
+        let cs = |s| Coords::from_str(s).unwrap();
+        // Castling, en passant, a promotion with capture and a plain capture
+        let cases = [
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -", "e1", "c1", None),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6", "e5", "d6", None),
+            ("1n2k3/P7/8/8/8/8/8/4K3 w - -", "a7", "b8", Some(Piece::Knight)),
+            ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq -", "e4", "d5", None),
+        ];
+        for (fen, from, unto, promotion) in cases {
+            let original = BoardState::from_fen(fen).unwrap();
+            let mut state = original;
+            let (_, undo) = state.make_move_undoable(cs(from), cs(unto), promotion).unwrap();
+            assert_ne!(state, original);
+            state.unmake_move(undo);
+            assert_eq!(state, original, "{fen}");
+            assert_eq!(state.display_fen().to_string(), original.display_fen().to_string());
+            assert_eq!(state.in_check(state.side_to_move), original.in_check(original.side_to_move));
+        }
+    }
+
+    #[test]
+    fn test_legal_moves_from() {
+        let cs = |s| Coords::from_str(s).unwrap();
+        let state = BoardState::new();
+        let targets: Vec<_> = state.legal_moves_from(cs("g1")).into_iter().map(|mv| mv.unto.to_string()).collect();
+        assert_eq!(targets, ["h3", "f3"]);
+        assert!(state.legal_moves_from(cs("e8")).is_empty());
+        assert!(state.legal_moves_from(cs("e4")).is_empty());
+
+        // The knight is pinned
+        let state = BoardState::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - -").unwrap();
+        assert!(state.legal_moves_from(cs("e2")).is_empty());
+        for from in Coords::full_range() {
+            let all: Vec<_> = movegen::get_all_moves_ordered(&state, movegen::GenMode::Stable).into_iter().filter(|mv| mv.from == from).collect();
+            assert_eq!(state.legal_moves_from(from), all);
+        }
+    }
+
+    #[test]
+    fn test_move_path() {
+        let state = BoardState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -").unwrap();
+        let path = |from, unto| {
+            state
+                .move_path(Coords::from_str(from).unwrap(), Coords::from_str(unto).unwrap())
+                .map(|cs| cs.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(path("a1", "a4"), ["a2", "a3", "a4"]);
+        assert_eq!(path("h1", "e4"), ["g2", "f3", "e4"]);
+        assert_eq!(path("b1", "c3"), ["c3"]);
+        assert_eq!(path("e1", "g1"), ["f1", "g1", "g1", "f1"]);
+        assert_eq!(path("e8", "c8"), ["d8", "c8", "b8", "c8", "d8"]);
+        assert_eq!(path("e1", "f1"), ["f1"]);
+    }
+
+    #[test]
+    fn test_flipped() {
+        let state = BoardState::from_fen("r3k2r/pp3ppp/8/2pPp3/8/8/PPP2PPP/R3K1NR w Kkq e6").unwrap();
+        let flipped = state.flipped();
+        assert_eq!(flipped.display_fen().to_string(), "r3k1nr/ppp2ppp/8/8/2PpP3/8/PP3PPP/R3K2R b KQk e3");
+        assert_eq!(flipped.flipped(), state);
+
+        let canonical = flipped.canonical();
+        assert!(canonical.flipped);
+        assert_eq!(canonical.state, state);
+        let e3 = Coords::from_str("e3").unwrap();
+        let d4 = Coords::from_str("d4").unwrap();
+        let mv = Move::new(&flipped, d4, e3, None).unwrap();
+        assert!(mv.en_passant && mv.is_capture());
+        let translated = canonical.translate(mv);
+        assert_eq!((translated.from, translated.unto), (d4.flip_rank(), e3.flip_rank()));
+        assert_eq!(canonical.translate(translated), mv);
+        assert!(!state.canonical().flipped);
+    }
+
+    #[test]
+    fn test_checkers() {
+        let cs = |s| Coords::from_str(s).unwrap();
+
+        assert_eq!(BoardState::new().checkers(Colour::White).count(), 0);
+
+        let single = BoardState::from_fen("4k3/8/8/8/8/5n2/8/R3K3 w - -").unwrap();
+        assert_eq!(single.checkers(Colour::White).collect::<Vec<_>>(), [cs("f3")]);
+        assert_eq!(single.checkers(Colour::Black).count(), 0);
+
+        let double = BoardState::from_fen("4k3/8/8/8/1b6/5n2/8/4K3 w - -").unwrap();
+        assert_eq!(double.checkers(Colour::White).collect::<Vec<_>>(), [cs("f3"), cs("b4")]);
+    }
+
+    #[test]
+    fn test_position_builder() {
+        let cs = |s| Coords::from_str(s).unwrap();
+        let kings = PositionBuilder::new()
+            .with_piece(cs("e1"), Colour::White, Piece::King)
+            .with_piece(cs("e8"), Colour::Black, Piece::King);
+
+        let state = kings
+            .with_piece(cs("h1"), Colour::White, Piece::Rook)
+            .with_piece(cs("d5"), Colour::White, Piece::Pawn)
+            .with_piece(cs("e5"), Colour::Black, Piece::Pawn)
+            .with_castling(Colour::White, true, false)
+            .with_en_passant(Some(cs("e6")))
+            .build()
+            .unwrap();
+        assert_eq!(state, BoardState::from_fen("4k3/8/8/3Pp3/8/8/8/4K2R w K e6").unwrap());
+        let built = PositionBuilder::from(BoardState::new()).build().unwrap();
+        assert_eq!(built, BoardState::new());
+
+        assert_eq!(PositionBuilder::new().build(), Err(PositionError::KingCount(Colour::White)));
+        let extra_king = kings.with_piece(cs("a8"), Colour::Black, Piece::King);
+        assert_eq!(extra_king.build(), Err(PositionError::KingCount(Colour::Black)));
+        let check = kings.with_piece(cs("e2"), Colour::White, Piece::Queen);
+        assert_eq!(check.build(), Err(PositionError::OpponentInCheck));
+        assert!(check.with_side_to_move(Colour::Black).build().unwrap().in_check(Colour::Black));
+        assert_eq!(kings.with_castling(Colour::Black, false, true).build(), Err(PositionError::InvalidCastling(Colour::Black)));
+        assert_eq!(kings.with_en_passant(Some(cs("e6"))).build(), Err(PositionError::InvalidEnPassant));
+        assert_eq!(kings.with_piece(cs("c8"), Colour::White, Piece::Pawn).build(), Err(PositionError::PawnOnBackRank(cs("c8"))));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(BoardState::new().validate(), Ok(()));
+        let mut state = BoardState::new();
+        state.board.set(Coords::E1, Field::Empty);
+        assert_eq!(state.validate(), Err(PositionError::KingCount(Colour::White)));
+
+        // None of these used to be rejected, and the ones without a king panicked later on
+        for fen in [
+            "8/8/8/8/8/8/8/8 w - -",
+            "rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ -",
+            "4k3/8/8/8/8/8/8/4K1pR w K -",
+            "4k3/8/8/8/8/8/8/4K2R w Kq -",
+            "4k3/8/8/8/8/8/4P3/4K3 w - e3",
+            "4k3/8/8/8/8/8/8/4R2K w - -",
+        ] {
+            assert_eq!(BoardState::from_fen(fen), None, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_material() {
+        let start = BoardState::new();
+        let material = start.material(Colour::Black);
+        assert_eq!((material.pawns, material.knights, material.bishops, material.rooks, material.queens), (8, 2, 2, 2, 1));
+        assert_eq!(material.points(), 39);
+
+        let state = BoardState::from_fen("4k3/8/8/8/8/8/1P3P2/R3K1N1 w - -").unwrap();
+        assert_eq!(state.material(Colour::White).points(), 10);
+        assert_eq!(state.material(Colour::Black), MaterialCount::default());
+        let pawns: Vec<_> = state.piece_squares(Colour::White, Piece::Pawn).map(|cs| cs.to_string()).collect();
+        assert_eq!(pawns, ["b2", "f2"]);
+        assert_eq!(state.piece_squares(Colour::Black, Piece::King).count(), 1);
+    }
+
+    #[test]
+    fn test_bytes() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq -",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6",
+            "8/8/8/8/8/8/8/k1K5 b - -",
+        ] {
+            let state = BoardState::from_fen(fen).unwrap();
+            let bytes = state.to_bytes();
+            assert_eq!(BoardState::from_bytes(bytes), Some(state), "{fen}");
+            assert_eq!(BoardState::from_bytes(bytes).unwrap().display_fen().to_string(), fen);
+        }
+        assert_eq!(&BoardState::new().to_bytes()[32..], [0b11110, 0xff]);
+
+        let mut bytes = BoardState::new().to_bytes();
+        bytes[16] = 0x77;
+        assert_eq!(BoardState::from_bytes(bytes), None);
+        assert_eq!(BoardState::from_bytes([0; BYTES_LEN]), None);
+    }
+
+    #[test]
+    fn test_attackers() {
+        let cs = |s| Coords::from_str(s).unwrap();
+
+        // Attackers come in square order, and d4 is attacked by the queen as well as the pawn on e5
+        let state = BoardState::from_fen("4k3/8/3p4/4p3/8/5N2/8/3QR1K1 w - -").unwrap();
+        assert_eq!(state.attackers(cs("e5"), Colour::White).collect::<Vec<_>>(), [cs("e1"), cs("f3")]);
+        assert_eq!(state.attackers(cs("e5"), Colour::Black).collect::<Vec<_>>(), [cs("d6")]);
+        assert!(state.is_attacked(cs("d4"), Colour::White));
+        assert!(state.is_attacked(cs("d4"), Colour::Black));
+        assert!(!state.is_attacked(cs("a8"), Colour::White));
+    }
 }
@@ -0,0 +1,102 @@
+//! Bots that hardly think at all, as baselines to measure the real bots against and as easy opponents
+
+use crate::{
+    game::Game,
+    movegen::{get_all_moves, Move},
+    player::Player,
+};
+
+use super::rng::Rng;
+
+/// Plays a random legal move
+#[derive(Debug, Clone)]
+pub struct RandomBot {
+    rng: Rng,
+}
+
+impl RandomBot {
+    pub fn new(rng: Rng) -> Self {
+        RandomBot { rng }
+    }
+}
+
+impl Default for RandomBot {
+    fn default() -> Self {
+        Self::new(Rng::from_entropy())
+    }
+}
+
+impl Player for RandomBot {
+    fn make_move(&mut self, game: &Game) -> Option<Move> {
+        let moves = get_all_moves(game.board_state());
+        moves.get(self.rng.below(moves.len().max(1))).copied()
+    }
+    fn name(&self) -> String {
+        "talv random".to_owned()
+    }
+}
+
+/// Takes the most valuable piece it can, with the least valuable piece it can, and otherwise plays a random move
+///
+/// It doesn't look at what happens after the capture, so it happily takes a defended pawn with its queen.
+#[derive(Debug, Clone)]
+pub struct GreedyCaptureBot {
+    rng: Rng,
+}
+
+impl GreedyCaptureBot {
+    pub fn new(rng: Rng) -> Self {
+        GreedyCaptureBot { rng }
+    }
+}
+
+impl Default for GreedyCaptureBot {
+    fn default() -> Self {
+        Self::new(Rng::from_entropy())
+    }
+}
+
+impl Player for GreedyCaptureBot {
+    fn make_move(&mut self, game: &Game) -> Option<Move> {
+        let moves = get_all_moves(game.board_state());
+        let gain = |mv: &Move| {
+            let taken = mv.captured.map_or(0, |p| p.points() as i32) + mv.promotion.map_or(0, |p| p.points() as i32 - 1);
+            (taken, -(mv.piece.points() as i32))
+        };
+        let best = moves.iter().map(gain).max()?;
+        if best.0 == 0 {
+            return moves.get(self.rng.below(moves.len())).copied();
+        }
+        // Picks at random between equally good captures, so games don't all go the same way
+        let best_moves: Vec<_> = moves.into_iter().filter(|mv| gain(mv) == best).collect();
+        Some(best_moves[self.rng.below(best_moves.len())])
+    }
+    fn name(&self) -> String {
+        "talv greedy".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Coords;
+
+    #[test]
+    fn test_baseline_bots() {
+        let mut game = Game::from_fen("4k3/8/8/3q4/8/1r2N3/P7/4K3 w - - 0 1").unwrap();
+        let mv = GreedyCaptureBot::new(Rng::new(0)).make_move(&game).unwrap();
+        assert_eq!((mv.from, mv.unto), (Coords::E3, Coords::D5));
+        let moves = get_all_moves(game.board_state());
+        assert!(moves.contains(&RandomBot::new(Rng::new(0)).make_move(&game).unwrap()));
+
+        // With the queen gone, the rook is the most valuable piece left to take
+        game.make_move(Coords::E3, Coords::D5, None).unwrap();
+        game.make_move(Coords::E8, Coords::D8, None).unwrap();
+        let mv = GreedyCaptureBot::new(Rng::new(0)).make_move(&game).unwrap();
+        assert_eq!((mv.from, mv.unto), (Coords::A2, Coords::B3));
+
+        let mated = Game::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(RandomBot::default().make_move(&mated), None);
+        assert_eq!(GreedyCaptureBot::default().make_move(&mated), None);
+    }
+}
@@ -1,143 +1,1002 @@
-use std::{collections::HashMap, convert::identity};
+use std::{
+    convert::identity,
+    fmt::{self, Display},
+    ops::Neg,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use crate::{board::{Colour, Field, Piece}, boardstate::BoardState, location::{Coords, File, Rank}, movegen::{any_legal_moves, gen_legal_moves, get_all_moves}};
+use serde::{Deserialize, Serialize};
 
-pub type Move = (Coords, Coords, Option<Piece>);
-const NULL_MOVE: Move = (Coords::new(File::A, Rank::N1), Coords::new(File::A, Rank::N1), None);
+use crate::{board::{Colour, Field, MaterialCount, Piece}, boardstate::BoardState, game::{Eval, Game}, location::Coords, polyglot::{self, Book}, bots::rng::Rng, tablebase::{self, Dtm}, movegen::{any_legal_moves, gen_legal_moves, get_all_moves, get_all_moves_ordered, legal_moves, GenMode, NoMoreSpace, MAX_MOVES, PLACEHOLDER_MOVE}};
 
-type Transpositions = HashMap<BoardState, (usize, f32)>;
+pub use crate::movegen::Move;
+
+/// An evaluation in centipawns from the point of view of the side to move
+///
+/// Scores beyond [`Score::MATE_BOUND`] are forced mates, counting down from [`Score::MATE`] by one per ply.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Score(pub i32);
+
+impl Score {
+    pub const ZERO: Self = Score(0);
+    /// The score of having checkmated the opponent
+    pub const MATE: Self = Score(30_000);
+    /// Mates further away than this many plies are scored as normal evaluations
+    const MAX_MATE_PLIES: i32 = 1000;
+    /// Scores above this, or below its negation, are mates
+    pub const MATE_BOUND: Self = Score(Self::MATE.0 - Self::MAX_MATE_PLIES);
+    /// Beyond every score, used as the open ends of a search window
+    pub const INFINITE: Self = Score(32_000);
+
+    /// Rounds an evaluation in pawns to centipawns
+    pub fn from_pawns(pawns: f32) -> Self {
+        Score((pawns * 100.).round().clamp(-Self::MATE_BOUND.0 as f32, Self::MATE_BOUND.0 as f32) as i32)
+    }
+    pub fn pawns(self) -> f32 {
+        self.0 as f32 / 100.
+    }
+    /// The number of plies until mate, negative if the side to move is the one getting mated
+    pub fn mate_in(self) -> Option<i32> {
+        if self > Self::MATE_BOUND {
+            Some(Self::MATE.0 - self.0)
+        } else if self < -Self::MATE_BOUND {
+            Some(-(Self::MATE.0 + self.0))
+        } else {
+            None
+        }
+    }
+    /// The score of a position as seen from the position before it, where any mate is a ply further away
+    #[inline]
+    fn for_parent(self) -> Self {
+        match -self {
+            s if s > Self::MATE_BOUND => Score(s.0 - 1),
+            s if s < -Self::MATE_BOUND => Score(s.0 + 1),
+            s => s,
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        Score(-self.0)
+    }
+}
+
+/// Pawns with two decimals, or the number of moves to mate like `#3` or `#-2`
+impl Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mate_in() {
+            Some(plies) if plies > 0 => write!(f, "#{}", (plies + 1) / 2),
+            Some(plies) => write!(f, "#-{}", -plies / 2),
+            None => write!(f, "{:.2}", self.pawns()),
+        }
+    }
+}
+
+/// The same evaluation with mates counted in moves, from the same side's point of view
+impl From<Score> for Eval {
+    fn from(score: Score) -> Self {
+        match score.mate_in() {
+            Some(plies) if plies > 0 => Eval::MateIn((plies + 1) / 2),
+            Some(plies) => Eval::MateIn(plies / 2),
+            None => Eval::Centipawns(score.0),
+        }
+    }
+}
+
+/// Statistics about how the transposition table has been used
+///
+/// The entries are counted either in the whole table or in a sample of its first slots, see [`SearchInfo::tt_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TtStats {
+    /// Number of positions stored in the slots looked at
+    pub entries: usize,
+    /// Number of slots looked at
+    pub capacity: usize,
+    /// Lookups during the last search that found the position
+    pub hits: u64,
+    /// Lookups during the last search that did not find the position
+    pub misses: u64,
+    /// Stores during the last search that replaced an existing entry
+    pub overwrites: u64,
+    /// Entries written by the last search, by the one before it and by older ones
+    pub ages: [usize; 3],
+}
+
+impl TtStats {
+    /// How full the table is in permille, as reported by UCI's `hashfull`
+    pub fn hashfull(&self) -> usize {
+        if self.capacity == 0 {
+            0
+        } else {
+            (self.entries as u128 * 1000 / self.capacity as u128).min(1000) as usize
+        }
+    }
+}
+
+/// The most slots the transposition table gets, however many nodes the search may visit
+const MAX_TT_ENTRIES: usize = 1 << 18;
+/// How many slots from the start of the table are counted for the statistics reported during a search
+const TT_SAMPLE: usize = 1000;
+
+/// How a stored evaluation relates to the real value of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    /// The search failed high, the real value is at least this
+    Lower,
+    /// The search failed low, the real value is at most this
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TtEntry {
+    /// The full Zobrist key, to tell apart positions sharing a slot
+    key: u64,
+    depth: usize,
+    eval: Score,
+    bound: Bound,
+    /// The move that raised alpha, searched first the next time the position comes up
+    best: Option<Move>,
+    /// The iteration of the search it was written in
+    age: usize,
+}
+
+impl TtEntry {
+    /// The stored evaluation, if it was searched deep enough and its bound settles the `alpha`-`beta` window
+    fn cutoff(&self, depth: usize, alpha: Score, beta: Score) -> Option<Score> {
+        if self.depth < depth {
+            return None;
+        }
+        let usable = match self.bound {
+            Bound::Exact => true,
+            Bound::Lower => self.eval >= beta,
+            Bound::Upper => self.eval <= alpha,
+        };
+        usable.then_some(self.eval)
+    }
+}
+
+/// A fixed-size transposition table indexed by the low bits of the position's Zobrist key
+///
+/// It is shared by all threads of a search, so every slot has its own lock and the counters are atomic.
+struct Transpositions {
+    table: Vec<Mutex<Option<TtEntry>>>,
+    age: AtomicUsize,
+    /// The age of the first iteration of the current search
+    search_age: usize,
+    /// The age of the first iteration of the search before it
+    previous_age: usize,
+    /// Positions searched so far by all threads, including ones that were already in the table
+    nodes: AtomicUsize,
+    /// The number of nodes after which the search stops expanding them
+    max_nodes: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    overwrites: AtomicU64,
+    /// Set once the main thread is done, telling the helper threads to give up
+    stop: AtomicBool,
+    /// Set from outside the search to make every thread give up
+    abort: Option<Arc<AtomicBool>>,
+    started: Instant,
+    /// See [`SearchOptions::noise`]
+    noise: Score,
+    noise_seed: u64,
+}
+
+impl Transpositions {
+    /// Makes an empty table with room for `capacity` entries rounded up to a power of two
+    fn with_capacity(capacity: usize) -> Self {
+        Transpositions {
+            table: (0..capacity.max(1).next_power_of_two()).map(|_| Mutex::new(None)).collect(),
+            age: AtomicUsize::new(0),
+            search_age: 0,
+            previous_age: 0,
+            nodes: AtomicUsize::new(0),
+            max_nodes: 0,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            overwrites: AtomicU64::new(0),
+            stop: AtomicBool::new(false),
+            abort: None,
+            started: Instant::now(),
+            noise: Score::ZERO,
+            noise_seed: 0,
+        }
+    }
+    /// Makes a table with room for `max_nodes` entries, up to [`MAX_TT_ENTRIES`], ready for a search with `options`
+    fn new(options: &SearchOptions) -> Self {
+        let mut transpositions = Self::with_capacity(options.max_nodes.min(MAX_TT_ENTRIES));
+        transpositions.begin(options);
+        transpositions
+    }
+    /// Gets ready for a new search with `options`, keeping the entries of earlier searches
+    fn begin(&mut self, options: &SearchOptions) {
+        // Entries evaluated with other noise would pass it on to this search
+        if (options.noise, options.noise_seed) != (self.noise, self.noise_seed) {
+            self.clear();
+        }
+        // Helper threads may store entries before the main thread gets going, so the first iteration starts here
+        self.next_age();
+        self.previous_age = self.search_age;
+        self.search_age = self.age.load(Ordering::Relaxed);
+        self.nodes = AtomicUsize::new(0);
+        self.max_nodes = options.max_nodes;
+        self.hits = AtomicU64::new(0);
+        self.misses = AtomicU64::new(0);
+        self.overwrites = AtomicU64::new(0);
+        self.stop = AtomicBool::new(false);
+        self.abort = options.stop.clone();
+        self.started = Instant::now();
+        self.noise = options.noise;
+        self.noise_seed = options.noise_seed;
+    }
+    /// Empties the table
+    fn clear(&mut self) {
+        for slot in &mut self.table {
+            *slot.get_mut().unwrap() = None;
+        }
+        self.age = AtomicUsize::new(0);
+        self.search_age = 0;
+        self.previous_age = 0;
+    }
+    #[inline]
+    fn slot(&self, key: u64) -> MutexGuard<'_, Option<TtEntry>> {
+        self.table[key as usize & (self.table.len() - 1)].lock().unwrap()
+    }
+    /// Starts a new iteration of the search, which entries written from now on will be aged by
+    fn next_age(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+    /// The noise added to the evaluation of the position with `key`, the same every time within a search
+    fn noise(&self, key: u64) -> Score {
+        if self.noise == Score::ZERO {
+            return Score::ZERO;
+        }
+        let random = Rng::new(key ^ self.noise_seed).next_u64();
+        let range = 2 * self.noise.0 as u64 + 1;
+        Score((random % range) as i32 - self.noise.0)
+    }
+    /// Counts a node, returning whether the search has run out of them
+    #[inline]
+    fn count_node(&self) -> bool {
+        self.nodes.fetch_add(1, Ordering::Relaxed) + 1 >= self.max_nodes
+    }
+    #[inline]
+    fn nodes(&self) -> usize {
+        self.nodes.load(Ordering::Relaxed)
+    }
+    #[inline]
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.aborted()
+    }
+    #[inline]
+    fn aborted(&self) -> bool {
+        self.abort.as_ref().is_some_and(|abort| abort.load(Ordering::Relaxed))
+    }
+    fn probe(&self, key: u64) -> Option<TtEntry> {
+        let entry = self.peek(key);
+        let counter = if entry.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        entry
+    }
+    /// Looks up an entry without counting it as a hit or miss
+    fn peek(&self, key: u64) -> Option<TtEntry> {
+        self.slot(key).filter(|entry| entry.key == key)
+    }
+    /// Stores an entry unless its slot holds a deeper search of the same position, or of another position from the
+    /// current iteration
+    fn store(&self, key: u64, depth: usize, eval: Score, bound: Bound, best: Option<Move>) {
+        let age = self.age.load(Ordering::Relaxed);
+        let mut slot = self.slot(key);
+        match *slot {
+            Some(old) if old.key == key && old.depth > depth => return,
+            Some(old) if old.key != key && old.age == age && old.depth > depth => return,
+            Some(_) => {
+                self.overwrites.fetch_add(1, Ordering::Relaxed);
+            }
+            None => (),
+        }
+        *slot = Some(TtEntry { key, depth, eval, bound, best, age });
+    }
+    /// Goes through the whole table to count its entries by the search they were written in
+    fn stats(&self) -> TtStats {
+        self.stats_of(&self.table)
+    }
+    /// Like [`stats`](Self::stats), but only counting the first [`TT_SAMPLE`] slots, which is quick enough to do
+    /// while the search is running
+    fn sampled_stats(&self) -> TtStats {
+        self.stats_of(&self.table[..self.table.len().min(TT_SAMPLE)])
+    }
+    fn stats_of(&self, slots: &[Mutex<Option<TtEntry>>]) -> TtStats {
+        let mut ages = [0; 3];
+        for slot in slots {
+            if let Some(entry) = *slot.lock().unwrap() {
+                let bucket = if entry.age >= self.search_age {
+                    0
+                } else if entry.age >= self.previous_age {
+                    1
+                } else {
+                    2
+                };
+                ages[bucket] += 1;
+            }
+        }
+        TtStats {
+            entries: ages.iter().sum(),
+            capacity: slots.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            overwrites: self.overwrites.load(Ordering::Relaxed),
+            ages,
+        }
+    }
+}
+
+/// Follows the best moves stored in the table from the position after `first`, up to `max_len` moves in total
+///
+/// The line stops at positions missing from the table, at moves that aren't legal because two positions shared a
+/// key, and when a position repeats.
+fn principal_variation(transpositions: &Transpositions, state: &BoardState, first: Move, max_len: usize) -> Vec<Move> {
+    let mut state = *state;
+    let mut pv = vec![first];
+    let mut seen = vec![polyglot::key(&state)];
+    state.make_move(first.from, first.unto, first.promotion).unwrap();
+    while pv.len() < max_len {
+        let key = polyglot::key(&state);
+        if seen.contains(&key) {
+            break;
+        }
+        seen.push(key);
+        let Some(mv) = transpositions.peek(key).and_then(|entry| entry.best) else { break };
+        if !legal_moves(&state).any(|legal| legal == mv) {
+            break;
+        }
+        state.make_move(mv.from, mv.unto, mv.promotion).unwrap();
+        pv.push(mv);
+    }
+    pv
+}
 
 struct SearchResult {
     ordered_moves: Vec<Move>,
     nodes: usize,
-    eval: f32,
+    /// Evaluations of the ordered moves, only exact for the first `multipv` of them
+    evals: Vec<Score>,
 }
 
-fn start_search(state: &BoardState, moves: &[Move], depth: usize, transpositions: &mut Transpositions, max_nodes: usize) -> SearchResult {
+fn start_search<F: FnMut(SearchEvent)>(state: &mut BoardState, moves: &[Move], depth: usize, multipv: usize, transpositions: &Transpositions, params: &EvalParams, on_event: &mut F) -> SearchResult {
     assert_ne!(depth, 0);
 
     let mut evals = Vec::with_capacity(moves.len());
     let mut ordered_moves = Vec::with_capacity(moves.len());
-    for &(f, t, prm) in moves {
-        let mut new_state = state.clone();
-        new_state.make_move(f, t, prm).unwrap();
-
-        let beta = evals.get(0).copied().unwrap_or(f32::NAN);
-        let eval = -search(&new_state, f32::NAN, -beta, depth-1, transpositions, max_nodes);
+    for (i, &mv) in moves.iter().enumerate() {
+        if transpositions.stopped() {
+            break;
+        }
+        on_event(SearchEvent::CurrMove(CurrMove {
+            depth,
+            mv,
+            number: i + 1,
+            total: moves.len(),
+        }));
+        let (_, undo) = state.make_move_undoable(mv.from, mv.unto, mv.promotion).unwrap();
+        // A move only needs an exact evaluation if it could be among the `multipv` best
+        let alpha = evals.get(multipv - 1).copied().unwrap_or(-Score::INFINITE);
+        let eval = search(state, -Score::INFINITE, -alpha, depth-1, transpositions, params).for_parent();
+        state.unmake_move(undo);
 
-        let i = evals.binary_search_by(|e| eval.total_cmp(e)).unwrap_or_else(identity);
+        let i = evals.binary_search_by(|e| eval.cmp(e)).unwrap_or_else(identity);
         evals.insert(i, eval);
-        ordered_moves.insert(i, (f, t, prm));
+        ordered_moves.insert(i, mv);
     }
 
     SearchResult {
-        nodes: transpositions.len(),
+        nodes: transpositions.nodes(),
         ordered_moves,
-        eval: evals.get(0).copied().unwrap_or(0.),
+        evals,
     }
 }
-fn search(state: &BoardState, alpha: f32, beta: f32, depth: usize, transpositions: &mut Transpositions, max_nodes: usize) -> f32 {
-    if let Some((d, v)) = transpositions.get(state).copied() {
-        if d >= depth {
+/// Searches `state` by making moves on it and taking them back, it is left the way it was
+fn search(state: &mut BoardState, alpha: Score, beta: Score, depth: usize, transpositions: &Transpositions, params: &EvalParams) -> Score {
+    let key = polyglot::key(state);
+    let mut tt_move = None;
+    if let Some(entry) = transpositions.probe(key) {
+        if let Some(v) = entry.cutoff(depth, alpha, beta) {
             return v;
         }
+        tt_move = entry.best;
     }
 
-    let v = search_inner(state, alpha, beta, depth, transpositions, max_nodes);
-    transpositions.insert(state.clone(), (depth, v));
-    v
-}
-fn search_inner(state: &BoardState, mut alpha: f32, beta: f32, depth: usize, transpositions: &mut Transpositions, max_nodes: usize) -> f32 {
-    if depth == 0 || transpositions.len() >= max_nodes {
-        let evaluation;
-        if let Some((_, v)) = transpositions.get(state).copied() {
-            evaluation = v
-        } else {
-            evaluation = eval(state);
+    // Endgames with three pieces are looked up, which also sees mates too far away for the search
+    if let Some(v) = tablebase_score(state) {
+        transpositions.count_node();
+        return v;
+    }
+
+    if transpositions.count_node() || depth == 0 {
+        let mut v = eval(state, params);
+        if v.mate_in().is_none() {
+            let noisy = v.0 + transpositions.noise(key).0;
+            v = Score(noisy.clamp(-Score::MATE_BOUND.0, Score::MATE_BOUND.0));
         }
-        return evaluation;
+        transpositions.store(key, 0, v, Bound::Exact, None);
+        return v;
     }
 
+    let (v, best) = search_inner(state, alpha, beta, depth, tt_move, transpositions, params);
+    // A search cut short doesn't give a value worth keeping
+    if transpositions.stopped() {
+        return v;
+    }
+    let bound = if v >= beta {
+        Bound::Lower
+    } else if v <= alpha {
+        Bound::Upper
+    } else {
+        Bound::Exact
+    };
+    transpositions.store(key, depth, v, bound, best);
+    v
+}
+/// The exact score of a position with three pieces on the board, if the tablebase covers it
+fn tablebase_score(state: &BoardState) -> Option<Score> {
+    if state.occupancy().count_ones() != 3 {
+        return None;
+    }
+    Some(match tablebase::probe(state)? {
+        Dtm::Win(plies) => Score(Score::MATE.0 - plies as i32),
+        Dtm::Draw => Score::ZERO,
+        Dtm::Loss(plies) => -Score(Score::MATE.0 - plies as i32),
+    })
+}
+/// Returns the evaluation along with the move that raised alpha, if any did
+fn search_inner(state: &mut BoardState, mut alpha: Score, beta: Score, depth: usize, tt_move: Option<Move>, transpositions: &Transpositions, params: &EvalParams) -> (Score, Option<Move>) {
     let mut buf;
+    let mut overflow;
     let possible_moves = {
-        const MAX_MOVES: usize = 200;
-        buf = [NULL_MOVE; MAX_MOVES];
+        buf = [PLACEHOLDER_MOVE; MAX_MOVES];
         let mut slice = &mut buf[..];
 
-        gen_legal_moves(&mut slice, state).expect("max moves exceeded");
-        let unused = slice.len(); 
-        &buf[..MAX_MOVES - unused]
+        match gen_legal_moves(&mut slice, state) {
+            Ok(()) => {
+                let unused = slice.len();
+                &mut buf[..MAX_MOVES - unused]
+            }
+            // Should the bound ever be wrong, spill onto the heap rather than give up mid-game
+            Err(NoMoreSpace) => {
+                overflow = get_all_moves(state);
+                &mut overflow[..]
+            }
+        }
     };
 
     if possible_moves.is_empty() {
-        return eval(state);
+        return (eval(state, params), None);
+    }
+    // The best move from an earlier search of the position is likely to cause a cutoff soonest
+    if let Some(i) = tt_move.and_then(|tt_move| possible_moves.iter().position(|&mv| mv == tt_move)) {
+        possible_moves[..=i].rotate_right(1);
     }
 
-    for &(f, t, prm) in possible_moves {
-        let mut new_state = state.clone();
-        new_state.make_move(f, t, prm).unwrap();
-
-        let eval = -search(&new_state, -beta, -alpha, depth-1, transpositions, max_nodes);
+    let mut best = None;
+    for &mut mv in possible_moves {
+        let (_, undo) = state.make_move_undoable(mv.from, mv.unto, mv.promotion).unwrap();
+        let eval = search(state, -beta, -alpha, depth-1, transpositions, params).for_parent();
+        state.unmake_move(undo);
+        if transpositions.stopped() {
+            break;
+        }
 
-        if alpha.is_nan() || eval > alpha {
-            // This will give `eval` if alpha is nan
-            alpha = alpha.max(eval);
+        if eval > alpha {
+            alpha = eval;
+            best = Some(mv);
             if beta <= alpha {
                 break;
             }
         }
     }
 
-    alpha
+    (alpha, best)
+}
+
+/// Something that happened during the search
+#[derive(Debug, Clone, Copy)]
+pub enum SearchEvent<'a> {
+    /// A move at the root is about to be searched
+    CurrMove(CurrMove),
+    /// An iteration has finished
+    Iteration(SearchInfo<'a>),
+}
+
+/// The root move currently being searched, like UCI's `info currmove` and `currmovenumber`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrMove {
+    pub depth: usize,
+    pub mv: Move,
+    /// Counted from 1
+    pub number: usize,
+    /// How many moves there are at the root
+    pub total: usize,
+}
+
+/// Information about a finished iteration of the search
+#[derive(Debug, Clone, Copy)]
+pub struct SearchInfo<'a> {
+    pub depth: usize,
+    pub eval: Score,
+    /// All moves ordered from best to worst as of this iteration
+    pub ranked_moves: &'a [Move],
+    /// The best moves with their lines, as many as asked for with `multipv`
+    pub lines: &'a [RankedLine],
+    /// Positions searched so far, counting every time one is reached
+    pub nodes: usize,
+    /// Time since the search started
+    pub elapsed: Duration,
+    /// Counted in a sample of the first slots of the table, so `capacity` may be less than its size
+    pub tt_stats: &'a TtStats,
+}
+
+impl SearchInfo<'_> {
+    /// Nodes searched per second, if any time has passed
+    pub fn nps(&self) -> Option<u64> {
+        (self.nodes as u128 * 1000).checked_div(self.elapsed.as_millis()).map(|nps| nps as u64)
+    }
+}
+
+/// A root move with its own evaluation and the line the search expects to follow it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedLine {
+    pub eval: Score,
+    /// Starts with the root move
+    pub pv: Vec<Move>,
+}
+
+/// What a search found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ranking {
+    /// The evaluation of the best move
+    pub eval: Score,
+    /// All moves ordered from best to worst
+    pub moves: Vec<Move>,
+    /// The best `multipv` moves, each with its own evaluation and line, best first
+    pub lines: Vec<RankedLine>,
+}
+
+/// How far and how a search goes
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub max_depth: usize,
+    /// The number of positions after which the search stops going deeper
+    pub max_nodes: usize,
+    /// How many of the best moves get exact evaluations and lines
+    pub multipv: usize,
+    /// With more than one thread, the extra threads search the same position alongside the main one and share the
+    /// transposition table with it (lazy SMP), so the main thread finds more of its positions already searched
+    pub threads: usize,
+    /// Setting this stops the search as soon as possible, it then gives what it found in the last finished iteration
+    pub stop: Option<Arc<AtomicBool>>,
+    /// Evaluations are off by up to this much either way, to make the bot weaker and less predictable
+    pub noise: Score,
+    /// Decides the noise of each position, so another seed gives other noise
+    pub noise_seed: u64,
+}
+
+impl SearchOptions {
+    pub fn new(max_depth: usize, max_nodes: usize) -> Self {
+        SearchOptions {
+            max_depth,
+            max_nodes,
+            multipv: 1,
+            threads: 1,
+            stop: None,
+            noise: Score::ZERO,
+            noise_seed: 0,
+        }
+    }
+    pub fn with_multipv(self, multipv: usize) -> Self {
+        Self { multipv: multipv.max(1), ..self }
+    }
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self { threads: threads.max(1), ..self }
+    }
+    /// Makes the search stop once `stop` is set
+    pub fn with_stop(self, stop: Arc<AtomicBool>) -> Self {
+        Self { stop: Some(stop), ..self }
+    }
+    pub fn with_noise(self, noise: Score, noise_seed: u64) -> Self {
+        Self { noise, noise_seed, ..self }
+    }
+    /// Limits the search to what `skill` allows, with noise from `noise_seed`
+    ///
+    /// Enough lines are searched exactly for [`Skill::choose`] to pick a worse move from.
+    pub fn with_skill(self, skill: Skill, noise_seed: u64) -> Self {
+        Self {
+            max_depth: self.max_depth.min(skill.max_depth()),
+            max_nodes: self.max_nodes.min(skill.max_nodes()),
+            multipv: self.multipv.max(Skill::MISTAKE_LINES),
+            noise: skill.noise(),
+            noise_seed,
+            ..self
+        }
+    }
+}
+
+/// How well the bot plays, from 0 to [`Skill::MAX`] at full strength
+///
+/// Lower levels search shallower with noisier evaluations and sometimes play a move that isn't the best.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Skill(u8);
+
+impl Skill {
+    pub const MAX: Self = Skill(20);
+    /// How many of the best moves a mistake can be
+    const MISTAKE_LINES: usize = 4;
+
+    /// Levels above [`Skill::MAX`] are the same as it
+    pub fn new(level: u8) -> Self {
+        Skill(level.min(Self::MAX.0))
+    }
+    pub fn level(self) -> u8 {
+        self.0
+    }
+    /// How many levels below full strength it is
+    fn weakness(self) -> i32 {
+        (Self::MAX.0 - self.0) as i32
+    }
+    pub fn max_depth(self) -> usize {
+        if self == Self::MAX { usize::MAX } else { 1 + self.0 as usize / 3 }
+    }
+    pub fn max_nodes(self) -> usize {
+        if self == Self::MAX { usize::MAX } else { 500 << (self.0 / 2) }
+    }
+    /// How far off the evaluation of a position may be
+    pub fn noise(self) -> Score {
+        Score(self.weakness() * 10)
+    }
+    /// How likely it is to play a worse move than the best one it found
+    pub fn mistake_chance(self) -> f64 {
+        self.weakness() as f64 / 40.
+    }
+    /// How much worse than the best move a mistake may be
+    pub fn max_mistake(self) -> Score {
+        Score(self.weakness() * 20)
+    }
+    /// Picks the move to play from a search, usually the best one but now and then a worse one
+    pub fn choose(self, ranking: &Ranking, rng: &mut Rng) -> Option<Move> {
+        let best = *ranking.moves.first()?;
+        if rng.unit() >= self.mistake_chance() {
+            return Some(best);
+        }
+        let good_enough: Vec<_> = ranking
+            .lines
+            .iter()
+            .skip(1)
+            .filter(|line| line.eval.0 >= ranking.eval.0 - self.max_mistake().0)
+            .filter_map(|line| line.pv.first())
+            .collect();
+        if good_enough.is_empty() {
+            return Some(best);
+        }
+        Some(*good_enough[rng.below(good_enough.len())])
+    }
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self::MAX
+    }
+}
+
+/// Searches for the best moves, giving exact evaluations and lines for the best `multipv` of them
+pub fn get_moves_ranked(state: &BoardState, options: &SearchOptions) -> Ranking {
+    get_moves_ranked_with_info(state, options, &EvalParams::default(), |_| ())
+}
+
+/// Like [`get_moves_ranked`], but evaluates with `params` and reports the progress of the search to `on_event`
+pub fn get_moves_ranked_with_info<F: FnMut(SearchEvent)>(state: &BoardState, options: &SearchOptions, params: &EvalParams, on_event: F) -> Ranking {
+    run_search(state, options, params, &Transpositions::new(options), on_event)
+}
+
+/// Keeps the transposition table from one search to the next, so searching the positions of a game one after
+/// another builds on what was found for the earlier ones instead of starting over every move
+pub struct Searcher {
+    transpositions: Transpositions,
+    /// The weights the evaluations in the table were made with
+    params: EvalParams,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new(MAX_TT_ENTRIES)
+    }
+}
+
+impl Searcher {
+    /// Makes a searcher whose table has room for `capacity` entries rounded up to a power of two
+    pub fn new(capacity: usize) -> Self {
+        Searcher {
+            transpositions: Transpositions::with_capacity(capacity),
+            params: EvalParams::default(),
+        }
+    }
+    /// Forgets everything found so far, like before a new game
+    pub fn clear(&mut self) {
+        self.transpositions.clear();
+    }
+    /// Like [`get_moves_ranked_with_info`], but starting from the table left by the last search
+    ///
+    /// The table is cleared first if the weights or the noise changed, as its evaluations would no longer fit.
+    pub fn get_moves_ranked_with_info<F: FnMut(SearchEvent)>(&mut self, state: &BoardState, options: &SearchOptions, params: &EvalParams, on_event: F) -> Ranking {
+        if *params != self.params {
+            self.clear();
+            self.params = params.clone();
+        }
+        self.transpositions.begin(options);
+        run_search(state, options, params, &self.transpositions, on_event)
+    }
+    /// How the table looks after the last search, going through all of it
+    pub fn tt_stats(&self) -> TtStats {
+        self.transpositions.stats()
+    }
 }
 
-pub fn get_moves_ranked(state: &BoardState, max_depth: usize, max_nodes: usize) -> (f32, Vec<Move>) {
-    let possible_moves = get_all_moves(state);
+fn run_search<F: FnMut(SearchEvent)>(state: &BoardState, options: &SearchOptions, params: &EvalParams, transpositions: &Transpositions, mut on_event: F) -> Ranking {
+    let possible_moves = get_all_moves_ordered(state, GenMode::Stable);
+    let (max_depth, multipv) = (options.max_depth, options.multipv.max(1));
 
-    let mut eval = f32::NAN;
-    let mut moves = possible_moves;
+    thread::scope(|scope| {
+        for helper in 1..options.threads {
+            let moves = possible_moves.clone();
+            scope.spawn(move || help_search(*state, moves, helper, max_depth, multipv, transpositions, params));
+        }
+        let ranking = main_search(*state, possible_moves, max_depth, multipv, transpositions, params, &mut on_event);
+        transpositions.stop.store(true, Ordering::Relaxed);
+        ranking
+    })
+}
 
-    let mut transpositions = Transpositions::with_capacity(1024);
+/// Searches one depth further each iteration, reporting the results
+fn main_search<F: FnMut(SearchEvent)>(mut state: BoardState, mut moves: Vec<Move>, max_depth: usize, multipv: usize, transpositions: &Transpositions, params: &EvalParams, on_event: &mut F) -> Ranking {
+    let mut eval = Score::ZERO;
+    let mut lines = Vec::new();
 
     for depth in 1..=max_depth {
-        let res = start_search(state, &moves, depth, &mut transpositions, max_nodes);
+        if depth > 1 {
+            transpositions.next_age();
+        }
+        let res = start_search(&mut state, &moves, depth, multipv, transpositions, params, on_event);
+        // An aborted iteration hasn't looked at every move, so the last finished one is kept
+        if transpositions.aborted() {
+            break;
+        }
 
         moves = res.ordered_moves;
-        eval = res.eval;
-        if res.nodes > max_nodes {
+        eval = res.evals.first().copied().unwrap_or(Score::ZERO);
+        lines = moves
+            .iter()
+            .zip(&res.evals)
+            .take(multipv)
+            .map(|(&mv, &eval)| RankedLine {
+                eval,
+                pv: principal_variation(transpositions, &state, mv, depth),
+            })
+            .collect();
+        on_event(SearchEvent::Iteration(SearchInfo {
+            depth,
+            eval,
+            ranked_moves: &moves,
+            lines: &lines,
+            nodes: res.nodes,
+            elapsed: transpositions.started.elapsed(),
+            tt_stats: &transpositions.sampled_stats(),
+        }));
+        if res.nodes >= transpositions.max_nodes {
+            break;
+        }
+    }
+
+    Ranking { eval, moves, lines }
+}
+
+/// Searches on a helper thread until the main thread is done, only to fill the transposition table
+fn help_search(mut state: BoardState, mut moves: Vec<Move>, helper: usize, max_depth: usize, multipv: usize, transpositions: &Transpositions, params: &EvalParams) {
+    // Every other helper starts a depth ahead, so the threads don't all search the same depth in lockstep
+    for depth in 1 + helper % 2..=max_depth {
+        if transpositions.stopped() {
             break;
         }
+        moves = start_search(&mut state, &moves, depth, multipv, transpositions, params, &mut |_| ()).ordered_moves;
     }
+}
+
+/// An opening book for the bot to play from at the start of games
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpeningBook {
+    pub book: Arc<Book>,
+    /// The book is only used up to this move number
+    pub max_moves: u64,
+}
+
+impl OpeningBook {
+    pub fn new(book: Arc<Book>, max_moves: u64) -> Self {
+        OpeningBook { book, max_moves }
+    }
+    /// A book move for the game's position picked at random by the weights, if it's early enough in the game
+    pub fn pick(&self, game: &Game, rng: &mut Rng) -> Option<Move> {
+        if game.fullmove_number() > self.max_moves {
+            return None;
+        }
+        self.book.weighted_move(game.board_state(), rng.next_u64())
+    }
+}
 
-    (eval, moves)
+/// The weights of the evaluation function
+///
+/// Any weight left out of a TOML file keeps its default value, e.g. `queen = 10.0` is a complete file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvalParams {
+    pub pawn: f32,
+    /// Added to a pawn's value for how far it has advanced, scaled by the number of ranks raised to `pawn_advance_exponent`
+    pub pawn_advance: f32,
+    pub pawn_advance_exponent: f32,
+    pub knight: f32,
+    pub bishop: f32,
+    pub rook: f32,
+    pub queen: f32,
+    /// Bonus for having the opponent in check
+    pub check_bonus: f32,
+    /// Endgame bonus for each step the king is closer to the centre
+    pub king_activity: f32,
+    /// Endgame bonus for each step the own king is closer to the square in front of a passed pawn than the enemy king is
+    pub passer_king_distance: f32,
+    /// Endgame bonus for a passed pawn the enemy king can't catch by the rule of the square
+    pub unstoppable_passer: f32,
+    /// Scales the bonuses from the piece-square tables for where each piece stands, 0 turns them off
+    pub piece_square: f32,
+    /// Bonus against a lone king for each step it is pushed from the centre, and half of it for each step the kings
+    /// are closer, so a rook or queen can drive it to the edge and mate it
+    pub mop_up: f32,
+    /// Bonus in pawn endgames for having the opposition, the kings facing each other with the opponent to move
+    pub opposition: f32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            pawn: 1.,
+            pawn_advance: 0.1,
+            pawn_advance_exponent: 1.1,
+            knight: 3.,
+            bishop: 3.2,
+            rook: 5.,
+            queen: 9.,
+            check_bonus: 0.3,
+            king_activity: 0.2,
+            passer_king_distance: 0.2,
+            unstoppable_passer: 5.,
+            piece_square: 1.,
+            mop_up: 0.2,
+            opposition: 0.2,
+        }
+    }
+}
+
+impl EvalParams {
+    pub fn from_toml(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("all weights are plain numbers")
+    }
+}
+
+/// Decides when the bot should give up or settle for a draw, based on its evaluations of the previous moves
+#[derive(Debug, Clone)]
+pub struct Conduct {
+    /// Evaluation at or below which the bot considers its position hopeless
+    pub resign_eval: Score,
+    /// How many moves in a row the position has to be hopeless before resigning
+    pub resign_moves: usize,
+    /// The evaluation has to be within this of zero for the position to be considered dead equal
+    pub draw_margin: Score,
+    /// How many moves in a row the position has to be dead equal before offering a draw
+    pub draw_moves: usize,
+    evals: Vec<Score>,
+}
+
+impl Default for Conduct {
+    fn default() -> Self {
+        Conduct {
+            resign_eval: Score(-1600),
+            resign_moves: 4,
+            draw_margin: Score(32),
+            draw_moves: 10,
+            evals: Vec::new(),
+        }
+    }
+}
+
+impl Conduct {
+    /// Record the bot's evaluation of the position after it has made a move
+    pub fn record_eval(&mut self, eval: Score) {
+        self.evals.push(eval);
+    }
+    /// Forgets the recorded evaluations, e.g. when a new game starts
+    pub fn forget_evals(&mut self) {
+        self.evals.clear();
+    }
+    fn last_evals(&self, n: usize) -> Option<&[Score]> {
+        if n == 0 || self.evals.len() < n {
+            None
+        } else {
+            Some(&self.evals[self.evals.len() - n..])
+        }
+    }
+    pub fn should_resign(&self) -> bool {
+        self.last_evals(self.resign_moves)
+            .is_some_and(|evals| evals.iter().all(|&e| e <= self.resign_eval))
+    }
+    /// Whether to offer a draw, `repeating` should be true if the game is heading towards repetition
+    pub fn should_offer_draw(&self, repeating: bool) -> bool {
+        let draw_moves = if repeating { 1 } else { self.draw_moves };
+        self.last_evals(draw_moves)
+            .is_some_and(|evals| evals.iter().all(|&e| e.0.abs() <= self.draw_margin.0))
+    }
+    /// Whether to accept a draw offered by the opponent
+    pub fn accepts_draw(&self) -> bool {
+        self.evals.last().is_some_and(|&e| e <= self.draw_margin)
+    }
 }
 
 /// Positive value => good for current last player
-fn eval(state: &BoardState) -> f32 {
+pub(super) fn eval(state: &BoardState, params: &EvalParams) -> Score {
     if !any_legal_moves(state) {
         if state.in_check(state.side_to_move) {
             // I'm in a checkmate!!! oh no!
-            return f32::NEG_INFINITY;
+            return -Score::MATE;
         } else {
             // draw :/
-            return 0.;
+            return Score::ZERO;
         }
     }
+    if is_wrong_bishop_draw(state) {
+        return Score::ZERO;
+    }
     let mut checking_bonus = 0.;
     if state.in_check(!state.side_to_move) {
-        checking_bonus += 10.;
-        let mut new_state = state.clone();
+        checking_bonus += params.check_bonus;
+        let mut new_state = *state;
         new_state.side_to_move = !new_state.side_to_move;
         if !any_legal_moves(&new_state) {
-            return f32::INFINITY;
+            return Score::MATE;
         }
     }
 
-    eval_pieces(state) + checking_bonus
+    Score::from_pawns(eval_pieces(state, params) + checking_bonus)
 }
-fn eval_pieces(state: &BoardState) -> f32 {
+/// Number of pieces in the starting position, which the material balance is scaled to
+const FULL_BOARD: f32 = 32.;
+/// Non-pawn material of both sides in the starting position, counting minor pieces as 1, rooks as 2 and queens as 4
+const OPENING_PHASE: u32 = 24;
+
+/// The material balance and endgame terms in pawns, scaled up by how many pieces have come off the board
+///
+/// It is the average value per piece on the board times [`FULL_BOARD`], so it's the plain balance in the starting
+/// position and trading down is worth more the further ahead a side is.
+fn eval_pieces(state: &BoardState, params: &EvalParams) -> f32 {
     let mut piece_difference = 0.;
     let mut piece_total = 0.;
+    let mut king_safety = 0.;
+    let mut phase = 0;
     for cs in Coords::full_range() {
         match state.board.get(cs) {
             Field::Empty => (),
@@ -150,27 +1009,587 @@ fn eval_pieces(state: &BoardState) -> f32 {
                     Colour::Black => 7 - r,
                 };
 
-                let value = piece_value(f, r, p);
-                if c == state.side_to_move {
-                    piece_difference += value;
-                } else {
-                    piece_difference -= value;
+                let value = piece_value(f, r, p, params);
+                let sign = if c == state.side_to_move { 1. } else { -1. };
+                piece_difference += sign * value;
+                if p == Piece::King {
+                    king_safety += sign * params.piece_square * square_bonus(&KING_SQUARES, f, r);
                 }
+                phase += match p {
+                    Piece::Knight | Piece::Bishop => 1,
+                    Piece::Rook => 2,
+                    Piece::Queen => 4,
+                    Piece::Pawn | Piece::King => 0,
+                };
+            }
+        }
+    }
+
+    // Tapers from 0 with all pieces on the board to 1 with only kings and pawns left
+    let endgame = 1. - phase.min(OPENING_PHASE) as f32 / OPENING_PHASE as f32;
+    // Hiding the king only matters while there are pieces to attack it, after that it should come out
+    piece_difference += (1. - endgame) * king_safety;
+    if endgame > 0. {
+        let side = state.side_to_move;
+        piece_difference += endgame * (endgame_terms(state, side, params) - endgame_terms(state, !side, params));
+    }
+    piece_difference * FULL_BOARD / piece_total
+}
+
+/// King activity, passed pawn, mating and opposition terms for `side`, which only matter once the pieces have come off
+fn endgame_terms(state: &BoardState, side: Colour, params: &EvalParams) -> f32 {
+    let mut own_king = None;
+    let mut enemy_king = None;
+    let mut own_pawns = Vec::new();
+    let mut enemy_pawns = Vec::new();
+    let mut own_has_pieces = false;
+    let mut own_has_major = false;
+    let mut enemy_has_pieces = false;
+    for cs in Coords::full_range() {
+        if let Field::Occupied(c, p) = state.board.get(cs) {
+            match (c == side, p) {
+                (true, Piece::King) => own_king = Some(cs),
+                (false, Piece::King) => enemy_king = Some(cs),
+                (true, Piece::Pawn) => own_pawns.push(cs),
+                (false, Piece::Pawn) => enemy_pawns.push(cs),
+                (true, p) => {
+                    own_has_pieces = true;
+                    own_has_major |= matches!(p, Piece::Rook | Piece::Queen);
+                }
+                (false, _) => enemy_has_pieces = true,
+            }
+        }
+    }
+    let (Some(own_king), Some(enemy_king)) = (own_king, enemy_king) else {
+        return 0.;
+    };
+    // Ranks as seen from `side`, so pawns always move up
+    let relative = |r: i8| match side {
+        Colour::White => r,
+        Colour::Black => 7 - r,
+    };
+
+    let mut score = params.king_activity * (3 - centre_distance(own_king)) as f32;
+    if own_has_major && !enemy_has_pieces && enemy_pawns.is_empty() {
+        // The lone king can only be mated on the edge, and the own king has to come help
+        let kings_closer = 7 - own_king.king_distance(enemy_king) as i8;
+        score += params.mop_up * (centre_distance(enemy_king) as f32 + kings_closer as f32 / 2.);
+    }
+    let (file_apart, rank_apart) = own_king.sub(enemy_king);
+    let facing = matches!((file_apart.abs(), rank_apart.abs()), (0, 2) | (2, 0));
+    if facing && !own_has_pieces && !enemy_has_pieces && state.side_to_move != side {
+        // The king to move has to give way
+        score += params.opposition;
+    }
+    for &pawn in &own_pawns {
+        let (f, r) = pawn.i8_tuple();
+        let passed = enemy_pawns.iter().all(|&enemy| {
+            let (ef, er) = enemy.i8_tuple();
+            (ef - f).abs() > 1 || relative(er) <= relative(r)
+        });
+        if !passed {
+            continue;
+        }
+
+        let forward = match side {
+            Colour::White => 1,
+            Colour::Black => -1,
+        };
+        // Pawns are never on the last rank, so the square in front is always on the board
+        let stop = pawn.add(0, forward).unwrap();
+        let king_distance = |king: Coords, square| king.king_distance(square) as i8;
+        score += params.passer_king_distance * (king_distance(enemy_king, stop) - king_distance(own_king, stop)) as f32;
+
+        if !enemy_has_pieces {
+            let promotion = Coords::from_u8_tuple(f, relative(7)).unwrap();
+            // From the starting rank the first move can be a double step
+            let pawn_moves = (7 - relative(r)).min(5);
+            let mut king_moves = king_distance(enemy_king, promotion);
+            if state.side_to_move != side {
+                king_moves -= 1;
+            }
+            if king_moves > pawn_moves {
+                score += params.unstoppable_passer;
             }
         }
     }
-    piece_difference / piece_total
+    score
+}
+
+/// Whether it's a king, a bishop and rook pawns against a lone king that has reached the corner the pawns promote on,
+/// which the bishop can't drive it out of when it's the wrong colour
+fn is_wrong_bishop_draw(state: &BoardState) -> bool {
+    let (white, black) = (state.material(Colour::White), state.material(Colour::Black));
+    let (strong, material) = match (white, black) {
+        (white, lone) if lone == MaterialCount::default() => (Colour::White, white),
+        (lone, black) if lone == MaterialCount::default() => (Colour::Black, black),
+        _ => return false,
+    };
+    if material != (MaterialCount { pawns: material.pawns, bishops: 1, ..MaterialCount::default() }) || material.pawns == 0 {
+        return false;
+    }
+
+    let mut pawns = state.piece_squares(strong, Piece::Pawn);
+    let file = pawns.next().unwrap().f();
+    if !matches!(file.i8(), 0 | 7) || pawns.any(|pawn| pawn.f() != file) {
+        return false;
+    }
+    let last_rank = match strong {
+        Colour::White => 7,
+        Colour::Black => 0,
+    };
+    let promotion = Coords::from_u8_tuple(file.i8(), last_rank).unwrap();
+    let bishop = state.piece_squares(strong, Piece::Bishop).next().unwrap();
+    let weak_king = state.piece_squares(!strong, Piece::King).next();
+    bishop.colour() != promotion.colour() && weak_king.is_some_and(|king| king.king_distance(promotion) <= 1)
+}
+
+/// Number of king moves to reach one of the four centre squares
+fn centre_distance(cs: Coords) -> i8 {
+    [Coords::D4, Coords::E4, Coords::D5, Coords::E5].into_iter().map(|centre| cs.king_distance(centre) as i8).min().unwrap()
 }
 
-fn piece_value(f: i8, r: i8, piece: Piece) -> f32 {
-    let _ = f;
-    match piece {
-        Piece::Pawn => 1. + 0.1 * (r as f32).powf(1.1),
-        Piece::Knight => 3.,
-        Piece::Bishop => 3.2,
-        Piece::Rook => 5.,
-        Piece::Queen => 9.,
+/// Material value plus the piece-square bonus, with `r` counted from the piece's own side of the board
+///
+/// The king's bonus is left to the caller, since it is only wanted in the middlegame.
+fn piece_value(f: i8, r: i8, piece: Piece, params: &EvalParams) -> f32 {
+    let (value, squares) = match piece {
+        Piece::Pawn => (params.pawn + params.pawn_advance * (r as f32).powf(params.pawn_advance_exponent), &PAWN_SQUARES),
+        Piece::Knight => (params.knight, &KNIGHT_SQUARES),
+        Piece::Bishop => (params.bishop, &BISHOP_SQUARES),
+        Piece::Rook => (params.rook, &ROOK_SQUARES),
+        Piece::Queen => (params.queen, &QUEEN_SQUARES),
         // cannot use infinity for this as it would make the average useless
-        Piece::King => 0.,
+        Piece::King => return 0.,
+    };
+    value + params.piece_square * square_bonus(squares, f, r)
+}
+
+/// A piece-square table in centipawns, indexed by rank and then file as seen from the piece's own side
+type SquareTable = [[i8; 8]; 8];
+
+/// The bonus in pawns
+#[inline]
+fn square_bonus(table: &SquareTable, f: i8, r: i8) -> f32 {
+    table[r as usize][f as usize] as f32 / 100.
+}
+
+/// Pawns are kept on the wings in front of the king and pushed in the centre, their advance is valued separately
+const PAWN_SQUARES: SquareTable = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [  5,  10,  10, -20, -20,  10,  10,   5],
+    [  5,  -5, -10,   0,   0, -10,  -5,   5],
+    [  0,   0,   0,  20,  20,   0,   0,   0],
+    [  5,   5,  10,  25,  25,  10,   5,   5],
+    [  0,   0,   5,  10,  10,   5,   0,   0],
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+];
+/// Knights want the centre and are poor on the rim
+const KNIGHT_SQUARES: SquareTable = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20,   0,   5,   5,   0, -20, -40],
+    [-30,   5,  10,  15,  15,  10,   5, -30],
+    [-30,   0,  15,  20,  20,  15,   0, -30],
+    [-30,   5,  15,  20,  20,  15,   5, -30],
+    [-30,   0,  10,  15,  15,  10,   0, -30],
+    [-40, -20,   0,   0,   0,   0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+];
+/// Bishops want long diagonals and to leave the back rank
+const BISHOP_SQUARES: SquareTable = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10,   5,   0,   0,   0,   0,   5, -10],
+    [-10,  10,  10,  10,  10,  10,  10, -10],
+    [-10,   0,  10,  10,  10,  10,   0, -10],
+    [-10,   5,   5,  10,  10,   5,   5, -10],
+    [-10,   0,   5,  10,  10,   5,   0, -10],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+];
+/// Rooks want the central files and the seventh rank, standing in for the open files they usually find there
+const ROOK_SQUARES: SquareTable = [
+    [  0,   0,   0,   5,   5,   0,   0,   0],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [  5,  10,  10,  10,  10,  10,  10,   5],
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+];
+/// The queen is kept off the edges
+const QUEEN_SQUARES: SquareTable = [
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+    [-10,   0,   5,   0,   0,   0,   0, -10],
+    [-10,   5,   5,   5,   5,   5,   0, -10],
+    [  0,   0,   5,   5,   5,   5,   0,  -5],
+    [ -5,   0,   5,   5,   5,   5,   0,  -5],
+    [-10,   0,   5,   5,   5,   5,   0, -10],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+];
+/// The king shelters in the corners it castles to
+const KING_SQUARES: SquareTable = [
+    [ 20,  30,  10,   0,   0,  10,  30,  20],
+    [ 20,  20,   0,   0,   0,   0,  20,  20],
+    [-10, -20, -20, -20, -20, -20, -20, -10],
+    [-20, -30, -30, -40, -40, -30, -30, -20],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameResult, WinReason};
+
+    #[test]
+    fn test_eval_params_toml() {
+        let params = EvalParams::from_toml("queen = 10.0\ncheck_bonus = 0.5\n").unwrap();
+        assert_eq!(params, EvalParams { queen: 10., check_bonus: 0.5, ..EvalParams::default() });
+        assert_eq!(EvalParams::from_toml(&params.to_toml()).unwrap(), params);
+        assert!(EvalParams::from_toml("qeen = 10.0").is_err());
+    }
+
+    #[test]
+    fn test_transpositions() {
+        let tt = Transpositions::new(&SearchOptions::new(1, 1000));
+        assert_eq!(tt.stats().capacity, 1024);
+        let key = polyglot::key(&BoardState::new());
+        assert!(tt.probe(key).is_none());
+
+        tt.store(key, 3, Score(50), Bound::Lower, None);
+        let entry = tt.probe(key).unwrap();
+        // A lower bound only settles windows it fails high against
+        assert_eq!(entry.cutoff(3, Score(0), Score(40)), Some(Score(50)));
+        assert_eq!(entry.cutoff(3, Score(0), Score(60)), None);
+        assert_eq!(entry.cutoff(4, Score(0), Score(40)), None);
+        assert_eq!(entry.cutoff(2, -Score::INFINITE, Score::INFINITE), None);
+
+        // A shallower search of the same position never replaces it, a deeper one does
+        tt.store(key, 0, Score::ZERO, Bound::Exact, None);
+        assert_eq!(tt.probe(key).unwrap().depth, 3);
+        tt.store(key, 3, Score(50), Bound::Exact, None);
+        tt.store(key, 4, Score(50), Bound::Lower, None);
+        assert_eq!(tt.probe(key).unwrap().depth, 4);
+        tt.next_age();
+        tt.store(key, 2, Score::ZERO, Bound::Exact, None);
+        assert_eq!(tt.probe(key).unwrap().depth, 4);
+        let tt = Transpositions::new(&SearchOptions::new(1, 1000));
+        tt.store(key, 3, Score(50), Bound::Lower, None);
+
+        // A shallower search of another position in the same slot doesn't replace it until the next iteration
+        let other = key ^ 1024;
+        tt.store(other, 2, Score::ZERO, Bound::Exact, None);
+        assert!(tt.probe(other).is_none());
+        tt.next_age();
+        tt.store(other, 2, Score::ZERO, Bound::Exact, None);
+        assert!(tt.probe(key).is_none());
+        assert_eq!(tt.probe(other).unwrap().cutoff(1, -Score::INFINITE, Score::INFINITE), Some(Score::ZERO));
+        let stats = tt.stats();
+        assert_eq!((stats.entries, stats.overwrites, stats.ages), (1, 1, [1, 0, 0]));
+        let sampled = tt.sampled_stats();
+        assert_eq!((sampled.capacity, sampled.overwrites), (TT_SAMPLE, 1));
+        assert_eq!(sampled.entries, usize::from((other as usize & 1023) < TT_SAMPLE));
+    }
+
+    #[test]
+    fn test_searcher() {
+        let state = BoardState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let options = SearchOptions::new(4, usize::MAX);
+        let params = EvalParams::default();
+        let search = |searcher: &mut Searcher| {
+            let mut nodes = 0;
+            let ranking = searcher.get_moves_ranked_with_info(&state, &options, &params, |event| {
+                if let SearchEvent::Iteration(info) = event {
+                    nodes = info.nodes;
+                }
+            });
+            (ranking, nodes)
+        };
+
+        // Searching again builds on the first search, finding the same with far fewer nodes
+        let mut searcher = Searcher::new(1 << 16);
+        let (first, first_nodes) = search(&mut searcher);
+        let (again, nodes) = search(&mut searcher);
+        assert_eq!(again.eval, first.eval);
+        assert!(nodes < first_nodes / 2);
+        let [this, previous, older] = searcher.tt_stats().ages;
+        assert!(this > 0 && previous > 0 && older == 0);
+        search(&mut searcher);
+        search(&mut searcher);
+        assert!(searcher.tt_stats().ages[2] > 0);
+
+        // Other weights clear the table, as do other noise and a new game
+        let other = EvalParams { queen: 10., ..EvalParams::default() };
+        searcher.get_moves_ranked_with_info(&state, &SearchOptions::new(1, usize::MAX), &other, |_| ());
+        assert_eq!(searcher.tt_stats().ages[1..], [0, 0]);
+        searcher.get_moves_ranked_with_info(&state, &SearchOptions::new(1, usize::MAX).with_noise(Score(10), 1), &other, |_| ());
+        assert_eq!(searcher.tt_stats().ages[1..], [0, 0]);
+        searcher.clear();
+        assert_eq!(searcher.tt_stats().entries, 0);
+    }
+
+    #[test]
+    fn test_threads() {
+        let state = BoardState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut iterations = 0;
+        let mut last = (0, Duration::ZERO);
+        let ranking = get_moves_ranked_with_info(&state, &SearchOptions::new(3, usize::MAX).with_threads(4), &EvalParams::default(), |event| {
+            if let SearchEvent::Iteration(info) = event {
+                iterations += 1;
+                assert!(info.tt_stats.entries <= info.tt_stats.capacity);
+                assert!(info.nodes > last.0 && info.elapsed >= last.1);
+                last = (info.nodes, info.elapsed);
+            }
+        });
+        assert_eq!(iterations, 3);
+        assert_eq!(ranking.moves.len(), get_all_moves(&state).len());
+        assert!(ranking.moves.contains(&ranking.lines[0].pv[0]));
+
+        // Back rank mate with Ra8#, which the helpers mustn't confuse the main thread about
+        let state = BoardState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let ranking = get_moves_ranked_with_info(&state, &SearchOptions::new(3, usize::MAX).with_threads(4), &EvalParams::default(), |_| ());
+        assert_eq!(ranking.eval.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn test_opening_book() {
+        // e2e4 from the starting position, with weight 1
+        let mut bytes = polyglot::key(&BoardState::new()).to_be_bytes().to_vec();
+        bytes.extend([0x03, 0x1c, 0, 1, 0, 0, 0, 0]);
+        let book = OpeningBook::new(Arc::new(Book::from_bytes(&bytes).unwrap()), 1);
+        let mut rng = Rng::new(0);
+        let mut game = Game::new();
+        let mv = book.pick(&game, &mut rng).unwrap();
+        assert_eq!((mv.from, mv.unto), (Coords::E2, Coords::E4));
+        game.make_move(mv.from, mv.unto, None).unwrap();
+        assert!(book.pick(&game, &mut rng).is_none());
+
+        let book = OpeningBook::new(book.book, 0);
+        assert!(book.pick(&Game::new(), &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_skill() {
+        assert_eq!(Skill::new(30), Skill::MAX);
+        let options = SearchOptions::new(10, 1_000_000).with_skill(Skill::new(0), 1);
+        assert_eq!((options.max_depth, options.max_nodes, options.multipv), (1, 500, Skill::MISTAKE_LINES));
+        let options = SearchOptions::new(10, 1_000_000).with_skill(Skill::MAX, 1);
+        assert_eq!((options.max_depth, options.noise), (10, Score::ZERO));
+
+        // The noise stays within its bounds and is the same for the same position
+        let tt = Transpositions::new(&options.with_noise(Score(50), 7));
+        let key = polyglot::key(&BoardState::new());
+        assert_eq!(tt.noise(key), tt.noise(key));
+        assert!((0..1000).all(|key| tt.noise(key).0.abs() <= 50));
+
+        let state = BoardState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let ranking = get_moves_ranked(&state, &SearchOptions::new(2, usize::MAX).with_multipv(Skill::MISTAKE_LINES));
+        let mut rng = Rng::new(3);
+        assert!((0..20).all(|_| Skill::MAX.choose(&ranking, &mut rng) == Some(ranking.moves[0])));
+        // The weakest level makes mistakes, but only among the lines it searched and not too bad ones
+        let picks: Vec<_> = (0..200).map(|_| Skill::new(0).choose(&ranking, &mut rng).unwrap()).collect();
+        assert!(picks.iter().any(|&mv| mv != ranking.moves[0]));
+        for mv in picks {
+            let line = ranking.lines.iter().find(|line| line.pv[0] == mv).unwrap();
+            assert!(line.eval.0 >= ranking.eval.0 - Skill::new(0).max_mistake().0);
+        }
+    }
+
+    #[test]
+    fn test_stop() {
+        let state = BoardState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let options = SearchOptions::new(100, usize::MAX).with_threads(2).with_stop(stop.clone());
+        let mut depths = Vec::new();
+        let ranking = get_moves_ranked_with_info(&state, &options, &EvalParams::default(), |event| {
+            if let SearchEvent::Iteration(info) = event {
+                depths.push(info.depth);
+                stop.store(true, Ordering::Relaxed);
+            }
+        });
+        // The first iteration is kept even though the second one was interrupted
+        assert_eq!(depths, [1]);
+        assert_eq!(ranking.moves.len(), get_all_moves(&state).len());
+        assert_eq!(ranking.lines.len(), 1);
+
+        // Stopped before it started, it still gives every move
+        let ranking = get_moves_ranked(&state, &options);
+        assert_eq!(ranking.moves, get_all_moves_ordered(&state, GenMode::Stable));
+        assert!(ranking.lines.is_empty());
+    }
+
+    #[test]
+    fn test_finds_mate() {
+        // Back rank mate with Ra8#
+        let state = BoardState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let moves = get_moves_ranked(&state, &SearchOptions::new(1, 100_000)).moves;
+        assert_eq!((moves[0].from, moves[0].unto), (Coords::A1, Coords::A8));
+        // Deeper searches see slower mates too, but must still prefer the quickest one
+        let ranking = get_moves_ranked(&state, &SearchOptions::new(4, 100_000));
+        assert_eq!((ranking.moves[0].from, ranking.moves[0].unto), (Coords::A1, Coords::A8));
+        assert_eq!(ranking.eval, Score(Score::MATE.0 - 1));
+        assert_eq!(ranking.eval.to_string(), "#1");
+    }
+
+    #[test]
+    fn test_score() {
+        assert_eq!(Score::from_pawns(1.234), Score(123));
+        assert_eq!(Score::from_pawns(-0.5).to_string(), "-0.50");
+        assert_eq!(Score::MATE.mate_in(), Some(0));
+        assert_eq!(Score(-Score::MATE.0 + 2).mate_in(), Some(-2));
+        assert_eq!(Score(-Score::MATE.0 + 2).to_string(), "#-1");
+        // Being mated now is mating in one ply for the side that moved before
+        assert_eq!((-Score::MATE).for_parent(), Score(Score::MATE.0 - 1));
+        assert_eq!(Score(250).for_parent(), Score(-250));
+        assert_eq!(Score::from_pawns(1e9), Score::MATE_BOUND);
+        assert_eq!(Eval::from(Score(-50)), Eval::Centipawns(-50));
+        assert_eq!(Eval::from(Score(Score::MATE.0 - 3)), Eval::MateIn(2));
+        assert_eq!(Eval::from(Score(-Score::MATE.0 + 2)), Eval::MateIn(-1));
+    }
+
+    #[test]
+    fn test_principal_variation() {
+        let state = BoardState::new();
+        let mut pvs = Vec::new();
+        get_moves_ranked_with_info(&state, &SearchOptions::new(4, 100_000), &EvalParams::default(), |event| {
+            if let SearchEvent::Iteration(info) = event {
+                let pv = &info.lines[0].pv;
+                assert_eq!(pv.first(), info.ranked_moves.first());
+                pvs.push(pv.to_vec());
+            }
+        });
+        assert_eq!(pvs.len(), 4);
+        for (depth, pv) in (1..).zip(pvs) {
+            assert!(!pv.is_empty() && pv.len() <= depth);
+            let mut state = state;
+            for mv in pv {
+                assert!(legal_moves(&state).any(|legal| legal == mv));
+                state.make_move(mv.from, mv.unto, mv.promotion).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_multipv() {
+        let state = BoardState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let single = get_moves_ranked(&state, &SearchOptions::new(3, usize::MAX));
+        let multi = get_moves_ranked(&state, &SearchOptions::new(3, usize::MAX).with_multipv(3));
+        assert_eq!(single.lines.len(), 1);
+        assert_eq!(multi.lines.len(), 3);
+        assert_eq!(multi.eval, single.eval);
+        assert_eq!(multi.lines[0].eval, multi.eval);
+        for (line, &mv) in multi.lines.iter().zip(&multi.moves) {
+            assert_eq!(line.pv[0], mv);
+            assert!(line.pv.len() <= 3);
+        }
+        assert!(multi.lines.windows(2).all(|w| w[0].eval >= w[1].eval));
+        // Asking for more lines than there are moves gives one for each
+        let state = BoardState::from_fen("k7/8/8/8/8/8/8/7K w - - 0 1").unwrap();
+        assert_eq!(get_moves_ranked(&state, &SearchOptions::new(2, usize::MAX).with_multipv(10)).lines.len(), 3);
+    }
+
+    #[test]
+    fn test_endgame_terms() {
+        let params = EvalParams::default();
+        // The black king is outside the square of the a-pawn and can't catch it
+        let outside = BoardState::from_fen("8/8/8/8/P7/8/6k1/K7 w - - 0 1").unwrap();
+        let inside = BoardState::from_fen("8/8/8/2k5/P7/8/8/K7 w - - 0 1").unwrap();
+        assert!(endgame_terms(&outside, Colour::White, &params) >= params.unstoppable_passer);
+        assert!(endgame_terms(&inside, Colour::White, &params) < params.unstoppable_passer);
+        // The king would be too slow with white to move, but gets a step closer first with black to move
+        let white_to_move = BoardState::from_fen("8/8/8/8/P4k2/8/8/K7 w - - 0 1").unwrap();
+        let black_to_move = BoardState::from_fen("8/8/8/8/P4k2/8/8/K7 b - - 0 1").unwrap();
+        assert!(endgame_terms(&white_to_move, Colour::White, &params) >= params.unstoppable_passer);
+        assert!(endgame_terms(&black_to_move, Colour::White, &params) < params.unstoppable_passer);
+
+        // A central king is worth more than one in the corner
+        let central = BoardState::from_fen("8/8/8/3k4/8/8/8/K7 w - - 0 1").unwrap();
+        assert!(endgame_terms(&central, Colour::Black, &params) > endgame_terms(&central, Colour::White, &params));
+    }
+
+    #[test]
+    fn test_endgame_heuristics() {
+        let params = EvalParams::default();
+        let eval_fen = |fen| eval(&BoardState::from_fen(fen).unwrap(), &params);
+        // The lone king is better off in the centre and away from the other king
+        let cornered = eval_fen("7k/8/5K2/8/8/8/8/1Q6 w - - 0 1");
+        let central = eval_fen("8/8/8/4k3/8/8/8/1Q4K1 w - - 0 1");
+        assert!(cornered > central, "{cornered} <= {central}");
+
+        // The dark-squared bishop can't drive the king out of a8, but the light-squared one can
+        assert_eq!(eval_fen("k7/8/8/8/8/8/P7/2B1K3 w - - 0 1"), Score::ZERO);
+        assert_eq!(eval_fen("8/p7/8/8/8/8/8/K2bk3 b - - 0 1"), Score::ZERO);
+        assert!(eval_fen("k7/8/8/8/8/8/P7/1B2K3 w - - 0 1") > Score::ZERO);
+        assert!(eval_fen("8/8/8/4k3/8/8/P7/2B1K3 w - - 0 1") > Score::ZERO);
+
+        // With black to move the white king has the opposition
+        let state = BoardState::from_fen("8/8/8/4k3/8/4K3/4P3/8 b - - 0 1").unwrap();
+        let without = EvalParams { opposition: 0., ..EvalParams::default() };
+        assert!(eval(&state, &params) < eval(&state, &without));
+    }
+
+    #[test]
+    fn test_mates_lone_king() {
+        // Rook mates need the king driven to the edge, which a shallow search doesn't find on its own
+        let mut game = Game::from_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        let options = SearchOptions::new(3, usize::MAX);
+        // Within the 50 move rule
+        for _ in 0..100 {
+            if game.result() != GameResult::Ongoing {
+                break;
+            }
+            let mv = get_moves_ranked(game.board_state(), &options).moves[0];
+            game.make_move(mv.from, mv.unto, mv.promotion).unwrap();
+        }
+        assert_eq!(game.result(), GameResult::WhiteWins(WinReason::Checkmate));
+    }
+
+    #[test]
+    fn test_tablebase() {
+        // A shallow search already sees the exact distance to mate, from the tablebase
+        let state = BoardState::from_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        let Some(Dtm::Win(plies)) = tablebase::probe(&state) else { panic!("KRK should be won") };
+        let ranking = get_moves_ranked(&state, &SearchOptions::new(2, usize::MAX));
+        assert_eq!(ranking.eval.mate_in(), Some(plies as i32));
+        // Positions with more pieces aren't looked up
+        assert_eq!(tablebase_score(&BoardState::new()), None);
+        assert_eq!(tablebase_score(&BoardState::from_fen("4k3/8/8/8/8/8/8/2N1K3 w - - 0 1").unwrap()), None);
+    }
+
+    #[test]
+    fn test_piece_squares() {
+        let params = EvalParams::default();
+        let eval_fen = |fen| eval(&BoardState::from_fen(fen).unwrap(), &params);
+        // A knight in the centre is better than one on the rim
+        let centre = eval_fen("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 1");
+        let rim = eval_fen("rnbqkbnr/pppppppp/8/8/8/7N/PPPPPPPP/RNBQKB1R w KQkq - 0 1");
+        assert!(centre > rim, "{centre} <= {rim}");
+        // A castled king is safer than one left nearer the middle
+        let castled = eval_fen("r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1 w kq - 0 1");
+        let central = eval_fen("r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQRK2 w kq - 0 1");
+        assert!(castled > central, "{castled} <= {central}");
+        // Without the tables only the material counts
+        let off = EvalParams { piece_square: 0., ..params.clone() };
+        let state = BoardState::from_fen("rnbqkbnr/pppppppp/8/8/8/7N/PPPPPPPP/RNBQKB1R w KQkq - 0 1").unwrap();
+        assert_eq!(eval(&state, &off), Score::ZERO);
+    }
+
+    #[test]
+    fn test_eval_symmetry() {
+        let params = EvalParams::default();
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "8/8/8/8/P4k2/8/8/K7 b - - 0 1",
+        ] {
+            let state = BoardState::from_fen(fen).unwrap();
+            let (eval, flipped_eval) = (eval(&state, &params), eval(&state.flipped(), &params));
+            assert!((eval.0 - flipped_eval.0).abs() <= 1, "{fen}: {eval} != {flipped_eval}");
+        }
     }
 }
@@ -0,0 +1,212 @@
+//! A Monte Carlo tree search bot, which grows a tree of the moves that turn out best instead of searching to a depth
+//!
+//! Each iteration walks down the tree picking moves by UCT (upper confidence bound applied to trees), adds a new
+//! position and scores it with a playout, and adds that score to every position on the way back up.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{
+    boardstate::BoardState,
+    movegen::{get_all_moves, Move},
+};
+
+use super::{
+    bot1::{self, EvalParams},
+    rng::Rng,
+};
+
+/// How a new position in the tree is scored
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Playout {
+    /// Plays random moves until the game ends or `max_plies` have been played, after which the position is evaluated
+    Random { max_plies: usize },
+    /// Evaluates the position straight away with bot1's evaluation
+    Eval,
+}
+
+/// How long and how a search goes
+#[derive(Debug, Clone)]
+pub struct MctsOptions {
+    /// The number of positions added to the tree
+    pub iterations: usize,
+    /// How much less visited moves are tried, higher explores more and lower goes deeper into the best moves
+    pub exploration: f64,
+    pub playout: Playout,
+    /// Setting this stops the search as soon as possible, it then gives what it found so far
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+impl MctsOptions {
+    pub fn new(iterations: usize) -> Self {
+        MctsOptions {
+            iterations,
+            exploration: std::f64::consts::SQRT_2,
+            playout: Playout::Eval,
+            stop: None,
+        }
+    }
+    pub fn with_playout(self, playout: Playout) -> Self {
+        Self { playout, ..self }
+    }
+    /// Makes the search stop once `stop` is set
+    pub fn with_stop(self, stop: Arc<AtomicBool>) -> Self {
+        Self { stop: Some(stop), ..self }
+    }
+}
+
+/// A root move and how the search rates it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RootMove {
+    pub mv: Move,
+    /// How many playouts went through the move, the best move has the most
+    pub visits: u32,
+    /// The average score of the playouts for the side making the move, from 0 for a loss to 1 for a win
+    pub score: f64,
+}
+
+struct Node {
+    state: BoardState,
+    /// The move that led here, `None` for the root
+    mv: Option<Move>,
+    parent: usize,
+    children: Vec<usize>,
+    /// Moves without a child yet
+    untried: Vec<Move>,
+    visits: u32,
+    /// Total score for the side that made the move leading here
+    total: f64,
+}
+
+impl Node {
+    fn new(state: BoardState, mv: Option<Move>, parent: usize) -> Self {
+        Node {
+            state,
+            mv,
+            parent,
+            children: Vec::new(),
+            untried: get_all_moves(&state),
+            visits: 0,
+            total: 0.,
+        }
+    }
+    /// The UCT value of going to this node from a parent that has been visited `parent_visits` times
+    fn uct(&self, parent_visits: u32, exploration: f64) -> f64 {
+        let visits = self.visits as f64;
+        self.total / visits + exploration * ((parent_visits as f64).ln() / visits).sqrt()
+    }
+}
+
+/// Searches `state` and gives its moves with the best first, none if there are no legal moves
+pub fn search(state: &BoardState, options: &MctsOptions, params: &EvalParams, rng: &mut Rng) -> Vec<RootMove> {
+    let mut tree = vec![Node::new(*state, None, 0)];
+
+    for _ in 0..options.iterations {
+        if options.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed)) {
+            break;
+        }
+
+        // Selection, going down through fully expanded nodes
+        let mut node = 0;
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            let parent_visits = tree[node].visits;
+            node = *tree[node]
+                .children
+                .iter()
+                .max_by(|&&a, &&b| tree[a].uct(parent_visits, options.exploration).total_cmp(&tree[b].uct(parent_visits, options.exploration)))
+                .unwrap();
+        }
+
+        // Expansion, unless the game is over here
+        if !tree[node].untried.is_empty() {
+            let i = rng.below(tree[node].untried.len());
+            let mv = tree[node].untried.swap_remove(i);
+            let mut child_state = tree[node].state;
+            child_state.make_move(mv.from, mv.unto, mv.promotion).expect("generated moves are legal");
+            tree.push(Node::new(child_state, Some(mv), node));
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            node = child;
+        }
+
+        // Playout, scored for the side to move in the new node
+        let mut score = playout(&tree[node].state, options.playout, params, rng);
+
+        // Backpropagation, each node getting the score of the side that moved into it
+        loop {
+            let n = &mut tree[node];
+            n.visits += 1;
+            n.total += 1. - score;
+            score = 1. - score;
+            if node == 0 {
+                break;
+            }
+            node = n.parent;
+        }
+    }
+
+    let mut moves: Vec<_> = tree[0]
+        .children
+        .iter()
+        .map(|&child| {
+            let child = &tree[child];
+            RootMove {
+                mv: child.mv.unwrap(),
+                visits: child.visits,
+                score: child.total / child.visits.max(1) as f64,
+            }
+        })
+        .collect();
+    moves.sort_by(|a, b| b.visits.cmp(&a.visits).then(b.score.total_cmp(&a.score)));
+    moves
+}
+
+/// The expected score for the side to move in `state`, from 0 for a loss to 1 for a win
+fn playout(state: &BoardState, playout: Playout, params: &EvalParams, rng: &mut Rng) -> f64 {
+    let mut state = *state;
+    let mut flipped = false;
+    if let Playout::Random { max_plies } = playout {
+        for _ in 0..max_plies {
+            let moves = get_all_moves(&state);
+            if moves.is_empty() {
+                break;
+            }
+            let mv = moves[rng.below(moves.len())];
+            state.make_move(mv.from, mv.unto, mv.promotion).expect("generated moves are legal");
+            flipped = !flipped;
+        }
+    }
+    // The evaluation already knows checkmate and stalemate
+    let eval = bot1::eval(&state, params);
+    let score = 1. / (1. + 10f64.powf(-eval.0 as f64 / 400.));
+    if flipped { 1. - score } else { score }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Coords;
+
+    #[test]
+    fn test_search() {
+        // Back rank mate with Ra8#
+        let state = BoardState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut rng = Rng::new(1);
+        let moves = search(&state, &MctsOptions::new(2000), &EvalParams::default(), &mut rng);
+        assert_eq!(moves.len(), get_all_moves(&state).len());
+        assert_eq!((moves[0].mv.from, moves[0].mv.unto), (Coords::A1, Coords::A8));
+        assert_eq!(moves.iter().map(|mv| mv.visits).sum::<u32>(), 2000);
+
+        let options = MctsOptions::new(300).with_playout(Playout::Random { max_plies: 20 });
+        let moves = search(&state, &options, &EvalParams::default(), &mut rng);
+        assert_eq!(moves.iter().map(|mv| mv.visits).sum::<u32>(), 300);
+
+        // Stopped before it started, and in a position without moves
+        let stop = Arc::new(AtomicBool::new(true));
+        assert!(search(&state, &MctsOptions::new(100).with_stop(stop), &EvalParams::default(), &mut rng).is_empty());
+        let mated = BoardState::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(search(&mated, &MctsOptions::new(100), &EvalParams::default(), &mut rng).is_empty());
+    }
+}
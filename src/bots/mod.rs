@@ -1 +1,6 @@
+pub mod baseline;
 pub mod bot1;
+pub mod bot2;
+pub mod rng;
+#[cfg(feature = "tune")]
+pub mod tune;
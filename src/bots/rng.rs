@@ -0,0 +1,57 @@
+//! A small random number generator for the bots' random choices, like which book move to play
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// SplitMix64, which is fast and plenty random for picking moves, but not for anything secret
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// The same seed always gives the same numbers
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+    /// Seeded differently every time
+    pub fn from_entropy() -> Self {
+        // Every RandomState has its own random keys, the time is only mixed in for good measure
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos()));
+        Rng::new(hasher.finish())
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+    /// A number from 0 up to but not including `n`, which mustn't be 0
+    pub fn below(&mut self, n: usize) -> usize {
+        // The bias from the modulo is far too small to matter for numbers this small compared to 2^64
+        (self.next_u64() % n as u64) as usize
+    }
+    /// A number from 0 up to but not including 1
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng() {
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        let numbers: Vec<_> = (0..10).map(|_| a.next_u64()).collect();
+        assert!(numbers.iter().all(|&n| n == b.next_u64()));
+        assert_ne!(numbers[0], numbers[1]);
+        assert!((0..1000).all(|_| a.below(6) < 6 && (0. ..1.).contains(&a.unit())));
+    }
+}
@@ -0,0 +1,246 @@
+//! Texel tuning: adjusting the evaluation weights so the evaluation predicts the results of real games better
+//!
+//! Every position is labelled with the result of the game it came from, and the evaluation is turned into an expected
+//! score with a sigmoid. The weights are then nudged one at a time as long as that lowers the mean squared error
+//! between the expected scores and the results.
+
+use std::thread;
+
+use crate::{board::Colour, boardstate::BoardState, epd::Epd, pgn::PgnResult};
+
+use super::bot1::{self, EvalParams};
+
+/// A position and the result of the game it was played in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabelledPosition {
+    pub state: BoardState,
+    /// The score white got in the game: 1 for a win, 0.5 for a draw and 0 for a loss
+    pub result: f64,
+}
+
+impl LabelledPosition {
+    pub fn new(state: BoardState, result: PgnResult) -> Option<Self> {
+        let result = match result {
+            PgnResult::Win(Colour::White) => 1.,
+            PgnResult::Win(Colour::Black) => 0.,
+            PgnResult::Draw => 0.5,
+            PgnResult::Unknown => return None,
+        };
+        Some(LabelledPosition { state, result })
+    }
+    /// The position with the result from its `c9` operation, like `pgn_to_epd --result` writes
+    pub fn from_epd(epd: &Epd) -> Option<Self> {
+        let result = PgnResult::from_str(epd.operands("c9")?.first()?)?;
+        Self::new(epd.state, result)
+    }
+}
+
+const TUNED_WEIGHTS: usize = 13;
+
+/// The weights that get tuned, the pawn is left out as it's the unit the others are measured in
+fn weights(params: &mut EvalParams) -> [(&'static str, &mut f32); TUNED_WEIGHTS] {
+    let EvalParams {
+        pawn: _,
+        pawn_advance,
+        pawn_advance_exponent,
+        knight,
+        bishop,
+        rook,
+        queen,
+        check_bonus,
+        king_activity,
+        passer_king_distance,
+        unstoppable_passer,
+        piece_square,
+        mop_up,
+        opposition,
+    } = params;
+    [
+        ("pawn_advance", pawn_advance),
+        ("pawn_advance_exponent", pawn_advance_exponent),
+        ("knight", knight),
+        ("bishop", bishop),
+        ("rook", rook),
+        ("queen", queen),
+        ("check_bonus", check_bonus),
+        ("king_activity", king_activity),
+        ("passer_king_distance", passer_king_distance),
+        ("unstoppable_passer", unstoppable_passer),
+        ("piece_square", piece_square),
+        ("mop_up", mop_up),
+        ("opposition", opposition),
+    ]
+}
+
+/// The expected score for white from a centipawn evaluation of white's position
+fn expected_score(centipawns: f64, scale: f64) -> f64 {
+    1. / (1. + 10f64.powf(-scale * centipawns / 400.))
+}
+
+/// White's evaluation of every position in centipawns
+fn white_evals(positions: &[LabelledPosition], params: &EvalParams) -> Vec<f64> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = positions.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let chunks: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|pos| {
+                            let eval = bot1::eval(&pos.state, params).0 as f64;
+                            match pos.state.side_to_move {
+                                Colour::White => eval,
+                                Colour::Black => -eval,
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        chunks.into_iter().flat_map(|chunk| chunk.join().unwrap()).collect()
+    })
+}
+
+fn mean_squared_error(positions: &[LabelledPosition], evals: &[f64], scale: f64) -> f64 {
+    let total: f64 = positions
+        .iter()
+        .zip(evals)
+        .map(|(pos, &eval)| (pos.result - expected_score(eval, scale)).powi(2))
+        .sum();
+    total / positions.len().max(1) as f64
+}
+
+/// The mean squared error between the results and the scores the evaluation expects with `params`
+pub fn error(positions: &[LabelledPosition], params: &EvalParams, scale: f64) -> f64 {
+    mean_squared_error(positions, &white_evals(positions, params), scale)
+}
+
+/// Finds the scale of the sigmoid that fits the current evaluation best, to within a thousandth
+///
+/// This should be found before tuning and then kept, otherwise the tuner can lower the error by just scaling every
+/// weight up or down.
+pub fn find_scale(positions: &[LabelledPosition], params: &EvalParams) -> f64 {
+    let evals = white_evals(positions, params);
+    let (mut low, mut high) = (0.01, 10.);
+    // The error is convex in the scale, so a ternary search finds its minimum
+    while high - low > 0.001 {
+        let a = low + (high - low) / 3.;
+        let b = high - (high - low) / 3.;
+        if mean_squared_error(positions, &evals, a) < mean_squared_error(positions, &evals, b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+    (low + high) / 2.
+}
+
+/// Tunes the weights with local search
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    /// The scale of the sigmoid from [`find_scale`]
+    pub scale: f64,
+    /// How much a weight is changed at first, relative to its size
+    pub step: f32,
+    /// Stops once the step has been halved to this
+    pub min_step: f32,
+    pub max_rounds: usize,
+}
+
+impl Tuner {
+    pub fn new(scale: f64) -> Self {
+        Tuner {
+            scale,
+            step: 0.1,
+            min_step: 0.005,
+            max_rounds: 100,
+        }
+    }
+    pub fn with_max_rounds(self, max_rounds: usize) -> Self {
+        Self { max_rounds, ..self }
+    }
+    /// Tries moving every weight up and down by the step, keeping the changes that lower the error
+    ///
+    /// The step is halved after a round without any improvements. `on_round` gets the round number, the weights and
+    /// their error after each round.
+    pub fn tune<F: FnMut(usize, &EvalParams, f64)>(&self, positions: &[LabelledPosition], params: &EvalParams, mut on_round: F) -> EvalParams {
+        let mut best = params.clone();
+        let mut best_error = error(positions, &best, self.scale);
+        let mut step = self.step;
+
+        for round in 1..=self.max_rounds {
+            if step < self.min_step {
+                break;
+            }
+            let mut improved = false;
+            for i in 0..TUNED_WEIGHTS {
+                for direction in [1., -1.] {
+                    let mut candidate = best.clone();
+                    let (_, weight) = &mut weights(&mut candidate)[i];
+                    // Weights at zero still need to be able to move
+                    **weight += direction * step * weight.abs().max(0.1);
+                    let candidate_error = error(positions, &candidate, self.scale);
+                    if candidate_error < best_error {
+                        best = candidate;
+                        best_error = candidate_error;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+            on_round(round, &best, best_error);
+            if !improved {
+                step /= 2.;
+            }
+        }
+        best
+    }
+}
+
+/// The names of the weights that changed and their new values, for reporting what the tuner did
+pub fn changes(old: &EvalParams, new: &EvalParams) -> Vec<(&'static str, f32, f32)> {
+    let (mut old, mut new) = (old.clone(), new.clone());
+    weights(&mut old)
+        .into_iter()
+        .zip(weights(&mut new))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((name, old), (_, new))| (name, *old, *new))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labelled_position() {
+        let epd = Epd::parse("4k3/8/8/8/8/8/8/3QK3 w - - c9 \"1-0\";").unwrap();
+        assert_eq!(LabelledPosition::from_epd(&epd).unwrap().result, 1.);
+        let epd = Epd::parse("4k3/8/8/8/8/8/8/3QK3 w - - c9 \"*\";").unwrap();
+        assert!(LabelledPosition::from_epd(&epd).is_none());
+        assert!(LabelledPosition::from_epd(&Epd::new(BoardState::new())).is_none());
+    }
+
+    #[test]
+    fn test_tune() {
+        let labelled = |fen, result| LabelledPosition::new(BoardState::from_fen(fen).unwrap(), result).unwrap();
+        // An extra queen wins, and so does an extra knight, while the starting position is drawn
+        let positions = [
+            labelled("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", PgnResult::Win(Colour::White)),
+            labelled("3qk3/8/8/8/8/8/8/4K3 b - - 0 1", PgnResult::Win(Colour::Black)),
+            labelled("4k3/pppppppp/8/8/8/8/PPPPPPPP/1N2K3 w - - 0 1", PgnResult::Win(Colour::White)),
+            labelled("1n2k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1", PgnResult::Win(Colour::Black)),
+            labelled("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", PgnResult::Draw),
+        ];
+        let params = EvalParams::default();
+        let scale = find_scale(&positions, &params);
+        assert!(scale > 0.01 && scale < 10.);
+
+        let tuned = Tuner::new(scale).with_max_rounds(3).tune(&positions, &params, |_, _, _| ());
+        assert!(error(&positions, &tuned, scale) < error(&positions, &params, scale));
+        assert!(!changes(&params, &tuned).is_empty());
+        assert_eq!(tuned.pawn, params.pawn);
+    }
+}
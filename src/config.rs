@@ -0,0 +1,191 @@
+//! The user's settings, read from `config.toml` in the talv config directory
+
+use std::{
+    env,
+    fmt::{self, Display},
+    fs, io,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bots::bot1::OpeningBook,
+    polyglot::{Book, BookError},
+};
+
+/// Everything that can be set in the config file, anything left out keeps its default value
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub archive: ArchiveConfig,
+    pub book: BookConfig,
+}
+
+/// Where finished games are saved, see [`crate::archive`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    /// Defaults to `games.pgn` in the talv data directory
+    pub path: Option<PathBuf>,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        ArchiveConfig {
+            enabled: true,
+            path: None,
+        }
+    }
+}
+
+impl ArchiveConfig {
+    /// The file games should be saved to, if saving is enabled and there is somewhere to put it
+    pub fn archive_path(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        self.path.clone().or_else(|| Some(data_dir()?.join("games.pgn")))
+    }
+}
+
+/// The Polyglot opening book the bot plays from, see [`OpeningBook`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BookConfig {
+    pub enabled: bool,
+    /// Defaults to `book.bin` in the talv data directory
+    pub path: Option<PathBuf>,
+    /// How many moves of each side can come from the book
+    pub moves: u64,
+}
+
+impl Default for BookConfig {
+    fn default() -> Self {
+        BookConfig {
+            enabled: true,
+            path: None,
+            moves: 10,
+        }
+    }
+}
+
+impl BookConfig {
+    /// Reads the book, `None` if books are disabled or there is no book where the default one would be
+    pub fn load(&self) -> Result<Option<OpeningBook>, BookLoadError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let (path, explicit) = match &self.path {
+            Some(path) => (path.clone(), true),
+            None => match data_dir() {
+                Some(dir) => (dir.join("book.bin"), false),
+                None => return Ok(None),
+            },
+        };
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => return Ok(None),
+            Err(e) => return Err(BookLoadError::Io(path, e)),
+        };
+        let book = Book::from_bytes(&bytes).map_err(|e| BookLoadError::Book(path, e))?;
+        Ok(Some(OpeningBook::new(Arc::new(book), self.moves)))
+    }
+}
+
+#[derive(Debug)]
+pub enum BookLoadError {
+    Io(PathBuf, io::Error),
+    Book(PathBuf, BookError),
+}
+
+impl Display for BookLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookLoadError::Io(path, e) => write!(f, "could not read the opening book {}: {e}", path.display()),
+            BookLoadError::Book(path, e) => write!(f, "invalid opening book {}: {e}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "could not read the config file: {e}"),
+            ConfigError::Toml(e) => write!(f, "invalid config file: {e}"),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+    /// Where the config file is looked for
+    pub fn path() -> Option<PathBuf> {
+        Some(config_dir()?.join("config.toml"))
+    }
+    /// Reads the config file, giving the default settings if there is none
+    pub fn load() -> Result<Self, ConfigError> {
+        let Some(path) = Self::path() else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(path) {
+            Ok(s) => Self::from_toml(&s).map_err(ConfigError::Toml),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(ConfigError::Io(e)),
+        }
+    }
+}
+
+/// `%APPDATA%\talv` on Windows and `$XDG_CONFIG_HOME/talv` (usually `~/.config/talv`) elsewhere
+fn config_dir() -> Option<PathBuf> {
+    user_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `%APPDATA%\talv` on Windows and `$XDG_DATA_HOME/talv` (usually `~/.local/share/talv`) elsewhere
+fn data_dir() -> Option<PathBuf> {
+    user_dir("XDG_DATA_HOME", ".local/share")
+}
+
+fn user_dir(xdg_var: &str, home_fallback: &str) -> Option<PathBuf> {
+    let var = |name| env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let base = if cfg!(windows) {
+        var("APPDATA")?
+    } else {
+        var(xdg_var).or_else(|| Some(var("HOME")?.join(home_fallback)))?
+    };
+    Some(base.join("talv"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_toml() {
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+
+        let config = Config::from_toml("[archive]\npath = \"my games.pgn\"\n").unwrap();
+        assert!(config.archive.enabled);
+        assert_eq!(config.archive.archive_path(), Some(PathBuf::from("my games.pgn")));
+
+        let config = Config::from_toml("[archive]\nenabled = false\npath = \"my games.pgn\"\n").unwrap();
+        assert_eq!(config.archive.archive_path(), None);
+        assert!(Config::from_toml("[archive]\nenable = false\n").is_err());
+
+        let config = Config::from_toml("[book]\nenabled = false\npath = \"missing.bin\"\n").unwrap();
+        assert!(config.book.load().unwrap().is_none());
+        let config = Config::from_toml("[book]\npath = \"missing.bin\"\nmoves = 4\n").unwrap();
+        assert_eq!(config.book.moves, 4);
+        assert!(matches!(config.book.load(), Err(BookLoadError::Io(..))));
+    }
+}
@@ -0,0 +1,200 @@
+//! Extended Position Description: a position without clocks followed by operations like `bm Nf3; id "test 1";`
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{algebraic::Move as AlgebraicMove, boardstate::BoardState, game::Game};
+
+/// One EPD record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Epd {
+    pub state: BoardState,
+    pub operations: Vec<Operation>,
+}
+
+/// An opcode and its operands, with the quotes around string operands removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    pub opcode: String,
+    pub operands: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EpdError {
+    /// The first four fields aren't a valid position
+    InvalidPosition,
+    /// A string operand is missing its closing quote
+    UnterminatedString,
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::InvalidPosition => write!(f, "invalid EPD position"),
+            EpdError::UnterminatedString => write!(f, "unterminated string in EPD operation"),
+        }
+    }
+}
+
+impl Error for EpdError {}
+
+impl Epd {
+    pub fn new(state: BoardState) -> Self {
+        Epd {
+            state,
+            operations: Vec::new(),
+        }
+    }
+    /// Parses a single EPD line, the semicolon after the last operation may be left out
+    pub fn parse(line: &str) -> Result<Self, EpdError> {
+        let line = line.trim();
+        let mut position_end = 0;
+        for _ in 0..4 {
+            let rest = &line[position_end..];
+            let start = position_end + rest.len() - rest.trim_start().len();
+            position_end = line[start..].find(char::is_whitespace).map_or(line.len(), |i| start + i);
+        }
+        let state = BoardState::from_fen(&line[..position_end]).ok_or(EpdError::InvalidPosition)?;
+
+        let mut operations = Vec::new();
+        let mut chars = line[position_end..].chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+            let mut opcode = String::new();
+            while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && c != ';') {
+                opcode.push(c);
+            }
+
+            let mut operands = Vec::new();
+            loop {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                match chars.next() {
+                    None | Some(';') => break,
+                    Some('"') => {
+                        let mut operand = String::new();
+                        loop {
+                            match chars.next() {
+                                Some('"') => break,
+                                Some(c) => operand.push(c),
+                                None => return Err(EpdError::UnterminatedString),
+                            }
+                        }
+                        operands.push(operand);
+                    }
+                    Some(c) => {
+                        let mut operand = c.to_string();
+                        while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && c != ';') {
+                            operand.push(c);
+                        }
+                        operands.push(operand);
+                    }
+                }
+            }
+            if !opcode.is_empty() {
+                operations.push(Operation { opcode, operands });
+            }
+        }
+
+        Ok(Epd { state, operations })
+    }
+    /// The operands of the first operation with the opcode
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter().find(|op| op.opcode == opcode).map(|op| &*op.operands)
+    }
+    /// Replaces the operands of the operation with the opcode, adding it at the end if there is none
+    pub fn set(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.operations.iter_mut().find(|op| op.opcode == opcode) {
+            Some(op) => op.operands = operands,
+            None => self.operations.push(Operation {
+                opcode: opcode.to_owned(),
+                operands,
+            }),
+        }
+    }
+    /// The `id` operation naming the position
+    pub fn id(&self) -> Option<&str> {
+        self.operands("id")?.first().map(|s| &**s)
+    }
+    /// The best moves from the `bm` operation
+    pub fn best_moves(&self) -> Option<Vec<AlgebraicMove>> {
+        self.moves("bm")
+    }
+    /// The moves to avoid from the `am` operation
+    pub fn avoid_moves(&self) -> Option<Vec<AlgebraicMove>> {
+        self.moves("am")
+    }
+    /// The operands of `opcode` as moves, `None` if there is no such operation or one of them isn't a move
+    pub fn moves(&self, opcode: &str) -> Option<Vec<AlgebraicMove>> {
+        self.operands(opcode)?.iter().map(|s| AlgebraicMove::from_str(s).ok()).collect()
+    }
+    /// The evaluation in centipawns from the side to move's perspective, from the `ce` operation
+    pub fn centipawn_eval(&self) -> Option<i32> {
+        self.operands("ce")?.first()?.parse().ok()
+    }
+    /// The position as a game, with its clocks taken from the `hmvc` and `fmvn` operations if they're there
+    pub fn game(&self) -> Game {
+        let clock = |opcode| self.operands(opcode).and_then(|ops| ops.first()?.parse::<u64>().ok());
+        let fen = format!("{} {} {}", self.state.display_fen(), clock("hmvc").unwrap_or(0), clock("fmvn").unwrap_or(1).max(1));
+        Game::from_fen(&fen).expect("position was already valid")
+    }
+}
+
+impl FromStr for Epd {
+    type Err = EpdError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Epd::parse(s)
+    }
+}
+
+impl Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.state.display_fen())?;
+        for op in &self.operations {
+            write!(f, " {}", op.opcode)?;
+            // Comments and identifiers are strings by convention even when they wouldn't need the quotes
+            let string_opcode = op.opcode == "id" || op.opcode.len() == 2 && op.opcode.starts_with(['c', 'v']) && op.opcode.ends_with(|c: char| c.is_ascii_digit());
+            for operand in &op.operands {
+                if string_opcode || operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';') {
+                    write!(f, " \"{operand}\"")?;
+                } else {
+                    write!(f, " {operand}")?;
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epd() {
+        let line = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; c0 \"mate in two\"; ce +32000;";
+        let epd = Epd::parse(line).unwrap();
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.best_moves().unwrap().iter().map(|mv| mv.to_string()).collect::<Vec<_>>(), ["Qg6"]);
+        assert_eq!(epd.avoid_moves(), None);
+        assert_eq!(epd.operands("c0").unwrap(), ["mate in two"]);
+        assert_eq!(epd.centipawn_eval(), Some(32000));
+        assert_eq!(epd.to_string(), line);
+        assert_eq!(Epd::parse(&epd.to_string()).unwrap(), epd);
+
+        let mut epd = Epd::parse("4k3/8/8/8/8/8/8/4K3 b - - hmvc 12; fmvn 40").unwrap();
+        assert_eq!(epd.game().display_fen().to_string(), "4k3/8/8/8/8/8/8/4K3 b - - 12 40");
+        epd.set("am", vec!["Kd7".to_owned(), "Kf7".to_owned()]);
+        epd.set("hmvc", vec!["0".to_owned()]);
+        assert_eq!(epd.to_string(), "4k3/8/8/8/8/8/8/4K3 b - - hmvc 0; fmvn 40; am Kd7 Kf7;");
+
+        assert_eq!(Epd::parse("4k3/8/8/8/8/8/8/4K3 x - -"), Err(EpdError::InvalidPosition));
+        assert_eq!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - id \"open"), Err(EpdError::UnterminatedString));
+    }
+}
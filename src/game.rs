@@ -1,98 +1,529 @@
 use std::{
     collections::HashMap,
+    error::Error,
     fmt::{self, Display},
+    mem,
     num::NonZeroU64,
+    str::FromStr,
+    time::Duration,
 };
 
-use crate::boardstate::{BoardState, Success};
+use crate::boardstate::{BoardState, FenClocks, FenError, FenStyle, MoveError, Success};
 
-use super::algebraic::{Move, MoveType, Mover};
+use crate::movegen::{self, any_legal_moves};
+use crate::pgn::{MoveList, Nag, PgnError, PgnGame, PgnResult, Tags};
+
+use super::algebraic::{KingThreat, Move, MoveType, Mover, ParseMoveError};
 use super::board::*;
-use super::location::{Coords, File, FileRange, Rank, RankRange};
+use super::location::{Coords, File, Rank};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Game {
     board_state: BoardState,
+    /// How many times each position has occurred since the last capture or pawn move
     last_move_states: HashMap<BoardState, u8>,
+    /// Half-moves since the last capture or pawn move, for the fifty-move rule
+    halfmove_clock: u16,
+    fullmove_count: NonZeroU64,
+    history: Vec<HistoryEntry>,
+    /// Moves after the current position that were gone back from with [`Game::seek`]
+    future: Vec<HistoryEntry>,
+    /// The position, halfmove clock and move number the history starts from
+    start: (BoardState, u16, NonZeroU64),
+    /// How the game ended if a player resigned or the players agreed to a draw
+    decided: Option<GameResult>,
+    /// The side whose draw offer is standing, if any
+    draw_offer: Option<Colour>,
+    /// The annotations of the moves in the history by ply, moves at the end without any may be left out
+    annotations: Vec<MoveAnnotation>,
+    time_control: Option<TimeControl>,
+}
+
+/// A move that has been played in a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryEntry {
+    pub from: Coords,
+    pub unto: Coords,
+    pub promotion: Option<Piece>,
+    /// How long the player spent thinking about the move, if known
+    pub think_time: Option<Duration>,
+}
+
+impl HistoryEntry {
+    /// Whether this is a null move, which is kept as the king moving to its own square
+    pub fn is_null(&self) -> bool {
+        self.from == self.unto
+    }
+    /// Displays the think time as a PGN `[%emt H:MM:SS]` command if it is known
+    pub fn display_emt(&self) -> Option<Emt> {
+        self.think_time.map(Emt)
+    }
+}
+
+/// Comments, NAGs and an evaluation attached to a move in a game, written after the move in PGN
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveAnnotation {
+    pub nags: Vec<Nag>,
+    /// The evaluation of the position after the move from white's point of view, written as a `[%eval]` command
+    pub eval: Option<Eval>,
+    pub comments: Vec<String>,
+}
+
+impl MoveAnnotation {
+    pub fn is_empty(&self) -> bool {
+        self.nags.is_empty() && self.eval.is_none() && self.comments.is_empty()
+    }
+}
+
+/// An evaluation of a position like in a PGN `[%eval]` command, positive when white is better
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Eval {
+    Centipawns(i32),
+    /// The number of moves until mate, negative when black is the one mating
+    MateIn(i32),
+}
+
+impl Eval {
+    /// Parses the value of a `[%eval]` command, pawns like `0.32` or moves to mate like `#-2`
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.strip_prefix('#') {
+            Some(moves) => moves.parse().ok().map(Eval::MateIn),
+            None => s.parse::<f64>().ok().filter(|pawns| pawns.is_finite()).map(|pawns| Eval::Centipawns((pawns * 100.).round() as i32)),
+        }
+    }
+}
+
+/// Pawns with two decimals, or the number of moves to mate like `#3` or `#-2`
+impl Display for Eval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Eval::Centipawns(cp) => write!(f, "{:.2}", cp as f64 / 100.),
+            Eval::MateIn(moves) => write!(f, "#{moves}"),
+        }
+    }
+}
+
+/// Elapsed move time formatted as a PGN `[%emt H:MM:SS]` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Emt(pub Duration);
+
+impl Emt {
+    /// Finds an `[%emt H:MM:SS]` command in a PGN comment, the seconds may have decimals
+    pub fn from_comment(comment: &str) -> Option<Self> {
+        find_time_command(comment, "emt").map(Emt)
+    }
+}
+
+impl Display for Emt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_time_command(f, "emt", self.0)
+    }
+}
+
+/// Time left on the clock of the player who made a move, formatted as a PGN `[%clk H:MM:SS]` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clk(pub Duration);
+
+impl Clk {
+    /// Finds a `[%clk H:MM:SS]` command in a PGN comment, the seconds may have decimals
+    pub fn from_comment(comment: &str) -> Option<Self> {
+        find_time_command(comment, "clk").map(Clk)
+    }
+}
+
+impl Display for Clk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_time_command(f, "clk", self.0)
+    }
+}
+
+fn find_time_command(comment: &str, name: &str) -> Option<Duration> {
+    let command = format!("[%{name} ");
+    let start = comment.find(&command)? + command.len();
+    let value = comment[start..].split(']').next()?.trim();
+    let mut parts = value.split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds = Duration::try_from_secs_f64(parts.next()?.parse().ok()?).ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + seconds)
+}
+
+fn write_time_command(f: &mut fmt::Formatter<'_>, name: &str, time: Duration) -> fmt::Result {
+    let secs = time.as_secs();
+    write!(f, "[%{name} {}:{:02}:{:02}]", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// A time control with `base` time for the game and `increment` added to a player's clock after each of their moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    pub const fn new(base: Duration, increment: Duration) -> Self {
+        TimeControl { base, increment }
+    }
+    /// Parses a PGN `TimeControl` tag in seconds like `300+5` or `600`, other kinds of time controls aren't supported
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let (base, increment) = s.split_once('+').unwrap_or((s, "0"));
+        Some(TimeControl::new(Duration::from_secs(base.parse().ok()?), Duration::from_secs(increment.parse().ok()?)))
+    }
+}
+
+/// As a PGN `TimeControl` tag in whole seconds
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base.as_secs())?;
+        if !self.increment.is_zero() {
+            write!(f, "+{}", self.increment.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+/// What a game is serialized as, the rest is found again by replaying the moves
+///
+/// The annotations are left out, PGN is the format that keeps those.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameRecord {
+    halfmove_clock: u16,
     fullmove_count: NonZeroU64,
+    /// The side that resigned, if one did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resigned: Option<Colour>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    agreed_draw: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time_control: Option<TimeControl>,
+    start: BoardState,
+    moves: Vec<HistoryEntry>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Game {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (start, halfmove_clock, fullmove_count) = self.start;
+        GameRecord {
+            start,
+            halfmove_clock,
+            fullmove_count,
+            resigned: match self.decided {
+                Some(GameResult::WhiteWins(WinReason::Resignation)) => Some(Colour::Black),
+                Some(GameResult::BlackWins(WinReason::Resignation)) => Some(Colour::White),
+                _ => None,
+            },
+            agreed_draw: self.decided == Some(GameResult::Draw(DrawReason::Agreement)),
+            time_control: self.time_control,
+            moves: self.history.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Game {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let record = GameRecord::deserialize(deserializer)?;
+        let mut game = Game::starting_at(record.start, record.halfmove_clock, record.fullmove_count);
+        game.time_control = record.time_control;
+        for (ply, mv) in record.moves.into_iter().enumerate() {
+            game.make_history_move(&mv)
+                .map_err(|e| serde::de::Error::custom(format!("illegal move {}{} at ply {ply}: {e}", mv.from, mv.unto)))?;
+        }
+        if let Some(colour) = record.resigned {
+            game.resign(colour);
+        } else if record.agreed_draw {
+            game.decided = Some(GameResult::Draw(DrawReason::Agreement));
+        }
+        Ok(game)
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Self {
-        Game {
-            board_state: BoardState::new(),
-            last_move_states: HashMap::new(),
-            fullmove_count: NonZeroU64::new(1).unwrap(),
-        }
+        Game::starting_at(BoardState::new(), 0, NonZeroU64::new(1).unwrap())
     }
     pub fn from_fen(fen: &str) -> Option<Self> {
         let move_count_index = fen.rfind(char::is_whitespace)?;
         let fullmove_count = fen[move_count_index..].trim_start().parse().ok()?;
         let half_move_clock_index = fen[..move_count_index].rfind(char::is_whitespace)?;
-
-        let mut last_move_states = HashMap::new();
-        // Set an impossible board state that will contribute to the fifty-move rule
-        last_move_states.insert(
-            BoardState {
-                board: Board::EMPTY,
-                ..BoardState::new()
-            },
-            fen[half_move_clock_index..move_count_index]
-                .trim_start()
-                .parse()
-                .ok()?,
-        );
+        let halfmove_clock = fen[half_move_clock_index..move_count_index].trim_start().parse().ok()?;
 
         let board_state = BoardState::from_fen(&fen[..half_move_clock_index])?;
 
-        Some(Game {
+        Some(Game::starting_at(board_state, halfmove_clock, fullmove_count))
+    }
+    fn starting_at(board_state: BoardState, halfmove_clock: u16, fullmove_count: NonZeroU64) -> Self {
+        Game {
             board_state,
-            last_move_states,
+            // The starting position counts as the first occurrence for repetitions
+            last_move_states: HashMap::from([(board_state, 1)]),
+            halfmove_clock,
             fullmove_count,
-        })
+            history: Vec::new(),
+            future: Vec::new(),
+            start: (board_state, halfmove_clock, fullmove_count),
+            decided: None,
+            draw_offer: None,
+            annotations: Vec::new(),
+            time_control: None,
+        }
+    }
+    /// The game as it was before any of the moves in the history were made, with the same time control
+    pub fn start(&self) -> Game {
+        let (board_state, halfmove_clock, fullmove_count) = self.start;
+        Game {
+            time_control: self.time_control,
+            ..Game::starting_at(board_state, halfmove_clock, fullmove_count)
+        }
+    }
+    /// Whether the game is over, counting draws that can be claimed as drawn
+    ///
+    /// A draw that happens automatically (see [`DrawReason::is_automatic`]) is reported over one that could only be claimed.
+    pub fn result(&self) -> GameResult {
+        if let Some(result) = self.decided {
+            return result;
+        }
+        let side = self.side_to_move();
+        if !any_legal_moves(&self.board_state) {
+            if self.is_checked(side) {
+                return match !side {
+                    Colour::White => GameResult::WhiteWins(WinReason::Checkmate),
+                    Colour::Black => GameResult::BlackWins(WinReason::Checkmate),
+                };
+            }
+            return GameResult::Draw(DrawReason::Stalemate);
+        }
+        match self.claimable_draw() {
+            Some(reason) => GameResult::Draw(reason),
+            None => GameResult::Ongoing,
+        }
+    }
+    /// Ends the game with `colour` giving up, unless it is already over
+    pub fn resign(&mut self, colour: Colour) {
+        if self.result().is_over() {
+            return;
+        }
+        self.decided = Some(match colour {
+            Colour::White => GameResult::BlackWins(WinReason::Resignation),
+            Colour::Black => GameResult::WhiteWins(WinReason::Resignation),
+        });
+        self.draw_offer = None;
+    }
+    /// Offers a draw on behalf of `colour`, returns whether the game is drawn because the opponent had offered one too
+    ///
+    /// Otherwise the offer stands until the opponent accepts it or makes a move.
+    pub fn offer_draw(&mut self, colour: Colour) -> bool {
+        if self.result().is_over() {
+            return false;
+        }
+        if self.draw_offer == Some(!colour) {
+            return self.accept_draw();
+        }
+        self.draw_offer = Some(colour);
+        false
+    }
+    /// Accepts the standing draw offer, returns whether there was one
+    pub fn accept_draw(&mut self) -> bool {
+        if self.draw_offer.take().is_none() || self.result().is_over() {
+            return false;
+        }
+        self.decided = Some(GameResult::Draw(DrawReason::Agreement));
+        true
+    }
+    /// The side whose draw offer is standing, if any
+    pub fn draw_offer(&self) -> Option<Colour> {
+        self.draw_offer
     }
     pub fn draw_claimable(&self) -> bool {
-        self.last_move_states.get(&self.board_state).copied().unwrap_or(0) == 3
-        || self.last_move_states.values().copied().sum::<u8>() == 100 || 'only_kings: {
-            // Check if only kings are left
-            for cs in Coords::full_range() {
-                match self.board_state.get(cs) {
-                    Field::Occupied(_, Piece::King) | Field::Empty => (),
-                    _ => break 'only_kings false,
-                }
+        self.claimable_draw().is_some()
+    }
+    /// Whether the game is drawn without either player having to claim it, by the FIDE rules
+    pub fn is_automatic_draw(&self) -> bool {
+        matches!(self.result(), GameResult::Draw(reason) if reason.is_automatic())
+    }
+    fn claimable_draw(&self) -> Option<DrawReason> {
+        let repetitions = self.repetition_count();
+        if repetitions >= 5 {
+            Some(DrawReason::FivefoldRepetition)
+        } else if self.halfmove_clock() >= 150 {
+            Some(DrawReason::SeventyFiveMove)
+        } else if self.insufficient_material() {
+            Some(DrawReason::InsufficientMaterial)
+        } else if repetitions >= 3 {
+            Some(DrawReason::Repetition)
+        } else if self.halfmove_clock() >= 100 {
+            Some(DrawReason::FiftyMove)
+        } else {
+            None
+        }
+    }
+    /// Half-moves made since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+    /// The number of the current move, starting at 1 and going up after black moves
+    pub fn fullmove_number(&self) -> u64 {
+        self.fullmove_count.get()
+    }
+    /// Whether neither side can possibly checkmate: a lone minor piece, or only bishops all on the same colour
+    fn insufficient_material(&self) -> bool {
+        let mut knights = 0;
+        let mut light_bishop = false;
+        let mut dark_bishop = false;
+        for cs in Coords::full_range() {
+            match self.board_state.get(cs) {
+                Field::Empty | Field::Occupied(_, Piece::King) => (),
+                Field::Occupied(_, Piece::Knight) => knights += 1,
+                Field::Occupied(_, Piece::Bishop) => match cs.colour() {
+                    Colour::White => light_bishop = true,
+                    Colour::Black => dark_bishop = true,
+                },
+                Field::Occupied(_, Piece::Pawn | Piece::Rook | Piece::Queen) => return false,
             }
-            true
         }
+        match (knights, light_bishop, dark_bishop) {
+            (0, true, true) => false,
+            (0, _, _) => true,
+            (1, false, false) => true,
+            _ => false,
+        }
+    }
+    /// Whether the current position has occurred before since the last capture or pawn move
+    pub fn position_repeated(&self) -> bool {
+        self.repetition_count() > 1
+    }
+    /// How many times the current position has occurred, counting this time
+    pub fn repetition_count(&self) -> u8 {
+        self.position_count(&self.board_state)
+    }
+    /// How many times `state` has occurred since the last capture or pawn move, the positions before can't come up again
+    pub fn position_count(&self, state: &BoardState) -> u8 {
+        self.last_move_states.get(state).copied().unwrap_or(0)
     }
-    fn attempt_move(&self, from: Coords, unto: Coords, promotion: Option<Piece>) -> Option<(Success, BoardState)> {
+    /// Whether the game has been decided or drawn automatically, so no more moves can be made
+    fn is_finished(&self) -> bool {
+        self.decided.is_some() || self.claimable_draw().is_some_and(DrawReason::is_automatic)
+    }
+    fn attempt_move(&self, from: Coords, unto: Coords, promotion: Option<Piece>) -> Result<(Success, BoardState), MoveError> {
+        if self.is_finished() {
+            return Err(MoveError::GameOver);
+        }
         let mut board_state = self.board_state;
 
-        let success = board_state.make_move(from, unto, promotion).ok()?;
+        let success = board_state.make_move(from, unto, promotion)?;
 
         if board_state.in_check(self.board_state.side_to_move) {
-            None
+            Err(MoveError::LeavesKingInCheck)
         } else {
-            Some((success, board_state))
-        }
-    }
-    pub fn make_move(&mut self, from: Coords, unto: Coords, promotion: Option<Piece>) -> bool {
-        match self.attempt_move(from, unto, promotion) {
-            Some((success, new_state)) => {
-                self.board_state = new_state;
-                match success {
-                    Success::PawnMovement | Success::PawnMovementAndCheck | Success::Capture => {
-                        self.last_move_states.clear();
-                    }
-                    Success::Check | Success::PieceMovement => (),
-                }
-                *self.last_move_states.entry(self.board_state).or_insert(0) += 1;
-                if matches!(self.side_to_move(), Colour::White) {
-                    self.fullmove_count = self.fullmove_count.checked_add(1).unwrap();
-                }
+            Ok((success, board_state))
+        }
+    }
+    pub fn make_move(&mut self, from: Coords, unto: Coords, promotion: Option<Piece>) -> Result<MoveOutcome, MoveError> {
+        self.make_timed_move(from, unto, promotion, None)
+    }
+    /// Makes a move and records how long was spent thinking about it in the history
+    pub fn make_timed_move(&mut self, from: Coords, unto: Coords, promotion: Option<Piece>, think_time: Option<Duration>) -> Result<MoveOutcome, MoveError> {
+        let (success, new_state) = self.attempt_move(from, unto, promotion)?;
+        let mv = movegen::Move::new(&self.board_state, from, unto, promotion).expect("the move was made");
+        let irreversible = matches!(success, Success::PawnMovement | Success::PawnMovementAndCheck | Success::Capture);
+        self.record_move(
+            new_state,
+            HistoryEntry {
+                from,
+                unto,
+                promotion,
+                think_time,
+            },
+            irreversible,
+        );
 
-                true
-            }
-            None => false,
+        let check = self.is_checked(self.side_to_move());
+        let can_move = any_legal_moves(&self.board_state);
+        Ok(MoveOutcome {
+            mv,
+            mover: !self.side_to_move(),
+            think_time,
+            result: self.result(),
+            king_threat: match (check, can_move) {
+                (false, _) => KingThreat::None,
+                (true, true) => KingThreat::Check,
+                (true, false) => KingThreat::CheckMate,
+            },
+            stalemate: !check && !can_move,
+        })
+    }
+    /// Passes the turn without moving, which isn't allowed in check
+    pub fn make_null_move(&mut self) -> Result<(), MoveError> {
+        self.make_timed_null_move(None)
+    }
+    fn make_timed_null_move(&mut self, think_time: Option<Duration>) -> Result<(), MoveError> {
+        if self.is_finished() {
+            return Err(MoveError::GameOver);
+        }
+        let mut new_state = self.board_state;
+        new_state.make_null_move()?;
+        let king = self.board_state.find_king(self.side_to_move());
+        self.record_move(
+            new_state,
+            HistoryEntry {
+                from: king,
+                unto: king,
+                promotion: None,
+                think_time,
+            },
+            false,
+        );
+        Ok(())
+    }
+    /// Makes a move from the history of a game, which may be a null move
+    pub fn make_history_move(&mut self, entry: &HistoryEntry) -> Result<(), MoveError> {
+        if entry.is_null() {
+            self.make_timed_null_move(entry.think_time)
+        } else {
+            self.make_timed_move(entry.from, entry.unto, entry.promotion, entry.think_time).map(|_| ())
+        }
+    }
+    /// Moves on to `new_state`, keeping the clocks and positions for the draw rules up to date
+    fn record_move(&mut self, new_state: BoardState, entry: HistoryEntry, irreversible: bool) {
+        // Going forward along the game keeps the rest of it, anything else starts a new line from here
+        let next = self.future.first().map(|next| (next.from, next.unto, next.promotion));
+        if next == Some((entry.from, entry.unto, entry.promotion)) {
+            self.future.remove(0);
+        } else if !self.future.is_empty() {
+            self.future.clear();
+            self.annotations.truncate(self.history.len());
+        }
+        // Making a move declines the opponent's draw offer
+        if self.draw_offer != Some(self.side_to_move()) {
+            self.draw_offer = None;
+        }
+        self.board_state = new_state;
+        self.history.push(entry);
+        if irreversible {
+            self.last_move_states.clear();
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
+        *self.last_move_states.entry(self.board_state).or_insert(0) += 1;
+        if matches!(self.side_to_move(), Colour::White) {
+            self.fullmove_count = self.fullmove_count.checked_add(1).unwrap();
         }
     }
     pub fn print_game(&self) {
@@ -109,6 +540,84 @@ impl Game {
     pub fn board_state(&self) -> &BoardState {
         &self.board_state
     }
+    /// The moves played in this game, not including any moves before the starting position
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+    /// The number of moves made to reach the current position
+    pub fn current_ply(&self) -> usize {
+        self.history.len()
+    }
+    /// The number of moves in the game, including the ones after the current position
+    pub fn ply_count(&self) -> usize {
+        self.history.len() + self.future.len()
+    }
+    /// Goes to the position after the first `ply` moves of the game, returns whether the game has that many
+    ///
+    /// Moves after it are kept to go forward to again, until a different move is made. Everything else, like the
+    /// history, the result and the PGN, is as of the position gone to.
+    pub fn seek(&mut self, ply: usize) -> bool {
+        if ply > self.ply_count() {
+            return false;
+        }
+        let mut moves = mem::take(&mut self.history);
+        moves.append(&mut self.future);
+        let mut game = self.start();
+        for entry in &moves[..ply] {
+            game.make_history_move(entry).expect("history only has legal moves");
+        }
+        game.future = moves.split_off(ply);
+        // A resignation or agreed draw only ended the game after its last move
+        if game.future.is_empty() {
+            game.decided = self.decided;
+        }
+        game.annotations = mem::take(&mut self.annotations);
+        *self = game;
+        true
+    }
+    /// The position after the first `ply` moves of the game
+    pub fn position_at(&self, ply: usize) -> Option<BoardState> {
+        let mut game = self.clone();
+        game.seek(ply).then_some(game.board_state)
+    }
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.time_control
+    }
+    /// Sets the time control the clocks are worked out from, it isn't enforced
+    pub fn set_time_control(&mut self, time_control: Option<TimeControl>) {
+        self.time_control = time_control;
+    }
+    /// The time the player who made the move at `ply` had left after it, including the increment
+    ///
+    /// This needs a time control and the think times of all of that player's moves up to `ply`.
+    pub fn clock(&self, ply: usize) -> Option<Duration> {
+        let time_control = self.time_control?;
+        let mut left = time_control.base;
+        for entry in self.history.get(..=ply)?.iter().skip(ply % 2).step_by(2) {
+            left = left.saturating_sub(entry.think_time?) + time_control.increment;
+        }
+        Some(left)
+    }
+    /// Sets how long was spent on the move at `ply`, e.g. when read from PGN
+    pub(crate) fn set_think_time(&mut self, ply: usize, think_time: Duration) {
+        if let Some(entry) = self.history.get_mut(ply) {
+            entry.think_time = Some(think_time);
+        }
+    }
+    /// The annotation of the move at `ply` in the game, if it has one
+    pub fn annotation(&self, ply: usize) -> Option<&MoveAnnotation> {
+        self.annotations.get(ply).filter(|annotation| !annotation.is_empty())
+    }
+    /// The annotation of the move at `ply` in the game to add to, `None` if there isn't a move there
+    pub fn annotate(&mut self, ply: usize) -> Option<&mut MoveAnnotation> {
+        if ply >= self.ply_count() {
+            return None;
+        }
+        if self.annotations.len() <= ply {
+            self.annotations.resize_with(ply + 1, MoveAnnotation::default);
+        }
+        Some(&mut self.annotations[ply])
+    }
     pub fn side_to_move(&self) -> Colour {
         self.board_state.side_to_move
     }
@@ -116,7 +625,7 @@ impl Game {
         self.board_state.in_check(side)
     }
     // Ignores check and checkmates
-    pub fn check_move(&self, alg_move: Move) -> Option<(Coords, Coords, Option<Piece>)> {
+    pub fn check_move(&self, alg_move: Move) -> Option<movegen::Move> {
         let to_play = self.board_state.side_to_move;
 
         let (ca, brn) = match self.board_state.side_to_move {
@@ -129,7 +638,7 @@ impl Game {
                 || self.board_state.en_passant_target == Some(destination)
         };
 
-        Some(match alg_move.move_type {
+        let (from, unto, promotion) = match alg_move.move_type {
             MoveType::ShortCastle if ca.short => {
                 (Coords::new(File::E, brn), Coords::new(File::G, brn), None)
             }
@@ -168,73 +677,24 @@ impl Game {
                                 _ => return None,
                             }
                         }
-                        Mover::PieceAtLetter(p, l) => {
-                            let mut move_from = None;
-                            for n in RankRange::full() {
-                                let coords = Coords::new(l, n);
-                                match self.board_state.board.get(coords) {
-                                    Field::Occupied(c, p2)
-                                        if c == to_play
-                                            && p2 == p
-                                            && self.attempt_move(coords, unto, promotes).is_some() =>
-                                    {
-                                        if move_from.is_some() {
-                                            // Ambiguous
-                                            return None;
-                                        } else {
-                                            move_from = Some(coords);
-                                        }
-                                    }
-                                    _ => (),
-                                }
-                            }
-                            move_from?
-                        }
-                        Mover::PieceAtNumber(p, n) => {
-                            let mut move_from = None;
-                            for l in FileRange::full() {
-                                let coords = Coords::new(l, n);
-                                match self.board_state.board.get(coords) {
-                                    Field::Occupied(c, p2)
-                                        if c == to_play
-                                            && p2 == p
-                                            && self.attempt_move(coords, unto, promotes).is_some() =>
-                                    {
-                                        if move_from.is_some() {
-                                            // Ambiguous
-                                            return None;
-                                        } else {
-                                            move_from = Some(coords);
-                                        }
-                                    }
-                                    _ => (),
-                                }
-                            }
-                            move_from?
-                        }
-                        Mover::Piece(p) => {
-                            let mut move_from = None;
-                            for n in RankRange::full() {
-                                for l in FileRange::full() {
-                                    let coords = Coords::new(l, n);
-                                    match self.board_state.board.get(coords) {
-                                        Field::Occupied(c, p2)
-                                            if c == to_play
-                                                && p2 == p
-                                                && self.attempt_move(coords, unto, promotes).is_some() =>
-                                        {
-                                            if move_from.is_some() {
-                                                // Ambiguous
-                                                return None;
-                                            } else {
-                                                move_from = Some(coords);
-                                            }
-                                        }
-                                        _ => (),
+                        Mover::PieceAtLetter(p, _) | Mover::PieceAtNumber(p, _) | Mover::Piece(p) => {
+                            let mut moves = Vec::new();
+                            movegen::moves_for_piece(&self.board_state, p, &mut moves).unwrap();
+                            let mut candidates = moves.into_iter().filter(|mv| {
+                                mv.unto == unto
+                                    && mv.promotion == promotes
+                                    && match mover {
+                                        Mover::PieceAtLetter(_, l) => mv.from.f() == l,
+                                        Mover::PieceAtNumber(_, n) => mv.from.r() == n,
+                                        _ => true,
                                     }
-                                }
+                            });
+                            let from = candidates.next()?.from;
+                            if candidates.next().is_some() {
+                                // Ambiguous
+                                return None;
                             }
-                            move_from?
+                            from
                         }
                     },
                     unto,
@@ -242,29 +702,753 @@ impl Game {
                 )
             }
             _ => return None,
+        };
+        movegen::Move::new(&self.board_state, from, unto, promotion)
+    }
+    /// Writes a legal move in the current position in standard algebraic notation
+    pub fn san(&self, from: Coords, unto: Coords, promotion: Option<Piece>) -> Option<Move> {
+        let (_, after) = self.attempt_move(from, unto, promotion).ok()?;
+        let Field::Occupied(_, piece) = self.board_state.get(from) else { return None };
+
+        let move_type = if piece == Piece::King && unto.sub(from).0.abs() == 2 {
+            if unto.f() == File::G {
+                MoveType::ShortCastle
+            } else {
+                MoveType::LongCastle
+            }
+        } else {
+            let captures = self.board_state.get(unto).is_occupied()
+                || (piece == Piece::Pawn && self.board_state.en_passant_target == Some(unto));
+
+            let mover = if piece == Piece::Pawn {
+                if captures {
+                    Mover::PieceAtLetter(piece, from.f())
+                } else {
+                    Mover::Piece(piece)
+                }
+            } else {
+                // Other pieces of the same kind that could also go to the destination
+                let mut moves = Vec::new();
+                movegen::moves_for_piece(&self.board_state, piece, &mut moves).unwrap();
+                let others = moves
+                    .into_iter()
+                    .filter(|mv| mv.from != from && mv.unto == unto && mv.promotion == promotion)
+                    .map(|mv| mv.from);
+                let (mut any, mut same_file, mut same_rank) = (false, false, false);
+                for cs in others {
+                    any = true;
+                    same_file |= cs.f() == from.f();
+                    same_rank |= cs.r() == from.r();
+                }
+                match (any, same_file, same_rank) {
+                    (false, _, _) => Mover::Piece(piece),
+                    (true, false, _) => Mover::PieceAtLetter(piece, from.f()),
+                    (true, true, false) => Mover::PieceAtNumber(piece, from.r()),
+                    (true, true, true) => Mover::PieceAt(piece, from),
+                }
+            };
+
+            MoveType::Regular {
+                mover,
+                captures,
+                destination: unto,
+                promotes: promotion,
+            }
+        };
+
+        let king_threat = if !after.in_check(after.side_to_move) {
+            KingThreat::None
+        } else if any_legal_moves(&after) {
+            KingThreat::Check
+        } else {
+            KingThreat::CheckMate
+        };
+
+        Some(Move {
+            move_type,
+            king_threat,
+            annotation: None,
         })
     }
-    pub const fn display_fen(&self) -> GameFen {
-        GameFen { inner: self }
+    /// Writes a line of moves from the current position in SAN, stopping before the first one that isn't legal
+    pub fn san_line(&self, moves: &[movegen::Move]) -> Vec<Move> {
+        let mut game = self.clone();
+        let mut sans = Vec::with_capacity(moves.len());
+        for mv in moves {
+            let Some(san) = game.san(mv.from, mv.unto, mv.promotion) else { break };
+            game.make_move(mv.from, mv.unto, mv.promotion).expect("the move was written");
+            sans.push(san);
+        }
+        sans
+    }
+    /// Plays a sequence of moves in SAN, requiring every move to be written exactly
+    /// as [`Game::san`] would write it (minimal disambiguation, correct capture, check and mate marks)
+    pub fn replay_strict<'a, I: IntoIterator<Item = &'a str>>(&mut self, moves: I) -> Result<(), ReplayError> {
+        for (ply, san) in moves.into_iter().enumerate() {
+            let given = Move::parse_strict(san).map_err(|error| ReplayError::Unparsable {
+                ply,
+                san: san.to_owned(),
+                error,
+            })?;
+            let illegal = || ReplayError::Illegal {
+                ply,
+                san: san.to_owned(),
+            };
+            if given.move_type == MoveType::Null {
+                self.make_null_move().map_err(|_| illegal())?;
+                continue;
+            }
+            let mv = self.check_move(given).ok_or_else(illegal)?;
+            let expected = self.san(mv.from, mv.unto, mv.promotion).ok_or_else(illegal)?;
+            if given != expected {
+                return Err(ReplayError::Mismatch { ply, given, expected });
+            }
+            self.make_move(mv.from, mv.unto, mv.promotion).expect("move was checked");
+        }
+        Ok(())
+    }
+    /// Plays the moves in algebraic notation, accepting any way of writing a move that picks out a single legal move
+    pub fn replay<'a, I: IntoIterator<Item = &'a str>>(&mut self, moves: I) -> Result<(), ReplayError> {
+        for (ply, san) in moves.into_iter().enumerate() {
+            let given = Move::from_str(san).map_err(|error| ReplayError::Unparsable {
+                ply,
+                san: san.to_owned(),
+                error,
+            })?;
+            let illegal = || ReplayError::Illegal {
+                ply,
+                san: san.to_owned(),
+            };
+            if given.move_type == MoveType::Null {
+                self.make_null_move().map_err(|_| illegal())?;
+                continue;
+            }
+            let mv = self.check_move(given).ok_or_else(illegal)?;
+            self.make_move(mv.from, mv.unto, mv.promotion).map_err(|_| illegal())?;
+        }
+        Ok(())
+    }
+    /// Reads the first game of a PGN file, returning it after its last move along with the moves written out again
+    pub fn from_pgn(pgn: &str) -> Result<(Game, MoveList), PgnError> {
+        let pgn_game = PgnGame::parse(pgn).ok_or(PgnError::NoGame)?;
+        let game = pgn_game.to_game()?;
+        let mut moves = game.move_list();
+        if let Some(result) = pgn_game.result {
+            moves = moves.with_result(result);
+        }
+        Ok((game, moves))
+    }
+    /// The game as PGN with `tags`, adding `SetUp` and `FEN` tags if it didn't start from the usual position
+    ///
+    /// The `Result` tag and the end of the movetext come from the `Result` in `tags` if there is one
+    /// and otherwise from [`Game::result`]. The setup, result and `Termination` tags are written after the other tags.
+    pub fn to_pgn(&self, tags: &Tags) -> String {
+        let result = tags.get("Result").and_then(PgnResult::from_str).unwrap_or_else(|| self.result().into());
+        let mut tags = tags.clone();
+        let termination = tags.remove("Termination");
+        tags.remove("Result");
+
+        let start = self.start();
+        if *start.board_state() != BoardState::new() {
+            tags.set("SetUp", "1");
+            tags.set("FEN", start.display_fen().to_string());
+        }
+        tags.set("Result", result.to_string());
+        if let (Some(time_control), None) = (self.time_control, tags.get("TimeControl")) {
+            tags.set("TimeControl", time_control.to_string());
+        }
+        if let Some(termination) = termination {
+            tags.set("Termination", termination);
+        }
+        format!("{tags}\n{}\n\n", self.move_list().with_width(80).with_result(result))
+    }
+    /// The moves played in this game in standard algebraic notation, numbered from the starting position
+    pub fn move_list(&self) -> MoveList {
+        let mut replay = self.start();
+        let mut moves = Vec::with_capacity(self.history.len());
+        for entry in &self.history {
+            let san = if entry.is_null() {
+                Move {
+                    move_type: MoveType::Null,
+                    king_threat: KingThreat::None,
+                    annotation: None,
+                }
+            } else {
+                replay.san(entry.from, entry.unto, entry.promotion).expect("history only has legal moves")
+            };
+            moves.push(san);
+            replay.make_history_move(entry).expect("history only has legal moves");
+        }
+        let (board_state, _, fullmove_count) = self.start;
+        let timed = self.history.iter().rposition(|entry| entry.think_time.is_some()).map_or(0, |i| i + 1);
+        MoveList::new(moves, fullmove_count.get(), board_state.side_to_move)
+            .with_annotations(self.annotations.clone())
+            .with_think_times(self.history[..timed].iter().map(|entry| entry.think_time).collect())
+            .with_clocks((0..timed).map(|ply| self.clock(ply)).collect())
+    }
+    pub const fn display_fen(&self) -> GameFen<'_> {
+        GameFen {
+            inner: self,
+            style: FenStyle::new(),
+        }
+    }
+}
+
+/// What a move did, as returned by [`Game::make_move`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MoveOutcome {
+    /// The move along with what it captured, and whether it was en passant, castling or a promotion
+    pub mv: movegen::Move,
+    /// Whether the opponent is now in check or checkmated
+    pub king_threat: KingThreat,
+    /// Whether the opponent now has no legal moves without being in check
+    pub stalemate: bool,
+    pub mover: Colour,
+    /// How long the mover spent thinking about the move, if known
+    pub think_time: Option<Duration>,
+    /// How the game stands after the move
+    pub result: GameResult,
+}
+
+impl MoveOutcome {
+    pub const fn captured(&self) -> Option<Piece> {
+        self.mv.captured
+    }
+    /// Whether the game ended with the move, by checkmate or stalemate
+    pub fn ends_game(&self) -> bool {
+        self.stalemate || self.king_threat == KingThreat::CheckMate
+    }
+    /// Displays the think time as a PGN `[%emt H:MM:SS]` command if it is known
+    pub fn display_emt(&self) -> Option<Emt> {
+        self.think_time.map(Emt)
+    }
+    /// Everything that happened with the move, in the order of [`GameEvent`]'s variants
+    pub fn events(&self) -> Vec<GameEvent> {
+        let by = self.mover;
+        let mut events = vec![GameEvent::Moved { mv: self.mv, by }];
+        if let Some(piece) = self.mv.captured {
+            events.push(GameEvent::Captured { piece, by });
+        }
+        if let Some(piece) = self.mv.promotion {
+            events.push(GameEvent::Promoted { piece, by });
+        }
+        if self.king_threat != KingThreat::None {
+            events.push(GameEvent::Check { side: !by });
+        }
+        match self.result {
+            GameResult::Ongoing => (),
+            GameResult::Draw(reason) if !reason.is_automatic() => events.push(GameEvent::DrawClaimable(reason)),
+            result => events.push(GameEvent::Ended(result)),
+        }
+        if let Some(think_time) = self.think_time {
+            events.push(GameEvent::Clock { colour: by, think_time });
+        }
+        events
+    }
+}
+
+/// Something that happened in a game, so front ends can react to a move without working out what it did themselves
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    Moved { mv: movegen::Move, by: Colour },
+    /// `by` took `piece`, also en passant
+    Captured { piece: Piece, by: Colour },
+    Promoted { piece: Piece, by: Colour },
+    /// `side` is in check, also when it's checkmate
+    Check { side: Colour },
+    /// A draw can be claimed, but the game goes on until it is
+    DrawClaimable(DrawReason),
+    /// The game is over by checkmate or a draw that needn't be claimed
+    Ended(GameResult),
+    /// `colour` spent `think_time` on its move
+    Clock { colour: Colour, think_time: Duration },
+}
+
+/// Whether a game is over and how, see [`Game::result`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameResult {
+    Ongoing,
+    WhiteWins(WinReason),
+    BlackWins(WinReason),
+    Draw(DrawReason),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WinReason {
+    Checkmate,
+    Resignation,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    /// The players agreed to a draw
+    Agreement,
+    /// The same position has occurred three times
+    Repetition,
+    /// Fifty moves by each side without a capture or pawn move
+    FiftyMove,
+    InsufficientMaterial,
+    /// The same position has occurred five times
+    FivefoldRepetition,
+    /// Seventy-five moves by each side without a capture or pawn move
+    SeventyFiveMove,
+}
+
+impl DrawReason {
+    /// Whether the game is drawn as soon as this happens, rather than when a player claims it
+    pub fn is_automatic(self) -> bool {
+        !matches!(self, DrawReason::Repetition | DrawReason::FiftyMove)
+    }
+}
+
+impl GameResult {
+    pub fn is_over(self) -> bool {
+        self != GameResult::Ongoing
+    }
+    pub fn winner(self) -> Option<Colour> {
+        match self {
+            GameResult::WhiteWins(_) => Some(Colour::White),
+            GameResult::BlackWins(_) => Some(Colour::Black),
+            GameResult::Ongoing | GameResult::Draw(_) => None,
+        }
+    }
+}
+
+/// Why a replay failed, `ply` counts from 0 at the position the replay started from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The move could not be parsed as algebraic notation
+    Unparsable { ply: usize, san: String, error: ParseMoveError },
+    /// The move does not describe a legal move in the position
+    Illegal { ply: usize, san: String },
+    /// The move is legal, but is not written the way standard algebraic notation requires
+    Mismatch { ply: usize, given: Move, expected: Move },
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Unparsable { ply, san, error } => write!(f, "could not parse {san} at ply {ply}, {error}"),
+            ReplayError::Illegal { ply, san } => write!(f, "{san} at ply {ply} is not a legal move"),
+            ReplayError::Mismatch { ply, given, expected } => write!(f, "{given} at ply {ply} should be written {expected}"),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/// Displays the full FEN, like [`Game::display_fen`]
+impl Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_fen().fmt(f)
+    }
+}
+
+impl FromStr for Game {
+    type Err = FenError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Game::from_fen(s).ok_or(FenError)
     }
 }
 
 pub struct GameFen<'a> {
     inner: &'a Game,
+    style: FenStyle,
+}
+
+impl GameFen<'_> {
+    pub const fn with_style(self, style: FenStyle) -> Self {
+        GameFen { style, ..self }
+    }
 }
 
 impl Display for GameFen<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let Game {
             board_state,
-            last_move_states,
+            halfmove_clock,
             fullmove_count,
+            ..
         } = &self.inner;
-        write!(
-            f,
-            "{} {} {fullmove_count}",
-            board_state.display_fen(),
-            last_move_states.values().sum::<u8>()
-        )
+        write!(f, "{}", board_state.display_fen().with_style(self.style))?;
+        match self.style.clocks {
+            FenClocks::Numbers => write!(f, " {halfmove_clock} {fullmove_count}"),
+            FenClocks::Dashes | FenClocks::Omitted => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::{Scenario, Status};
+
+    #[test]
+    fn test_replay_strict() {
+        let mut game = Game::new();
+        assert_eq!(game.replay_strict("e4 e5 Nf3 Nc6 Bb5 a6 Bxc6 dxc6 O-O".split(' ')), Ok(()));
+
+        let mut game = Game::new();
+        match game.replay_strict("e4 e5 Ng1f3".split(' ')) {
+            Err(ReplayError::Mismatch { ply: 2, expected, .. }) => assert_eq!(expected.to_string(), "Nf3"),
+            res => panic!("unexpected {res:?}"),
+        }
+
+        let mut game = Game::new();
+        match game.replay_strict("e4 e5 Qh5 Nc6 Bc4 Nf6 Qxf7+".split(' ')) {
+            Err(ReplayError::Mismatch { ply: 6, expected, .. }) => assert_eq!(expected.to_string(), "Qxf7#"),
+            res => panic!("unexpected {res:?}"),
+        }
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+        assert_eq!(game.replay_strict(["Rad1"]), Ok(()));
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+        assert!(matches!(game.replay_strict(["Rd1"]), Err(ReplayError::Illegal { ply: 0, .. })));
+
+        for san in ["0-0", "O-O ", "Qxf7++", "e8Q", "exd8=K", "e2e4", "xd5", "Nf3!"] {
+            assert!(Move::parse_strict(san).is_err(), "{san}");
+        }
+        for san in ["O-O-O", "Qxf7#", "e8=Q", "exd8=N+", "R1a3", "Qh4xe1"] {
+            assert_eq!(Move::parse_strict(san).map(|mv| mv.to_string()).as_deref(), Ok(san));
+        }
+        let mut game = Game::new();
+        let moves = ["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "0-0"];
+        assert!(matches!(game.replay_strict(moves), Err(ReplayError::Unparsable { ply: 6, .. })));
+
+        let mut game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert!(game.clone().replay_strict(["exd6 e.p."]).is_err());
+        assert_eq!(game.replay(["exd6 e.p.", "Kd7", "Ke2", "Kxd6"]), Ok(()));
+        assert_eq!(Move::from_str("exd6e.p.+").unwrap().to_string(), "exd6+");
+    }
+
+    #[test]
+    fn test_result() {
+        let result = |fen| Game::from_fen(fen).unwrap().result();
+        assert_eq!(Game::new().result(), GameResult::Ongoing);
+        assert_eq!(result("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"), GameResult::BlackWins(WinReason::Checkmate));
+        assert_eq!(result("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), GameResult::Draw(DrawReason::Stalemate));
+        assert_eq!(result("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), GameResult::Draw(DrawReason::InsufficientMaterial));
+        assert_eq!(result("4k3/8/8/8/8/8/8/2N1K3 w - - 0 1"), GameResult::Draw(DrawReason::InsufficientMaterial));
+        assert_eq!(result("2b1k3/8/8/8/8/8/8/3BK3 w - - 0 1"), GameResult::Draw(DrawReason::InsufficientMaterial));
+        assert_eq!(result("3bk3/8/8/8/8/8/8/3BK3 w - - 0 1"), GameResult::Ongoing);
+        assert_eq!(result("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1"), GameResult::Ongoing);
+        assert_eq!(result("4k3/8/8/8/8/8/8/R3K3 w - - 100 80"), GameResult::Draw(DrawReason::FiftyMove));
+
+        let scenario = Scenario::start().play("Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1 Ng8").expect_status(Status::DrawClaimable);
+        let game = scenario.game();
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::Repetition));
+        assert_eq!(game.result().winner(), None);
+        assert!(game.draw_claimable() && !game.is_automatic_draw());
+        let mut game = scenario.play("Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1 Ng8").expect_illegal("Nf3").into_game();
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::FivefoldRepetition));
+        assert!(game.is_automatic_draw());
+        assert_eq!(game.make_move(Coords::G1, Coords::F3, None), Err(MoveError::GameOver));
+        assert_eq!(game.make_null_move(), Err(MoveError::GameOver));
+
+        assert_eq!(result("4k3/8/8/8/8/8/8/R3K3 w - - 150 100"), GameResult::Draw(DrawReason::SeventyFiveMove));
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap().is_automatic_draw());
+
+        // No moves can be made once the game is drawn automatically, but they can when a draw can only be claimed
+        let make_move = |fen| Game::from_fen(fen).unwrap().make_move(Coords::E1, Coords::E2, None);
+        assert_eq!(make_move("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), Err(MoveError::GameOver));
+        assert_eq!(make_move("4k3/8/8/8/8/8/8/R3K3 w - - 150 100"), Err(MoveError::GameOver));
+        assert!(make_move("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").is_ok());
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut game = Scenario::start().play("1. e4 e5 2. Nf3 Nc6 3. Bb5").into_game();
+        let end = *game.board_state();
+        game.annotate(4).unwrap().comments.push("The Spanish".to_owned());
+
+        assert!(game.seek(2));
+        assert_eq!((game.current_ply(), game.ply_count(), game.history().len()), (2, 5, 2));
+        assert_eq!(game.fullmove_number(), 2);
+        assert_eq!(game.position_at(5), Some(end));
+        assert_eq!(game.position_at(0), Some(BoardState::new()));
+        assert_eq!(game.position_at(6), None);
+        assert!(!game.seek(6));
+
+        // Playing the next move of the game keeps the rest, going all the way forward gives the same game back
+        game.replay_strict(["Nf3"]).unwrap();
+        assert_eq!(game.ply_count(), 5);
+        assert!(game.seek(5));
+        assert_eq!(*game.board_state(), end);
+        assert_eq!(game.annotation(4).unwrap().comments, ["The Spanish"]);
+
+        // A different move starts a new line
+        game.seek(4);
+        game.replay_strict(["Bc4"]).unwrap();
+        assert_eq!((game.current_ply(), game.ply_count()), (5, 5));
+        assert_eq!(game.annotation(4), None);
+        assert!(game.seek(0));
+        assert_eq!((game.board_state(), game.history(), game.fullmove_number()), (&BoardState::new(), &[][..], 1));
+    }
+
+    #[test]
+    fn test_seek_past_resignation() {
+        let mut game = Scenario::start().play("1. e4 e5 2. Nf3").into_game();
+        game.resign(Colour::Black);
+
+        // Staying at the end keeps the resignation
+        assert!(game.seek(3));
+        assert_eq!(game.result(), GameResult::WhiteWins(WinReason::Resignation));
+
+        // Before it, the game goes on and a different move can be made
+        assert!(game.seek(2));
+        assert_eq!(game.result(), GameResult::Ongoing);
+        game.replay_strict(["Nc3"]).unwrap();
+        assert_eq!((game.current_ply(), game.ply_count()), (3, 3));
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn test_resign_and_draw_offers() {
+        let mut game = Game::new();
+        game.resign(Colour::White);
+        assert_eq!(game.result(), GameResult::BlackWins(WinReason::Resignation));
+        assert_eq!(game.make_move(Coords::E2, Coords::E4, None), Err(MoveError::GameOver));
+        // The game is already over, so black can't resign it as well
+        game.resign(Colour::Black);
+        assert_eq!(game.result().winner(), Some(Colour::Black));
+
+        let mut game = Game::new();
+        assert!(!game.accept_draw());
+        assert!(!game.offer_draw(Colour::White));
+        game.replay_strict(["e4"]).unwrap();
+        // The offer stands while white moves, but black moving declines it
+        assert_eq!(game.draw_offer(), Some(Colour::White));
+        game.replay_strict(["e5"]).unwrap();
+        assert_eq!(game.draw_offer(), None);
+        assert!(!game.offer_draw(Colour::White));
+        assert!(game.offer_draw(Colour::Black));
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::Agreement));
+        assert!(game.to_pgn(&Tags::default()).contains("1. e4 e5 1/2-1/2"));
+
+        let mut game = Game::new();
+        game.offer_draw(Colour::White);
+        assert!(game.accept_draw());
+        assert_eq!((game.result(), game.draw_offer()), (GameResult::Draw(DrawReason::Agreement), None));
+    }
+
+    #[test]
+    fn test_repetition_count() {
+        assert_eq!(Game::new().repetition_count(), 1);
+        let scenario = Scenario::start().play("Nf3 Nf6 Ng1 Ng8 Nf3").expect_status(Status::Normal);
+        let game = scenario.game();
+        assert_eq!(game.repetition_count(), 2);
+        assert!(game.position_repeated());
+        assert_eq!(game.position_count(&BoardState::new()), 2);
+        let after_e4 = *Scenario::start().play("e4").game().board_state();
+        assert_eq!(game.position_count(&after_e4), 0);
+
+        // A pawn move means none of the earlier positions can come up again
+        let scenario = scenario.play("e5");
+        let game = scenario.game();
+        assert_eq!(game.repetition_count(), 1);
+        assert_eq!(game.position_count(&BoardState::new()), 0);
+    }
+
+    #[test]
+    fn test_null_move() {
+        let mut game = Game::new();
+        assert_eq!(game.replay(["e4", "--", "d4", "Z0"]), Ok(()));
+        assert_eq!(game.display_fen().to_string(), "rnbqkbnr/pppppppp/8/8/3PP3/8/PPP2PPP/RNBQKBNR w KQkq - 1 3");
+        assert!(game.history()[1].is_null());
+        assert_eq!(game.move_list().to_string(), "1. e4 -- 2. d4 --");
+
+        let mut replay = game.start();
+        for entry in game.history() {
+            replay.make_history_move(entry).unwrap();
+        }
+        assert_eq!(replay.board_state(), game.board_state());
+
+        let mut game = Scenario::start().play("e4 f5 Qh5+").expect_status(Status::Check).into_game();
+        assert_eq!(game.make_null_move(), Err(MoveError::LeavesKingInCheck));
+    }
+
+    #[test]
+    fn test_from_str_and_display() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2";
+        let game: Game = fen.parse().unwrap();
+        assert_eq!(game.to_string(), fen);
+        let state: BoardState = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6".parse().unwrap();
+        assert_eq!(&state, game.board_state());
+        assert_eq!(state.to_string(), fen[..fen.len() - 4]);
+        assert_eq!("not a fen".parse::<Game>(), Err(FenError));
+        let style = FenStyle::new().with_legal_en_passant_only(true);
+        assert_eq!(game.display_fen().with_style(style).to_string(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+        assert_eq!(game.display_fen().with_style(style.with_clocks(FenClocks::Dashes)).to_string(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - - -");
+        assert_eq!(game.display_fen().with_style(FenStyle::new().with_clocks(FenClocks::Omitted)).to_string(), fen[..fen.len() - 4]);
+
+        assert_eq!("Nf3".parse::<Move>().map(|mv| mv.to_string()), Ok("Nf3".to_owned()));
+        assert!("Zz9".parse::<Move>().is_err());
+        assert_eq!("b".parse(), Ok(Colour::Black));
+        assert_eq!(Colour::White.to_string().parse(), Ok(Colour::White));
+        assert_eq!("n".parse(), Ok(Piece::Knight));
+        assert_eq!("P".parse(), Ok(Piece::Pawn));
+        assert!("X".parse::<Piece>().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut game = Game::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2").unwrap();
+        game.make_timed_move(Coords::from_str("g1").unwrap(), Coords::from_str("f3").unwrap(), None, Some(Duration::from_secs(3)))
+            .unwrap();
+        game.replay_strict(["Nc6", "Bb5"]).unwrap();
+
+        let toml = toml::to_string(&game).unwrap();
+        assert!(toml.contains("\"rnbqkbnr\""), "{toml}");
+        let read: Game = toml::from_str(&toml).unwrap();
+        assert_eq!(read, game);
+        assert_eq!(read.history()[0].think_time, Some(Duration::from_secs(3)));
+        game.resign(Colour::Black);
+        assert_eq!(toml::from_str::<Game>(&toml::to_string(&game).unwrap()).unwrap().result(), GameResult::WhiteWins(WinReason::Resignation));
+
+        let state: BoardState = toml::from_str(&toml::to_string(game.board_state()).unwrap()).unwrap();
+        assert_eq!(&state, game.board_state());
+        let no_king = toml::to_string(game.board_state()).unwrap().replacen("qkb", "q.b", 1);
+        assert_eq!(toml::from_str::<BoardState>(&no_king).unwrap_err().to_string(), "black must have exactly one king");
+    }
+
+    #[test]
+    fn test_halfmove_clock() {
+        let scenario = Scenario::start()
+            .play("Nf3 Nf6")
+            .expect_fen("rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 2 2");
+        assert_eq!(scenario.game().halfmove_clock(), 2);
+        assert_eq!(scenario.play("e4").game().halfmove_clock(), 0);
+
+        let scenario = Scenario::new("4k3/8/8/8/8/8/3p4/R6K w - - 99 80");
+        assert_eq!(scenario.game().start().halfmove_clock(), 99);
+        let scenario = scenario.play("Ra2").expect_status(Status::DrawClaimable).expect_fen("4k3/8/8/8/8/8/R2p4/7K b - - 100 80");
+        assert_eq!(scenario.game().result(), GameResult::Draw(DrawReason::FiftyMove));
+        let game = scenario.play("d1=Q+").expect_status(Status::Check).into_game();
+        assert_eq!(game.halfmove_clock(), 0);
+        assert_eq!(game.start().display_fen().to_string(), "4k3/8/8/8/8/8/3p4/R6K w - - 99 80");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        use crate::algebraic::Expected;
+        let error = |offset, expected| Err(ParseMoveError { offset, expected });
+        assert_eq!(Move::from_str("Nbd7x"), error(4, Expected::End));
+        assert_eq!(Move::from_str("Nb"), error(2, Expected::Rank));
+        assert_eq!(Move::from_str("Bxz3"), error(2, Expected::Square));
+        assert_eq!(Move::from_str("e8="), error(3, Expected::Promotion));
+        assert_eq!(Move::from_str(" Y"), error(1, Expected::Move));
+        assert_eq!(Move::parse_strict("0-0"), error(0, Expected::Standard));
+        assert_eq!(Move::parse_strict("Qh4++"), error(3, Expected::Standard));
+        assert_eq!("e2e9".parse::<movegen::UciMove>().unwrap_err(), ParseMoveError { offset: 2, expected: Expected::Square });
+
+        let mut game = Game::new();
+        let err = game.replay(["e4", "Nbd7x"]).unwrap_err();
+        assert_eq!(err.to_string(), "could not parse Nbd7x at ply 1, expected the end of the move at byte 4");
+    }
+
+    #[test]
+    fn test_notation() {
+        use crate::algebraic::Notation;
+        let mut game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K1N1 w - - 0 1").unwrap();
+        let danish = Notation::DANISH;
+        let mv = Move::parse_with("Sf3", &danish).unwrap();
+        assert_eq!(mv.to_string(), "Nf3");
+        assert_eq!(mv.display(&danish).to_string(), "Sf3");
+        assert_eq!(Move::parse_with("b8=D+", &danish).unwrap().promotion(), Some(Piece::Queen));
+        assert!(Move::parse_with("Nf3", &danish).is_err());
+        let mv = game.check_move(mv).unwrap();
+        game.make_move(mv.from, mv.unto, mv.promotion).unwrap();
+        assert_eq!(game.replay(["Kd7"]), Ok(()));
+        let mate = game.san(Coords::from_str("b7").unwrap(), Coords::from_str("b8").unwrap(), Some(Piece::Rook));
+        assert_eq!(mate.unwrap().display(&danish).to_string(), "b8=T");
+    }
+
+    #[test]
+    fn test_san_line() {
+        let cs = |s| Coords::from_str(s).unwrap();
+        let game = Game::new();
+        let mut moves: Vec<_> = [("g1", "f3"), ("d7", "d5"), ("f3", "e5")]
+            .into_iter()
+            .scan(*game.board_state(), |state, (from, unto)| {
+                let mv = movegen::Move::new(state, cs(from), cs(unto), None)?;
+                state.make_move(mv.from, mv.unto, None).ok()?;
+                Some(mv)
+            })
+            .collect();
+        assert_eq!(moves.len(), 3);
+        // The knight has left g1 by then, so the line stops before it
+        moves.extend([moves[0], moves[1]]);
+        let line: Vec<_> = game.san_line(&moves).iter().map(|san| san.to_string()).collect();
+        assert_eq!(line, ["Nf3", "d5", "Ne5"]);
+    }
+
+    #[test]
+    fn test_move_outcome() {
+        let cs = |s| Coords::from_str(s).unwrap();
+        let mut game = Game::new();
+        game.replay_strict("f3 e5 g4".split(' ')).unwrap();
+        let mate = game.make_move(cs("d8"), cs("h4"), None).unwrap();
+        assert_eq!((mate.mv.piece, mate.captured(), mate.king_threat), (Piece::Queen, None, KingThreat::CheckMate));
+        assert!(mate.ends_game());
+
+        let mut game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+        let en_passant = game.make_move(cs("e5"), cs("d6"), None).unwrap();
+        assert!(en_passant.mv.en_passant);
+        assert_eq!(en_passant.captured(), Some(Piece::Pawn));
+        assert_eq!(en_passant.king_threat, KingThreat::None);
+        game.make_move(cs("e8"), cs("d8"), None).unwrap();
+        let castle = game.make_move(cs("e1"), cs("g1"), None).unwrap();
+        assert_eq!(castle.mv.castling, Some(movegen::CastleSide::Short));
+
+        assert!(!castle.stalemate);
+        let stalemate = Game::from_fen("k7/8/8/1Q6/8/8/8/K7 w - - 0 1").unwrap().make_move(cs("b5"), cs("b6"), None).unwrap();
+        assert!(stalemate.stalemate && stalemate.ends_game());
+        assert_eq!(stalemate.result, GameResult::Draw(DrawReason::Stalemate));
+    }
+
+    #[test]
+    fn test_move_events() {
+        let cs = |s| Coords::from_str(s).unwrap();
+        let mut game = Game::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let outcome = game.make_timed_move(cs("b7"), cs("a8"), Some(Piece::Queen), Some(Duration::from_secs(2))).unwrap();
+        assert_eq!(
+            outcome.events(),
+            [
+                GameEvent::Moved { mv: outcome.mv, by: Colour::White },
+                GameEvent::Captured { piece: Piece::Rook, by: Colour::White },
+                GameEvent::Promoted { piece: Piece::Queen, by: Colour::White },
+                GameEvent::Check { side: Colour::Black },
+                GameEvent::Clock { colour: Colour::White, think_time: Duration::from_secs(2) },
+            ]
+        );
+
+        let outcome = game.make_move(cs("e8"), cs("e7"), None).unwrap();
+        assert_eq!(outcome.events(), [GameEvent::Moved { mv: outcome.mv, by: Colour::Black }]);
+        let mut game = Game::from_fen("7k/8/8/8/8/8/8/KR6 w - - 99 80").unwrap();
+        let outcome = game.make_move(cs("b1"), cs("b2"), None).unwrap();
+        assert_eq!(outcome.events()[1], GameEvent::DrawClaimable(DrawReason::FiftyMove));
+        let mut game = Game::from_fen("7k/8/8/8/8/8/8/KQ6 w - - 0 1").unwrap();
+        let outcome = game.make_move(cs("b1"), cs("g6"), None).unwrap();
+        assert_eq!(outcome.events()[1], GameEvent::Ended(GameResult::Draw(DrawReason::Stalemate)));
+    }
+
+    #[test]
+    fn test_move_errors() {
+        let cs = |s| Coords::from_str(s).unwrap();
+        let mut game = Game::from_fen("r3r2k/1P5p/8/8/8/8/3rB3/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(game.make_move(cs("c3"), cs("c4"), None), Err(MoveError::NoPieceOnSquare));
+        assert_eq!(game.make_move(cs("d2"), cs("d1"), None), Err(MoveError::WrongColour));
+        assert_eq!(game.make_move(cs("a1"), cs("b2"), None), Err(MoveError::IllegalPieceMovement));
+        assert_eq!(game.make_move(cs("e2"), cs("d3"), None), Err(MoveError::LeavesKingInCheck));
+        assert_eq!(game.make_move(cs("b7"), cs("b8"), None), Err(MoveError::MissingPromotion));
+        assert_eq!(game.make_move(cs("b7"), cs("b8"), Some(Piece::King)), Err(MoveError::IllegalPromotion));
+        assert_eq!(game.make_move(cs("a1"), cs("a2"), Some(Piece::Queen)), Err(MoveError::IllegalPromotion));
+        // The king would pass d1, which the rook on d2 attacks
+        assert_eq!(game.make_move(cs("e1"), cs("c1"), None), Err(MoveError::IllegalCastle));
+        assert_eq!(game.board_state(), Game::from_fen("r3r2k/1P5p/8/8/8/8/3rB3/R3K2R w KQ - 0 1").unwrap().board_state());
+        assert!(game.history().is_empty());
+        assert_eq!(game.make_move(cs("e1"), cs("d2"), None).map(|outcome| outcome.captured()), Ok(Some(Piece::Rook)));
     }
 }
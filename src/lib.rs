@@ -1,7 +1,42 @@
+//! Chess rules and a chess engine
+//!
+//! Without the default `std` feature only the rules are available: [`board`], [`location`], [`boardstate`],
+//! [`algebraic`], [`movegen`] and [`polyglot`], which just need an allocator.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod algebraic;
+#[cfg(feature = "std")]
+pub mod archive;
 pub mod board;
 pub mod boardstate;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod epd;
+#[cfg(feature = "std")]
 pub mod game;
 pub mod location;
+#[cfg(feature = "std")]
+pub mod motifs;
 pub mod movegen;
+#[cfg(feature = "std")]
+pub mod openings;
+#[cfg(feature = "std")]
+pub mod pgn;
+#[cfg(feature = "std")]
+pub mod player;
+pub mod polyglot;
+pub mod prelude;
+#[cfg(feature = "std")]
+pub mod scenario;
+#[cfg(feature = "std")]
+pub mod tablebase;
+#[cfg(feature = "std")]
+pub mod trainer;
+#[cfg(feature = "std")]
+pub mod uci;
+#[cfg(feature = "std")]
 pub mod bots;
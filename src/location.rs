@@ -1,4 +1,6 @@
-use std::{fmt::{self, Display}, iter};
+use core::{array, error::Error, fmt::{self, Display}, iter, ops::{Add, Index, IndexMut, Sub}, str::FromStr};
+
+use crate::board::Colour;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct File(u8);
@@ -36,6 +38,14 @@ impl File {
     pub fn i8(self) -> i8 {
         self.0 as i8
     }
+    /// The colour of the square on this file and `rank`, light squares being white
+    pub fn square_colour(self, rank: Rank) -> Colour {
+        if (self.i8() + rank.i8()) % 2 == 0 {
+            Colour::Black
+        } else {
+            Colour::White
+        }
+    }
 }
 
 impl From<File> for usize {
@@ -51,6 +61,17 @@ impl Display for File {
     }
 }
 
+impl FromStr for File {
+    type Err = ParseLocationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next().and_then(File::from_char), chars.next()) {
+            (Some(l), None) => Ok(l),
+            _ => Err(ParseLocationError),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Rank(u8);
 
@@ -84,6 +105,10 @@ impl Rank {
     pub fn i8(self) -> i8 {
         (self.0 >> 3) as i8
     }
+    /// The colour of the square on this rank and `file`, see [`File::square_colour`]
+    pub fn square_colour(self, file: File) -> Colour {
+        file.square_colour(self)
+    }
 }
 
 impl From<Rank> for usize {
@@ -99,14 +124,142 @@ impl Display for Rank {
     }
 }
 
+impl FromStr for Rank {
+    type Err = ParseLocationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next().and_then(Rank::from_char), chars.next()) {
+            (Some(n), None) => Ok(n),
+            _ => Err(ParseLocationError),
+        }
+    }
+}
+
+/// The error from parsing a [`File`], [`Rank`] or [`Coords`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseLocationError;
+
+impl Display for ParseLocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a file, rank or square on the board")
+    }
+}
+
+impl Error for ParseLocationError {}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct Coords(u8);
 
+/// Steps `(files, ranks)` away, `None` if that's off the board
+impl Add<(i8, i8)> for Coords {
+    type Output = Option<Coords>;
+    fn add(self, (l, n): (i8, i8)) -> Self::Output {
+        Coords::add(self, l, n)
+    }
+}
+
+/// The `(files, ranks)` step from `other` to `self`
+impl Sub for Coords {
+    type Output = (i8, i8);
+    fn sub(self, other: Self) -> Self::Output {
+        Coords::sub(self, other)
+    }
+}
+
+/// Serialized as the square's name, like `e4`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Coords {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Coords {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = alloc::string::String::deserialize(deserializer)?;
+        Coords::from_str(&s).ok_or_else(|| serde::de::Error::custom(format_args!("invalid square {s:?}")))
+    }
+}
+
 impl Coords {
+    pub const A1: Self = Coords::new(File::A, Rank::N1);
+    pub const B1: Self = Coords::new(File::B, Rank::N1);
+    pub const C1: Self = Coords::new(File::C, Rank::N1);
+    pub const D1: Self = Coords::new(File::D, Rank::N1);
+    pub const E1: Self = Coords::new(File::E, Rank::N1);
+    pub const F1: Self = Coords::new(File::F, Rank::N1);
+    pub const G1: Self = Coords::new(File::G, Rank::N1);
+    pub const H1: Self = Coords::new(File::H, Rank::N1);
+
+    pub const A2: Self = Coords::new(File::A, Rank::N2);
+    pub const B2: Self = Coords::new(File::B, Rank::N2);
+    pub const C2: Self = Coords::new(File::C, Rank::N2);
+    pub const D2: Self = Coords::new(File::D, Rank::N2);
+    pub const E2: Self = Coords::new(File::E, Rank::N2);
+    pub const F2: Self = Coords::new(File::F, Rank::N2);
+    pub const G2: Self = Coords::new(File::G, Rank::N2);
+    pub const H2: Self = Coords::new(File::H, Rank::N2);
+
+    pub const A3: Self = Coords::new(File::A, Rank::N3);
+    pub const B3: Self = Coords::new(File::B, Rank::N3);
+    pub const C3: Self = Coords::new(File::C, Rank::N3);
+    pub const D3: Self = Coords::new(File::D, Rank::N3);
+    pub const E3: Self = Coords::new(File::E, Rank::N3);
+    pub const F3: Self = Coords::new(File::F, Rank::N3);
+    pub const G3: Self = Coords::new(File::G, Rank::N3);
+    pub const H3: Self = Coords::new(File::H, Rank::N3);
+
+    pub const A4: Self = Coords::new(File::A, Rank::N4);
+    pub const B4: Self = Coords::new(File::B, Rank::N4);
+    pub const C4: Self = Coords::new(File::C, Rank::N4);
+    pub const D4: Self = Coords::new(File::D, Rank::N4);
+    pub const E4: Self = Coords::new(File::E, Rank::N4);
+    pub const F4: Self = Coords::new(File::F, Rank::N4);
+    pub const G4: Self = Coords::new(File::G, Rank::N4);
+    pub const H4: Self = Coords::new(File::H, Rank::N4);
+
+    pub const A5: Self = Coords::new(File::A, Rank::N5);
+    pub const B5: Self = Coords::new(File::B, Rank::N5);
+    pub const C5: Self = Coords::new(File::C, Rank::N5);
+    pub const D5: Self = Coords::new(File::D, Rank::N5);
+    pub const E5: Self = Coords::new(File::E, Rank::N5);
+    pub const F5: Self = Coords::new(File::F, Rank::N5);
+    pub const G5: Self = Coords::new(File::G, Rank::N5);
+    pub const H5: Self = Coords::new(File::H, Rank::N5);
+
+    pub const A6: Self = Coords::new(File::A, Rank::N6);
+    pub const B6: Self = Coords::new(File::B, Rank::N6);
+    pub const C6: Self = Coords::new(File::C, Rank::N6);
+    pub const D6: Self = Coords::new(File::D, Rank::N6);
+    pub const E6: Self = Coords::new(File::E, Rank::N6);
+    pub const F6: Self = Coords::new(File::F, Rank::N6);
+    pub const G6: Self = Coords::new(File::G, Rank::N6);
+    pub const H6: Self = Coords::new(File::H, Rank::N6);
+
+    pub const A7: Self = Coords::new(File::A, Rank::N7);
+    pub const B7: Self = Coords::new(File::B, Rank::N7);
+    pub const C7: Self = Coords::new(File::C, Rank::N7);
+    pub const D7: Self = Coords::new(File::D, Rank::N7);
+    pub const E7: Self = Coords::new(File::E, Rank::N7);
+    pub const F7: Self = Coords::new(File::F, Rank::N7);
+    pub const G7: Self = Coords::new(File::G, Rank::N7);
+    pub const H7: Self = Coords::new(File::H, Rank::N7);
+
+    pub const A8: Self = Coords::new(File::A, Rank::N8);
+    pub const B8: Self = Coords::new(File::B, Rank::N8);
+    pub const C8: Self = Coords::new(File::C, Rank::N8);
+    pub const D8: Self = Coords::new(File::D, Rank::N8);
+    pub const E8: Self = Coords::new(File::E, Rank::N8);
+    pub const F8: Self = Coords::new(File::F, Rank::N8);
+    pub const G8: Self = Coords::new(File::G, Rank::N8);
+    pub const H8: Self = Coords::new(File::H, Rank::N8);
+
     pub const fn new(l: File, n: Rank) -> Self {
         Coords(l.0 | n.0)
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         let mut chars = s.chars();
         let l = File::from_char(chars.next()?)?;
@@ -136,15 +289,46 @@ impl Coords {
     pub fn from_u8_tuple(l: i8, n: i8) -> Option<Self> {
         Some(Coords::new(File::new(l as u8)?, Rank::new(n as u8)?))
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn sub(self, other: Self) -> (i8, i8) {
         (
             self.f().i8() - other.f().i8(),
             self.r().i8() - other.r().i8(),
         )
     }
+    /// The square on the same file as seen from the other side of the board, e.g. e2 becomes e7
+    pub const fn flip_rank(self) -> Coords {
+        Coords(self.0 ^ 0b111_000)
+    }
+    /// The square on the same rank as seen from the other side of the board, e.g. b1 becomes g1
+    pub const fn flip_file(self) -> Coords {
+        Coords(self.0 ^ 0b000_111)
+    }
     pub fn into_u8(self) -> u8 {
         self.0
     }
+    /// Whether this is a light (white) or dark (black) square, a1 being dark
+    pub fn colour(self) -> Colour {
+        self.f().square_colour(self.r())
+    }
+    /// The larger of the file and rank differences, which is the number of moves a king needs between the squares
+    pub fn chebyshev_distance(self, other: Self) -> u8 {
+        let (dl, dn) = self.sub(other);
+        dl.unsigned_abs().max(dn.unsigned_abs())
+    }
+    /// The sum of the file and rank differences, the number of moves a rook would need one square at a time
+    pub fn manhattan_distance(self, other: Self) -> u8 {
+        let (dl, dn) = self.sub(other);
+        dl.unsigned_abs() + dn.unsigned_abs()
+    }
+    /// The number of moves a king needs to get from one square to the other, see [`Coords::chebyshev_distance`]
+    pub fn king_distance(self, other: Self) -> u8 {
+        self.chebyshev_distance(other)
+    }
+    /// The inverse of [`Coords::into_u8`], `None` for numbers from 64 up
+    pub const fn from_u8(i: u8) -> Option<Self> {
+        if i < 64 { Some(Coords(i)) } else { None }
+    }
     pub fn full_range() -> impl Iterator<Item=Coords> {
         let mut i = 0;
         iter::from_fn(move || {
@@ -165,16 +349,228 @@ impl Display for Coords {
     }
 }
 
-pub const LEAPS: [(i8, i8); 8] = [
-    (2, 1),
-    (2, -1),
-    (1, 2),
-    (1, -2),
-    (-2, 1),
-    (-2, -1),
-    (-1, 2),
-    (-1, -2),
-];
+impl FromStr for Coords {
+    type Err = ParseLocationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Coords::from_str(s).ok_or(ParseLocationError)
+    }
+}
+
+/// An array with a value for every square on the board
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PerSquare<T>(pub [T; 64]);
+
+impl<T: Copy> PerSquare<T> {
+    /// Gives every square the same value
+    pub const fn splat(value: T) -> Self {
+        PerSquare([value; 64])
+    }
+}
+
+impl<T: Default> Default for PerSquare<T> {
+    fn default() -> Self {
+        Self::from_fn(|_| T::default())
+    }
+}
+
+impl<T> PerSquare<T> {
+    pub fn from_fn<F: FnMut(Coords) -> T>(mut f: F) -> Self {
+        PerSquare(array::from_fn(|i| f(Coords(i as u8))))
+    }
+    /// Iterates over all squares in the same order as [`Coords::full_range`] along with their values
+    pub fn iter(&self) -> impl Iterator<Item = (Coords, &T)> {
+        Coords::full_range().zip(&self.0)
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Coords, &mut T)> {
+        Coords::full_range().zip(&mut self.0)
+    }
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> PerSquare<U> {
+        PerSquare(self.0.map(f))
+    }
+    /// Pairs up the values of two arrays square by square
+    pub fn zip<U>(self, other: PerSquare<U>) -> PerSquare<(T, U)> {
+        let mut other = other.0.into_iter();
+        PerSquare(self.0.map(|t| (t, other.next().unwrap())))
+    }
+}
+
+impl<T> Index<Coords> for PerSquare<T> {
+    type Output = T;
+    #[inline]
+    fn index(&self, index: Coords) -> &Self::Output {
+        &self.0[index.0 as usize]
+    }
+}
+
+impl<T> IndexMut<Coords> for PerSquare<T> {
+    #[inline]
+    fn index_mut(&mut self, index: Coords) -> &mut Self::Output {
+        &mut self.0[index.0 as usize]
+    }
+}
+
+/// The kind of line two squares lie on
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Line {
+    None,
+    Straight,
+    Diagonal,
+}
+
+/// Line between two squares and a mask of the squares strictly between them (bit `i` is `Coords(i)`)
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Ray {
+    pub(crate) line: Line,
+    pub(crate) between: u64,
+}
+
+/// Rays indexed by `[from][unto]`
+pub(crate) static RAYS: PerSquare<PerSquare<Ray>> = compute_rays();
+
+const fn compute_rays() -> PerSquare<PerSquare<Ray>> {
+    let mut rays = PerSquare::splat(PerSquare::splat(Ray { line: Line::None, between: 0 }));
+    let mut from = 0;
+    while from < 64 {
+        let (fl, fn_) = ((from & 0b111) as i8, (from >> 3) as i8);
+        let mut unto = 0;
+        while unto < 64 {
+            let (dl, dn) = ((unto & 0b111) as i8 - fl, (unto >> 3) as i8 - fn_);
+            let line = if from == unto {
+                Line::None
+            } else if dl == 0 || dn == 0 {
+                Line::Straight
+            } else if dl == dn || dl == -dn {
+                Line::Diagonal
+            } else {
+                Line::None
+            };
+            let mut between = 0;
+            if !matches!(line, Line::None) {
+                let (sl, sn) = (dl.signum(), dn.signum());
+                let (mut l, mut n) = (fl + sl, fn_ + sn);
+                while l != fl + dl || n != fn_ + dn {
+                    between |= 1 << (n * 8 + l);
+                    l += sl;
+                    n += sn;
+                }
+            }
+            rays.0[from].0[unto] = Ray { line, between };
+            unto += 1;
+        }
+        from += 1;
+    }
+    rays
+}
+
+/// Iterates over the squares in a mask where bit `i` is `Coords(i)`
+#[inline]
+pub fn squares_in(mut mask: u64) -> impl Iterator<Item = Coords> {
+    iter::from_fn(move || {
+        if mask == 0 {
+            None
+        } else {
+            let i = mask.trailing_zeros() as u8;
+            mask &= mask - 1;
+            Some(Coords(i))
+        }
+    })
+}
+
+/// A step across the board, either along one of the eight lines from a square or as a knight's jump
+///
+/// North is towards the eighth rank and east towards the h-file. Knight jumps are named by their long leg first,
+/// so `NNE` is two ranks up and one file to the right.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Direction {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+    NNE,
+    ENE,
+    ESE,
+    SSE,
+    SSW,
+    WSW,
+    WNW,
+    NNW,
+}
+
+impl Direction {
+    /// The directions rooks move in
+    pub const STRAIGHT: [Direction; 4] = [Direction::E, Direction::W, Direction::N, Direction::S];
+    /// The directions bishops move in
+    pub const DIAGONAL: [Direction; 4] = [Direction::NE, Direction::SE, Direction::NW, Direction::SW];
+    /// The directions queens and kings move in, the straight ones first
+    pub const LINES: [Direction; 8] = [
+        Direction::E,
+        Direction::W,
+        Direction::N,
+        Direction::S,
+        Direction::NE,
+        Direction::SE,
+        Direction::NW,
+        Direction::SW,
+    ];
+    pub const KNIGHT: [Direction; 8] = [
+        Direction::ENE,
+        Direction::ESE,
+        Direction::NNE,
+        Direction::SSE,
+        Direction::WNW,
+        Direction::WSW,
+        Direction::NNW,
+        Direction::SSW,
+    ];
+
+    /// The step as `(files, ranks)`
+    #[inline]
+    pub const fn delta(self) -> (i8, i8) {
+        const DELTAS: [(i8, i8); 16] = [
+            (0, 1),
+            (1, 1),
+            (1, 0),
+            (1, -1),
+            (0, -1),
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (1, 2),
+            (2, 1),
+            (2, -1),
+            (1, -2),
+            (-1, -2),
+            (-2, -1),
+            (-2, 1),
+            (-1, 2),
+        ];
+        DELTAS[self as usize]
+    }
+    pub const fn is_knight_jump(self) -> bool {
+        let (dl, dn) = self.delta();
+        dl * dn == 2 || dl * dn == -2
+    }
+}
+
+impl Coords {
+    /// The square one step away in `dir`, if it's on the board
+    #[inline]
+    pub fn step(self, dir: Direction) -> Option<Coords> {
+        let (dl, dn) = dir.delta();
+        self.add(dl, dn)
+    }
+    /// The squares reached by stepping in `dir` again and again until the edge of the board, not including `self`
+    #[inline]
+    pub fn ray(self, dir: Direction) -> impl Iterator<Item = Coords> {
+        let (dl, dn) = dir.delta();
+        (1..).map_while(move |i| self.add(i * dl, i * dn))
+    }
+}
 
 pub struct RankRange {
     start: Rank,
@@ -239,3 +635,89 @@ impl Iterator for FileRange {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("e4".parse(), Ok(Coords::E4));
+        assert_eq!("E4".parse::<Coords>().map(|cs| cs.to_string()), Ok("e4".to_owned()));
+        assert_eq!("e9".parse::<Coords>(), Err(ParseLocationError));
+        assert_eq!("e4 ".parse::<Coords>(), Err(ParseLocationError));
+        assert_eq!("h".parse(), Ok(File::H));
+        assert_eq!("8".parse(), Ok(Rank::N8));
+        assert_eq!("88".parse::<Rank>(), Err(ParseLocationError));
+    }
+
+    #[test]
+    fn test_rays() {
+        let ray = RAYS[Coords::A1][Coords::H8];
+        assert_eq!(ray.line, Line::Diagonal);
+        let between: Vec<_> = squares_in(ray.between).map(|c| c.to_string()).collect();
+        assert_eq!(between, ["b2", "c3", "d4", "e5", "f6", "g7"]);
+
+        let ray = RAYS[Coords::D5][Coords::D1];
+        assert_eq!(ray.line, Line::Straight);
+        assert_eq!(squares_in(ray.between).count(), 3);
+
+        let ray = RAYS[Coords::A1][Coords::B3];
+        assert_eq!(ray.line, Line::None);
+        assert_eq!(ray.between, 0);
+    }
+
+    #[test]
+    fn test_per_square() {
+        let mut ps = PerSquare::from_fn(|c| c.r().i8());
+        assert_eq!(ps[Coords::E4], 3);
+        ps[Coords::E4] = 10;
+        let ps = ps.map(|r| r * 2).zip(PerSquare::from_fn(|c| c.f()));
+        assert_eq!(ps[Coords::E4], (20, File::E));
+        assert_eq!(ps.iter().next(), Some((Coords::A1, &(0, File::A))));
+    }
+
+    #[test]
+    fn test_directions() {
+        let ray: Vec<_> = Coords::C3.ray(Direction::NW).collect();
+        assert_eq!(ray, [Coords::B4, Coords::A5]);
+        assert_eq!(Coords::H1.ray(Direction::E).next(), None);
+        assert_eq!(Coords::A1.ray(Direction::N).count(), 7);
+        assert_eq!(Coords::B1.step(Direction::NNE), Some(Coords::C3));
+        assert!(Direction::KNIGHT.iter().all(|dir| dir.is_knight_jump()));
+        assert!(!Direction::LINES.iter().any(|dir| dir.is_knight_jump()));
+    }
+
+    #[test]
+    fn test_constants_and_arithmetic() {
+        for cs in Coords::full_range() {
+            assert_eq!(Coords::from_str(&cs.to_string()), Some(cs));
+        }
+        assert_eq!(Coords::A1.into_u8(), 0);
+        assert_eq!(Coords::H8.into_u8(), 63);
+        assert_eq!(Coords::G1.to_string(), "g1");
+        assert_eq!(Coords::E2 + (0, 2), Some(Coords::E4));
+        assert_eq!(Coords::H8 + (1, 0), None);
+        assert_eq!(Coords::C3 - Coords::E4, (-2, -1));
+    }
+
+    #[test]
+    fn test_square_colour() {
+        assert_eq!(Coords::A1.colour(), Colour::Black);
+        assert_eq!(Coords::H1.colour(), Colour::White);
+        assert_eq!(Coords::D1.colour(), Colour::White);
+        assert_eq!(Coords::E1.colour(), Colour::Black);
+        assert_eq!(Coords::H8.colour(), Colour::Black);
+        assert_eq!(Rank::N4.square_colour(File::E), Coords::E4.colour());
+        assert_eq!(Coords::full_range().filter(|cs| cs.colour() == Colour::White).count(), 32);
+    }
+
+    #[test]
+    fn test_distances() {
+        assert_eq!(Coords::A1.chebyshev_distance(Coords::H8), 7);
+        assert_eq!(Coords::A1.manhattan_distance(Coords::H8), 14);
+        assert_eq!(Coords::E4.king_distance(Coords::C5), 2);
+        assert_eq!(Coords::E4.manhattan_distance(Coords::C5), 3);
+        assert_eq!(Coords::E4.king_distance(Coords::E4), 0);
+    }
+}
@@ -0,0 +1,191 @@
+//! Detection of tactical motifs, to explain moves in words like "missed a knight fork on c7"
+
+use std::fmt::{self, Display};
+
+use crate::{
+    board::{Colour, Field, Piece},
+    boardstate::BoardState,
+    location::{Coords, Direction, RAYS},
+    movegen::Move,
+};
+
+/// A tactical pattern one side can use against the other
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Motif {
+    /// A piece attacks several pieces that are either worth more than it or undefended
+    Fork { attacker: Coords, piece: Piece, targets: Vec<Coords> },
+    /// A piece can't move off the line it's attacked along without exposing a more valuable piece
+    Pin { attacker: Coords, pinned: Coords, piece: Piece, behind: Coords },
+    /// A piece is attacked and has to move out of the way of a less valuable piece behind it
+    Skewer { attacker: Coords, front: Coords, piece: Piece, behind: Coords },
+    /// Moving a piece opened a line for another piece to attack something
+    DiscoveredAttack { moved: Coords, attacker: Coords, target: Coords, piece: Piece },
+    /// The king is stuck on its back rank behind its own pieces
+    BackRankWeakness { king: Coords },
+}
+
+impl Display for Motif {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Motif::Fork { attacker, piece, .. } => write!(f, "{} fork on {attacker}", name(piece)),
+            Motif::Pin { pinned, piece, .. } => write!(f, "pin of the {} on {pinned}", name(piece)),
+            Motif::Skewer { front, piece, .. } => write!(f, "skewer of the {} on {front}", name(piece)),
+            Motif::DiscoveredAttack { target, piece, .. } => write!(f, "discovered attack on the {} on {target}", name(piece)),
+            Motif::BackRankWeakness { king } => write!(f, "back-rank weakness on {king}"),
+        }
+    }
+}
+
+fn name(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => "pawn",
+        Piece::Knight => "knight",
+        Piece::Bishop => "bishop",
+        Piece::Rook => "rook",
+        Piece::Queen => "queen",
+        Piece::King => "king",
+    }
+}
+
+/// Rough piece values, only used to tell which piece of two is worth more
+fn value(piece: Piece) -> u8 {
+    match piece {
+        Piece::Pawn => 1,
+        Piece::Knight | Piece::Bishop => 3,
+        Piece::Rook => 5,
+        Piece::Queen => 9,
+        Piece::King => 100,
+    }
+}
+
+fn piece_of(state: &BoardState, side: Colour, cs: Coords) -> Option<Piece> {
+    match state.get(cs) {
+        Field::Occupied(c, p) if c == side => Some(p),
+        _ => None,
+    }
+}
+
+/// Whether the piece on `cs` is protected by its own side
+fn defended(state: &BoardState, cs: Coords) -> bool {
+    let Field::Occupied(side, _) = state.get(cs) else {
+        return false;
+    };
+    // Put an enemy piece there so that taking it counts as a move for the defenders
+    let mut state = *state;
+    state.board.set(cs, Field::Occupied(!side, Piece::Pawn));
+    state.attackers(cs, side).next().is_some()
+}
+
+/// All motifs `side` has against the other side in the position
+pub fn find_motifs(state: &BoardState, side: Colour) -> Vec<Motif> {
+    let mut motifs = Vec::new();
+
+    for attacker in Coords::full_range() {
+        let Some(piece) = piece_of(state, side, attacker) else { continue };
+
+        let targets: Vec<_> = Coords::full_range()
+            .filter(|&t| {
+                piece_of(state, !side, t).is_some_and(|p| value(p) > value(piece) || !defended(state, t))
+                    && state.is_pseudo_legal(side, attacker, t)
+            })
+            .collect();
+        if targets.len() >= 2 {
+            motifs.push(Motif::Fork { attacker, piece, targets });
+        }
+
+        let directions: &[_] = match piece {
+            Piece::Rook => &Direction::STRAIGHT,
+            Piece::Bishop => &Direction::DIAGONAL,
+            Piece::Queen => &Direction::LINES,
+            _ => &[],
+        };
+        for &dir in directions {
+            let mut along = attacker.ray(dir).filter(|&cs| state.get(cs).is_occupied());
+            let (Some(front), Some(behind)) = (along.next(), along.next()) else { continue };
+            let (Some(front_piece), Some(behind_piece)) = (piece_of(state, !side, front), piece_of(state, !side, behind)) else {
+                continue;
+            };
+            if value(front_piece) < value(behind_piece) {
+                motifs.push(Motif::Pin { attacker, pinned: front, piece: front_piece, behind });
+            } else if value(front_piece) > value(behind_piece) {
+                motifs.push(Motif::Skewer { attacker, front, piece: front_piece, behind });
+            }
+        }
+    }
+
+    let back_rank = match side {
+        Colour::White => 7,
+        Colour::Black => 0,
+    };
+    let forward = if back_rank == 0 { 1 } else { -1 };
+    let has_heavy_piece = Coords::full_range().any(|cs| matches!(piece_of(state, side, cs), Some(Piece::Rook | Piece::Queen)));
+    if let Some(king) = Coords::full_range().find(|&cs| piece_of(state, !side, cs) == Some(Piece::King)) {
+        let stuck = (-1..=1)
+            .filter_map(|dl| king.add(dl, forward))
+            .all(|cs| piece_of(state, !side, cs).is_some());
+        if king.r().i8() == back_rank && stuck && has_heavy_piece {
+            motifs.push(Motif::BackRankWeakness { king });
+        }
+    }
+
+    motifs
+}
+
+/// The motifs the side making `mv` gains by it, `None` if the move can't be made
+pub fn motifs_of_move(state: &BoardState, mv: Move) -> Option<Vec<Motif>> {
+    let Move { from, unto, promotion, .. } = mv;
+    let side = state.side_to_move;
+    let mut after = *state;
+    after.make_move(from, unto, promotion).ok()?;
+
+    let before = find_motifs(state, side);
+    let mut motifs: Vec<_> = find_motifs(&after, side).into_iter().filter(|m| !before.contains(m)).collect();
+
+    for attacker in Coords::full_range().filter(|&cs| cs != unto) {
+        if !matches!(piece_of(&after, side, attacker), Some(Piece::Rook | Piece::Bishop | Piece::Queen)) {
+            continue;
+        }
+        for target in Coords::full_range() {
+            let Some(piece) = piece_of(&after, !side, target) else { continue };
+            let uncovered = RAYS[attacker][target].between & (1 << from.into_u8()) != 0;
+            if uncovered && after.is_pseudo_legal(side, attacker, target) && !state.is_pseudo_legal(side, attacker, target) {
+                motifs.push(Motif::DiscoveredAttack { moved: unto, attacker, target, piece });
+            }
+        }
+    }
+
+    Some(motifs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cs(s: &str) -> Coords {
+        Coords::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_motifs() {
+        let fork = BoardState::from_fen("r3k3/8/8/3N4/8/8/8/4K3 w - -").unwrap();
+        let motifs = motifs_of_move(&fork, Move::new(&fork, cs("d5"), cs("c7"), None).unwrap()).unwrap();
+        assert_eq!(motifs, [Motif::Fork { attacker: cs("c7"), piece: Piece::Knight, targets: vec![cs("a8"), cs("e8")] }]);
+        assert_eq!(motifs[0].to_string(), "knight fork on c7");
+
+        let pin = BoardState::from_fen("3qk3/8/5n2/8/8/8/8/2B1K3 w - -").unwrap();
+        let motifs = motifs_of_move(&pin, Move::new(&pin, cs("c1"), cs("g5"), None).unwrap()).unwrap();
+        assert!(motifs.contains(&Motif::Pin { attacker: cs("g5"), pinned: cs("f6"), piece: Piece::Knight, behind: cs("d8") }));
+
+        let skewer = BoardState::from_fen("8/4k2r/8/8/8/8/8/R5K1 w - -").unwrap();
+        let motifs = motifs_of_move(&skewer, Move::new(&skewer, cs("a1"), cs("a7"), None).unwrap()).unwrap();
+        assert_eq!(motifs, [Motif::Skewer { attacker: cs("a7"), front: cs("e7"), piece: Piece::King, behind: cs("h7") }]);
+
+        let discovered = BoardState::from_fen("3qk3/8/8/8/3N4/8/8/3RK3 w - -").unwrap();
+        let motifs = motifs_of_move(&discovered, Move::new(&discovered, cs("d4"), cs("b5"), None).unwrap()).unwrap();
+        assert!(motifs.contains(&Motif::DiscoveredAttack { moved: cs("b5"), attacker: cs("d1"), target: cs("d8"), piece: Piece::Queen }));
+
+        let back_rank = BoardState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - -").unwrap();
+        assert_eq!(find_motifs(&back_rank, Colour::White), [Motif::BackRankWeakness { king: cs("g8") }]);
+        assert_eq!(find_motifs(&back_rank, Colour::White)[0].to_string(), "back-rank weakness on g8");
+    }
+}
@@ -1,17 +1,168 @@
-use std::mem;
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Display},
+    mem,
+    str::FromStr,
+};
 
 use crate::{
+    algebraic::{Expected, ParseMoveError},
     board::{Colour, Field, Piece},
     boardstate::BoardState,
-    location::{Coords, Rank, LEAPS},
+    location::{Coords, Direction, PerSquare, Rank},
 };
 
-const STRAIGHTS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
 const CASTLINGS: [(i8, i8); 2] = [(2, 0), (-2, 0)];
-const DIAGANOLS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
-const KNIGHTIES: [(i8, i8); 8] = LEAPS;
+/// A queen in the middle of an empty board has the most moves any one piece can have
+const MAX_MOVES_FROM_SQUARE: usize = 27;
+/// No legal position is known to have more than 218 moves
+pub(crate) const MAX_MOVES: usize = 256;
+
+/// Fills move buffers before they are written to, it isn't a move that can be made
+pub(crate) const PLACEHOLDER_MOVE: Move = Move {
+    from: Coords::A1,
+    unto: Coords::A1,
+    promotion: None,
+    piece: Piece::King,
+    captured: None,
+    en_passant: false,
+    castling: None,
+};
+
+/// A move in a position, along with what it does there
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Coords,
+    pub unto: Coords,
+    pub promotion: Option<Piece>,
+    /// The piece that moves, a pawn for promotions
+    pub piece: Piece,
+    /// The piece that is taken, including the pawn taken en passant
+    pub captured: Option<Piece>,
+    pub en_passant: bool,
+    pub castling: Option<CastleSide>,
+}
+
+/// Which rook a king castles with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CastleSide {
+    /// With the rook on the h-file
+    Short,
+    /// With the rook on the a-file
+    Long,
+}
+
+impl Move {
+    /// Works out what the move does in `state`, `None` if the side to move has no piece on `from`
+    ///
+    /// Whether the move is legal isn't checked.
+    pub fn new(state: &BoardState, from: Coords, unto: Coords, promotion: Option<Piece>) -> Option<Self> {
+        let piece = match state.board.get(from) {
+            Field::Occupied(c, p) if c == state.side_to_move => p,
+            _ => return None,
+        };
+        let en_passant = piece == Piece::Pawn && state.en_passant_target == Some(unto);
+        let captured = if en_passant { Some(Piece::Pawn) } else { state.board.get(unto).into_piece() };
+        let castling = match unto.sub(from) {
+            (2, 0) if piece == Piece::King => Some(CastleSide::Short),
+            (-2, 0) if piece == Piece::King => Some(CastleSide::Long),
+            _ => None,
+        };
+        Some(Move {
+            from,
+            unto,
+            promotion,
+            piece,
+            captured,
+            en_passant,
+            castling,
+        })
+    }
+    #[inline]
+    pub const fn is_capture(&self) -> bool {
+        self.captured.is_some()
+    }
+    /// The move in coordinate notation
+    pub const fn uci(&self) -> UciMove {
+        UciMove {
+            from: self.from,
+            unto: self.unto,
+            promotion: self.promotion,
+        }
+    }
+}
+
+/// A move in the coordinate notation used by UCI, like `e2e4`, `e1g1` for castling or `e7e8q` for a promotion
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UciMove {
+    pub from: Coords,
+    pub unto: Coords,
+    pub promotion: Option<Piece>,
+}
+
+impl UciMove {
+    /// Parses the notation, which says nothing about whether the move is legal in any position
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let from = Coords::from_str(s.get(..2)?)?;
+        let unto = Coords::from_str(s.get(2..4)?)?;
+        let promotion = match &s[4..] {
+            "" => None,
+            "q" => Some(Piece::Queen),
+            "r" => Some(Piece::Rook),
+            "b" => Some(Piece::Bishop),
+            "n" => Some(Piece::Knight),
+            _ => return None,
+        };
+        Some(UciMove { from, unto, promotion })
+    }
+}
+
+impl FromStr for UciMove {
+    type Err = ParseMoveError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UciMove::from_str(s).ok_or_else(|| {
+            let square = |range| s.get(range).and_then(Coords::from_str).is_some();
+            let (offset, expected) = match (square(0..2), square(2..4)) {
+                (false, _) => (0, Expected::Square),
+                (true, false) => (2, Expected::Square),
+                (true, true) => (4, Expected::Promotion),
+            };
+            ParseMoveError { offset, expected }
+        })
+    }
+}
+
+impl UciMove {
+    /// The move with what it does in `state`, see [`Move::new`]
+    pub fn in_position(self, state: &BoardState) -> Option<Move> {
+        Move::new(state, self.from, self.unto, self.promotion)
+    }
+}
 
-pub type Move = (Coords, Coords, Option<Piece>);
+impl From<Move> for UciMove {
+    fn from(mv: Move) -> Self {
+        mv.uci()
+    }
+}
+
+impl Display for UciMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.unto)?;
+        match self.promotion {
+            None => (),
+            Some(Piece::Queen) => write!(f, "q")?,
+            Some(Piece::Rook) => write!(f, "r")?,
+            Some(Piece::Bishop) => write!(f, "b")?,
+            Some(Piece::Knight) => write!(f, "n")?,
+            // Can't be promoted to, but has to be written somehow
+            Some(Piece::Pawn) => write!(f, "p")?,
+            Some(Piece::King) => write!(f, "k")?,
+        }
+        Ok(())
+    }
+}
 
 pub trait AddMove {
     /// Returns an error if it could not add the move due to lack of space
@@ -21,116 +172,392 @@ pub trait AddMove {
 #[derive(Debug, Clone, Copy)]
 pub struct NoMoreSpace;
 
+/// The order in which moves are generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GenMode {
+    /// No particular order is guaranteed and it may change between versions.
+    /// Use this when the order does not matter.
+    #[default]
+    Unordered,
+    /// Moves are ordered by the square they move from (a1, b1, ..., h1, a2, ..., h8)
+    /// and then by a fixed order of directions for each kind of piece.
+    /// Promotions are ordered queen, knight, rook, bishop.
+    /// This order is guaranteed to stay the same.
+    Stable,
+    /// All captures (including en passant) come first and then all other moves,
+    /// each group in the same order as [`GenMode::Stable`].
+    CapturesFirst,
+}
+
+#[inline(always)]
 pub fn gen_legal_moves<B: AddMove>(buf: &mut B, state: &BoardState) -> Result<(), NoMoreSpace> {
-    let mut check_move = |from, unto, promotion| {
-        // bit silly
-        let mut state = state.clone();
-        // Check if move is pseudo-legal and then fully by seeing if it leaves us in check afterwards
-        if state.make_move(from, unto, promotion).is_ok() && !state.in_check(!state.side_to_move) {
-            buf.add_move((from, unto, promotion))?;
-            return Ok(true);
+    gen_legal_moves_ordered(buf, state, GenMode::Unordered)
+}
+
+pub fn gen_legal_moves_ordered<B: AddMove>(buf: &mut B, state: &BoardState, mode: GenMode) -> Result<(), NoMoreSpace> {
+    let targets = evasion_targets(state);
+    match mode {
+        GenMode::Unordered | GenMode::Stable => gen_legal_moves_filtered(buf, state, targets, Coords::full_range(), |_| true),
+        GenMode::CapturesFirst => {
+            gen_legal_moves_filtered(buf, state, targets, Coords::full_range(), |capture| capture)?;
+            gen_legal_moves_filtered(buf, state, targets, Coords::full_range(), |capture| !capture)
         }
-        Ok(false)
+    }
+}
+
+/// The legal moves in `state` in the stable order (see [`GenMode::Stable`]), generated as they are asked for
+///
+/// Moves are found one piece at a time, so stopping early, like on a cutoff, skips the work for the rest of the pieces.
+pub fn legal_moves(state: &BoardState) -> LegalMoves<'_> {
+    LegalMoves {
+        state,
+        targets: evasion_targets(state),
+        square: 0,
+        buf: [PLACEHOLDER_MOVE; MAX_MOVES_FROM_SQUARE],
+        len: 0,
+        next: 0,
+    }
+}
+
+/// Iterator over the legal moves in a position, see [`legal_moves`]
+#[derive(Debug, Clone)]
+pub struct LegalMoves<'a> {
+    state: &'a BoardState,
+    /// See [`evasion_targets`]
+    targets: u64,
+    /// The next square to generate moves from
+    square: u8,
+    /// The moves of the piece last generated for
+    buf: [Move; MAX_MOVES_FROM_SQUARE],
+    len: usize,
+    next: usize,
+}
+
+impl Iterator for LegalMoves<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        while self.next == self.len {
+            let from = Coords::from_u8(self.square)?;
+            self.square += 1;
+            let mut buf: &mut [Move] = &mut self.buf;
+            gen_legal_moves_filtered(&mut buf, self.state, self.targets, [from], |_| true).expect("no piece has more moves than a queen");
+            self.len = MAX_MOVES_FROM_SQUARE - buf.len();
+            self.next = 0;
+        }
+        let mv = self.buf[self.next];
+        self.next += 1;
+        Some(mv)
+    }
+}
+
+/// Generates the legal moves of the piece on `from` in the stable order, none if it isn't the side to move's
+pub fn moves_for_square<B: AddMove>(state: &BoardState, from: Coords, buf: &mut B) -> Result<(), NoMoreSpace> {
+    gen_legal_moves_filtered(buf, state, evasion_targets(state), [from], |_| true)
+}
+
+/// Generates the legal moves of the side to move's pieces of the kind `piece`, in the stable order
+pub fn moves_for_piece<B: AddMove>(state: &BoardState, piece: Piece, buf: &mut B) -> Result<(), NoMoreSpace> {
+    gen_legal_moves_filtered(buf, state, evasion_targets(state), state.piece_squares(state.side_to_move, piece), |_| true)
+}
+
+/// The legal moves of the piece on `from` in the stable order, none if it isn't the side to move's
+pub(crate) fn legal_moves_from(state: &BoardState, from: Coords) -> Vec<Move> {
+    let mut vec = Vec::new();
+    moves_for_square(state, from, &mut vec).unwrap();
+    vec
+}
+
+/// The squares a piece other than the king has to move to for the move to get out of check
+///
+/// That is every square when not in check, the checker and the squares between it and the king when in check from one piece
+/// and none in double check, where only the king can move.
+fn evasion_targets(state: &BoardState) -> u64 {
+    let side = state.side_to_move;
+    let mut checkers = state.checkers(side);
+    let Some(checker) = checkers.next() else {
+        return !0;
     };
+    if checkers.next().is_some() {
+        return 0;
+    }
+    let king = state.find_king(side);
+    for dir in Direction::LINES {
+        let mut between = 0;
+        for cs in king.ray(dir) {
+            if cs == checker {
+                return 1 << checker.into_u8() | between;
+            }
+            if state.board.get(cs).is_occupied() {
+                break;
+            }
+            between |= 1 << cs.into_u8();
+        }
+    }
+    // A knight
+    1 << checker.into_u8()
+}
+
+/// Generates the moves from `squares` in the stable order, only adding those for which `filter` returns true
+/// when given whether the move is a capture
+///
+/// Moves by other pieces than the king that don't go to one of the `targets` from [`evasion_targets`] are skipped without being tried.
+fn gen_legal_moves_filtered<B: AddMove, I: IntoIterator<Item = Coords>, F: Fn(bool) -> bool>(
+    buf: &mut B,
+    state: &BoardState,
+    targets: u64,
+    squares: I,
+    filter: F,
+) -> Result<(), NoMoreSpace> {
+    // Every move is tried on this one copy and taken back again
+    let mut scratch = *state;
+    for from in squares {
+        let allowed = match state.board.get(from) {
+            Field::Occupied(_, Piece::King) => !0,
+            _ if targets == 0 => continue,
+            // Taking en passant can remove a checking pawn without landing on its square
+            Field::Occupied(_, Piece::Pawn) => targets | state.en_passant_target.map_or(0, |cs| 1 << cs.into_u8()),
+            _ => targets,
+        };
+        let mut check_move = |from, unto: Coords, promotion| {
+            if allowed & 1 << unto.into_u8() == 0 {
+                // Still in check here, but a slider might block the check further on
+                return Ok(!state.board.get(unto).is_occupied());
+            }
+            // Check if move is pseudo-legal and then fully by seeing if it leaves us in check afterwards
+            let Ok((_, undo)) = scratch.make_move_undoable(from, unto, promotion) else {
+                return Ok(false);
+            };
+            let legal = !scratch.in_check(state.side_to_move);
+            scratch.unmake_move(undo);
+            if legal {
+                let mv = Move::new(state, from, unto, promotion).expect("the move was made");
+                if filter(mv.is_capture()) {
+                    buf.add_move(mv)?;
+                }
+            }
+            // A slider can go on past a square where it would leave the king in check, it might block the check further on
+            Ok(true)
+        };
+        gen_moves_from(&mut check_move, state, from)?;
+    }
 
+    Ok(())
+}
+
+/// Tries every move the piece on `from` could make, if it belongs to the side to move
+fn gen_moves_from<F: FnMut(Coords, Coords, Option<Piece>) -> Result<bool, NoMoreSpace>>(
+    check_move: &mut F,
+    state: &BoardState,
+    from: Coords,
+) -> Result<(), NoMoreSpace> {
     let forwards = match state.side_to_move {
         Colour::Black => -1,
         Colour::White => 1,
     };
 
-    for from in Coords::full_range() {
-        match state.board.get(from) {
-            Field::Occupied(side, p) if side == state.side_to_move => match p {
-                Piece::Pawn => for unto in [
-                    (0, 1 * forwards),
-                    (0, 2 * forwards),
-                    (1, 1 * forwards),
-                    (-1, 1 * forwards),
-                ]
+    match state.board.get(from) {
+        Field::Occupied(side, p) if side == state.side_to_move => match p {
+            Piece::Pawn => for unto in [
+                (0, forwards),
+                (0, 2 * forwards),
+                (1, forwards),
+                (-1, forwards),
+            ]
+            .into_iter()
+            .filter_map(|(l, n)| from.add(l, n))
+            {
+                if unto.r() == Rank::N1 || unto.r() == Rank::N8 {
+                    check_move(from, unto, Some(Piece::Queen))?;
+                    check_move(from, unto, Some(Piece::Knight))?;
+                    check_move(from, unto, Some(Piece::Rook))?;
+                    check_move(from, unto, Some(Piece::Bishop))?;
+                } else {
+                    check_move(from, unto, None)?;
+                }
+            },
+            Piece::Knight => for unto in Direction::KNIGHT
+                .into_iter()
+                .filter_map(|dir| from.step(dir))
+                {
+                    check_move(from, unto, None)?;
+                },
+            Piece::King => for unto in Direction::LINES
                 .into_iter()
+                .map(Direction::delta)
+                .chain(CASTLINGS)
                 .filter_map(|(l, n)| from.add(l, n))
                 {
-                    if unto.r() == Rank::N1 || unto.r() == Rank::N8 {
-                        (&mut check_move)(from, unto, Some(Piece::Queen))?;
-                        (&mut check_move)(from, unto, Some(Piece::Knight))?;
-                        (&mut check_move)(from, unto, Some(Piece::Rook))?;
-                        (&mut check_move)(from, unto, Some(Piece::Bishop))?;
-                    } else {
-                        (&mut check_move)(from, unto, None)?;
-                    }
+                    check_move(from, unto, None)?;
                 },
-                Piece::Knight => for unto in KNIGHTIES
-                    .into_iter()
-                    .filter_map(|(l, n)| from.add(l, n))
-                    {
-                        (&mut check_move)(from, unto, None)?;
-                    },
-                Piece::King => for unto in STRAIGHTS
-                    .into_iter()
-                    .chain(DIAGANOLS.into_iter())
-                    .chain(CASTLINGS.into_iter())
-                    .into_iter()
-                    .filter_map(|(l, n)| from.add(l, n))
-                    {
-                        (&mut check_move)(from, unto, None)?;
-                    },
-                Piece::Rook => {
-                    for (dl, dn) in STRAIGHTS {
-                        follow_direction(&mut check_move, from, dl, dn)?;
-                    }
+            Piece::Rook => {
+                for dir in Direction::STRAIGHT {
+                    follow_direction(check_move, from, dir)?;
                 }
-                Piece::Bishop => {
-                    for (dl, dn) in DIAGANOLS {
-                        follow_direction(&mut check_move, from, dl, dn)?;
-                    }
+            }
+            Piece::Bishop => {
+                for dir in Direction::DIAGONAL {
+                    follow_direction(check_move, from, dir)?;
                 }
-                Piece::Queen => {
-                    for (dl, dn) in [STRAIGHTS, DIAGANOLS].concat() {
-                        follow_direction(&mut check_move, from, dl, dn)?;
-                    }
+            }
+            Piece::Queen => {
+                for dir in Direction::LINES {
+                    follow_direction(check_move, from, dir)?;
                 }
-            },
-            _ => (),
-        }
+            }
+        },
+        _ => (),
     }
-
     Ok(())
 }
 
 fn follow_direction<F: FnMut(Coords, Coords, Option<Piece>) -> Result<bool, NoMoreSpace>>(
     check_move: &mut F,
     from: Coords,
-    dl: i8,
-    dn: i8,
+    dir: Direction,
 ) -> Result<(), NoMoreSpace> {
-    for i in 1.. {
-        if let Some(unto) = from.add(i * dl, i * dn) {
-            if check_move(from, unto, None)? {
-                continue;
-            }
+    for unto in from.ray(dir) {
+        if !check_move(from, unto, None)? {
+            break;
         }
-        break;
     }
     Ok(())
 }
 
 #[inline(always)]
 pub fn any_legal_moves(state: &BoardState) -> bool {
-    gen_legal_moves(&mut (), state).is_err()
+    legal_moves(state).next().is_some()
 }
 #[inline(always)]
 pub fn get_all_moves(state: &BoardState) -> Vec<Move> {
+    get_all_moves_ordered(state, GenMode::Unordered)
+}
+pub fn get_all_moves_ordered(state: &BoardState, mode: GenMode) -> Vec<Move> {
     let mut vec = Vec::new();
-    gen_legal_moves(&mut vec, state).unwrap();
+    gen_legal_moves_ordered(&mut vec, state, mode).unwrap();
     vec
 }
 
-impl AddMove for () {
+/// The number of ways to play `depth` more legal moves from `state`, for checking the move generator against known counts
+///
+/// The moves at the last ply are counted without being made, and nothing is allocated.
+pub fn perft(state: &BoardState, depth: usize) -> u64 {
+    let mut state = *state;
+    perft_inner(&mut state, depth)
+}
+
+fn perft_inner(state: &mut BoardState, depth: usize) -> u64 {
+    match depth {
+        0 => 1,
+        1 => {
+            let mut count = MoveCount(0);
+            gen_legal_moves(&mut count, state).expect("counting has no limit");
+            count.0
+        }
+        _ => {
+            let mut buf = [PLACEHOLDER_MOVE; MAX_MOVES];
+            let mut slice = &mut buf[..];
+            gen_legal_moves(&mut slice, state).expect("no position has more than MAX_MOVES moves");
+            let len = MAX_MOVES - slice.len();
+            let mut nodes = 0;
+            for mv in &buf[..len] {
+                let (_, undo) = state.make_move_undoable(mv.from, mv.unto, mv.promotion).expect("generated moves are legal");
+                nodes += perft_inner(state, depth - 1);
+                state.unmake_move(undo);
+            }
+            nodes
+        }
+    }
+}
+
+/// Counts the moves it is given instead of keeping them
+struct MoveCount(u64);
+
+impl AddMove for MoveCount {
     #[inline(always)]
     fn add_move(&mut self, _: Move) -> Result<(), NoMoreSpace> {
-        Err(NoMoreSpace)
+        self.0 += 1;
+        Ok(())
+    }
+}
+
+/// [`perft`] split up by the first move, in the stable order, like Stockfish's `go perft`
+///
+/// Comparing these with another engine's shows which move a wrong total comes from.
+pub fn perft_divide(state: &BoardState, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
     }
+    get_all_moves_ordered(state, GenMode::Stable)
+        .into_iter()
+        .map(|mv| {
+            let mut new_state = *state;
+            new_state.make_move(mv.from, mv.unto, mv.promotion).expect("generated moves are legal");
+            (mv, perft(&new_state, depth - 1))
+        })
+        .collect()
+}
+
+/// Squares a knight attacks, indexed by the square it stands on
+pub static KNIGHT_ATTACKS: PerSquare<u64> = step_attacks(&Direction::KNIGHT);
+/// Squares a king attacks, indexed by the square it stands on
+pub static KING_ATTACKS: PerSquare<u64> = step_attacks(&Direction::LINES);
+/// Squares a pawn attacks, indexed by its colour (white first) and then the square it stands on
+pub static PAWN_ATTACKS: [PerSquare<u64>; 2] = [
+    step_attacks(&[Direction::NW, Direction::NE]),
+    step_attacks(&[Direction::SW, Direction::SE]),
+];
+
+/// The squares a pawn of `colour` on `square` attacks
+#[inline]
+pub fn pawn_attacks(colour: Colour, square: Coords) -> u64 {
+    PAWN_ATTACKS[matches!(colour, Colour::Black) as usize][square]
 }
+
+const fn step_attacks(dirs: &[Direction]) -> PerSquare<u64> {
+    let mut attacks = PerSquare::splat(0);
+    let mut sq = 0;
+    while sq < 64 {
+        let (file, rank) = ((sq & 0b111) as i8, (sq >> 3) as i8);
+        let mut i = 0;
+        while i < dirs.len() {
+            let (df, dr) = dirs[i].delta();
+            let (f, r) = (file + df, rank + dr);
+            if 0 <= f && f < 8 && 0 <= r && r < 8 {
+                attacks.0[sq] |= 1 << (r * 8 + f);
+            }
+            i += 1;
+        }
+        sq += 1;
+    }
+    attacks
+}
+
+/// The squares attacked by a `piece` of `colour` standing on `square`, as a mask where bit `i` is `Coords` number `i`
+///
+/// `occupancy` is a mask of the occupied squares, sliders stop at the first one they reach (which they still attack).
+/// Pawns only attack diagonally forwards and castling isn't an attack, so neither is included.
+pub fn attacks_from(piece: Piece, colour: Colour, square: Coords, occupancy: u64) -> u64 {
+    let lines: &[Direction] = match piece {
+        Piece::Pawn => return pawn_attacks(colour, square),
+        Piece::Knight => return KNIGHT_ATTACKS[square],
+        Piece::King => return KING_ATTACKS[square],
+        Piece::Bishop => &Direction::DIAGONAL,
+        Piece::Rook => &Direction::STRAIGHT,
+        Piece::Queen => &Direction::LINES,
+    };
+    let mut mask = 0;
+    for &dir in lines {
+        for cs in square.ray(dir) {
+            mask |= 1 << cs.into_u8();
+            if occupancy & 1 << cs.into_u8() != 0 {
+                break;
+            }
+        }
+    }
+    mask
+}
+
 impl AddMove for Vec<Move> {
     #[inline(always)]
     fn add_move(&mut self, mv: Move) -> Result<(), NoMoreSpace> {
@@ -151,3 +578,165 @@ impl AddMove for &mut [Move] {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::squares_in;
+
+    #[test]
+    fn test_attack_tables() {
+        assert_eq!(KNIGHT_ATTACKS[Coords::A1].count_ones(), 2);
+        assert_eq!(KNIGHT_ATTACKS[Coords::D4].count_ones(), 8);
+        assert_eq!(KING_ATTACKS[Coords::H8].count_ones(), 3);
+        assert_eq!(KING_ATTACKS[Coords::E4].count_ones(), 8);
+        let attacked: Vec<_> = squares_in(pawn_attacks(Colour::White, Coords::A2)).collect();
+        assert_eq!(attacked, [Coords::B3]);
+        let attacked: Vec<_> = squares_in(pawn_attacks(Colour::Black, Coords::E5)).collect();
+        assert_eq!(attacked, [Coords::D4, Coords::F4]);
+        for (cs, _) in KNIGHT_ATTACKS.iter() {
+            let stepped = Direction::KNIGHT.iter().filter_map(|&dir| cs.step(dir)).fold(0, |m, c| m | 1 << c.into_u8());
+            assert_eq!(KNIGHT_ATTACKS[cs], stepped, "{cs}");
+            let stepped = Direction::LINES.iter().filter_map(|&dir| cs.step(dir)).fold(0, |m, c| m | 1 << c.into_u8());
+            assert_eq!(KING_ATTACKS[cs], stepped, "{cs}");
+        }
+    }
+
+    #[test]
+    fn test_uci_move() {
+        let mv = UciMove::from_str("e7e8q").unwrap();
+        assert_eq!((mv.from, mv.unto, mv.promotion), (Coords::E7, Coords::E8, Some(Piece::Queen)));
+        assert_eq!(mv.to_string(), "e7e8q");
+        assert_eq!(UciMove::from_str("e1g1").unwrap().to_string(), "e1g1");
+        for invalid in ["", "e2", "e2e", "e2e9", "e7e8k", "e7e8Q", "e2e4 "] {
+            assert_eq!(UciMove::from_str(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_move_flags() {
+        let state = BoardState::from_fen("r3k3/1P6/8/3pP3/8/8/8/4K2R w Kq d6").unwrap();
+        let moves = get_all_moves(&state);
+        let find = |uci: &str| *moves.iter().find(|mv| mv.uci().to_string() == uci).unwrap();
+
+        let castle = find("e1g1");
+        assert_eq!((castle.piece, castle.castling, castle.captured), (Piece::King, Some(CastleSide::Short), None));
+        let en_passant = find("e5d6");
+        assert!(en_passant.en_passant);
+        assert_eq!(en_passant.captured, Some(Piece::Pawn));
+        let promotion = find("b7a8n");
+        assert_eq!((promotion.piece, promotion.captured, promotion.promotion), (Piece::Pawn, Some(Piece::Rook), Some(Piece::Knight)));
+        assert_eq!(Move::new(&state, Coords::E1, Coords::E2, None), Some(find("e1e2")));
+        assert_eq!(Move::new(&state, Coords::E8, Coords::E7, None), None);
+    }
+
+    #[test]
+    fn test_attacks_from() {
+        let cs = |s| Coords::from_str(s).unwrap();
+        let squares = |mask| squares_in(mask).map(|c| c.to_string()).collect::<Vec<_>>();
+
+        let blocker = 1 << cs("a4").into_u8() | 1 << cs("c1").into_u8();
+        assert_eq!(squares(attacks_from(Piece::Rook, Colour::White, cs("a1"), blocker)), ["b1", "c1", "a2", "a3", "a4"]);
+        assert_eq!(squares(attacks_from(Piece::Pawn, Colour::Black, cs("a7"), 0)), ["b6"]);
+        assert_eq!(attacks_from(Piece::Knight, Colour::White, cs("d4"), !0).count_ones(), 8);
+        assert_eq!(attacks_from(Piece::Queen, Colour::White, cs("d4"), 0).count_ones(), 27);
+
+        let state = BoardState::new();
+        let b1 = attacks_from(Piece::Knight, Colour::White, cs("b1"), state.occupancy());
+        assert_eq!(squares(b1), ["d2", "a3", "c3"]);
+    }
+
+    #[test]
+    fn test_perft() {
+        // Reference counts from the Chess Programming Wiki's perft results page
+        for (fen, depth, nodes) in [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", 3, 8902),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -", 2, 2039),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -", 3, 2812),
+            ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq -", 3, 9467),
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ -", 2, 1486),
+        ] {
+            assert_eq!(perft(&BoardState::from_fen(fen).unwrap(), depth), nodes, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let state = BoardState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -").unwrap();
+        let divide = perft_divide(&state, 2);
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|&(_, nodes)| nodes).sum::<u64>(), perft(&state, 2));
+        let nodes = |uci| divide.iter().find(|&&(mv, _)| mv.uci().to_string() == uci).unwrap().1;
+        assert_eq!(nodes("e1g1"), 43);
+        assert_eq!(nodes("e1c1"), 43);
+        assert_eq!(nodes("d5e6"), 46);
+        assert!(perft_divide(&state, 0).is_empty());
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "r3k3/1P6/8/3pP3/8/8/8/4K2R w Kq d6",
+            "k7/8/8/8/3Q4/8/8/7K w - -",
+        ] {
+            let state = BoardState::from_fen(fen).unwrap();
+            assert_eq!(legal_moves(&state).collect::<Vec<_>>(), get_all_moves_ordered(&state, GenMode::Stable), "{fen}");
+        }
+        let mate = BoardState::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq -").unwrap();
+        assert_eq!(legal_moves(&mate).next(), None);
+        assert!(!any_legal_moves(&mate));
+    }
+
+    #[test]
+    fn test_moves_for_square_and_piece() {
+        let state = BoardState::new();
+        let ucis = |moves: &[Move]| moves.iter().map(|mv| mv.uci().to_string()).collect::<Vec<_>>();
+        let mut moves = Vec::new();
+        moves_for_square(&state, Coords::G1, &mut moves).unwrap();
+        assert_eq!(ucis(&moves), ["g1h3", "g1f3"]);
+
+        let mut moves = Vec::new();
+        moves_for_piece(&state, Piece::Knight, &mut moves).unwrap();
+        assert_eq!(ucis(&moves), ["b1c3", "b1a3", "g1h3", "g1f3"]);
+
+        let mut buf = [PLACEHOLDER_MOVE; 1];
+        assert!(moves_for_piece(&state, Piece::Knight, &mut &mut buf[..]).is_err());
+        assert_eq!(buf[0].uci().to_string(), "b1c3");
+    }
+
+    #[test]
+    fn test_evasion_targets() {
+        let squares = |fen| {
+            let state = BoardState::from_fen(fen).unwrap();
+            squares_in(evasion_targets(&state)).map(|c| c.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(squares("4k3/8/8/8/8/8/8/4K3 w - -").len(), 64);
+        assert_eq!(squares("4k3/8/8/8/8/8/8/r3K3 w - -"), ["a1", "b1", "c1", "d1"]);
+        assert_eq!(squares("4k3/8/8/8/8/5n2/8/4K3 w - -"), ["f3"]);
+        assert!(squares("4k3/8/8/8/8/5n2/8/r3K3 w - -").is_empty());
+
+        // Only moves of the king, blocks and capturing the checker are left
+        let state = BoardState::from_fen("4k3/8/8/8/8/2N5/1B6/r3K2R w K -").unwrap();
+        let moves: Vec<_> = legal_moves(&state).map(|mv| mv.uci().to_string()).collect();
+        assert_eq!(moves, ["e1e2", "e1f2", "e1d2", "b2c1", "b2a1", "c3d1", "c3b1"]);
+    }
+
+    #[test]
+    fn test_captures_first() {
+        let state = BoardState::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq -").unwrap();
+        let stable = get_all_moves_ordered(&state, GenMode::Stable);
+        let captures_first = get_all_moves_ordered(&state, GenMode::CapturesFirst);
+
+        assert_eq!(stable.len(), captures_first.len());
+        let captures = captures_first.iter().take_while(|mv| mv.is_capture()).count();
+        // Only dxe4
+        assert_eq!(captures, 1);
+        assert!(captures_first[captures..].iter().all(|mv| !mv.is_capture()));
+
+        let mut stable_captures: Vec<_> = stable.iter().copied().filter(|mv| mv.is_capture()).collect();
+        stable_captures.extend(stable.iter().copied().filter(|mv| !mv.is_capture()));
+        assert_eq!(stable_captures, captures_first);
+    }
+}
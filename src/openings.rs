@@ -0,0 +1,137 @@
+//! Suites of starting positions, so that comparisons between players don't depend on a single opening
+
+use std::fmt::{self, Display};
+
+use crate::{algebraic::Move as AlgebraicMove, board::Colour, epd::Epd, game::Game};
+
+/// A starting position from a suite
+#[derive(Debug, Clone)]
+pub struct Opening {
+    /// The `id` of an EPD line or the moves of a move list
+    pub name: String,
+    pub game: Game,
+}
+
+/// One game to be played from an opening, every opening is played twice with the colours swapped
+#[derive(Debug, Clone)]
+pub struct Pairing<'a> {
+    pub opening: &'a Opening,
+    /// The colour the first of the two compared players plays
+    pub first_plays: Colour,
+}
+
+/// Why a line of a suite could not be loaded, `line` counts from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteError {
+    pub line: usize,
+    pub text: String,
+}
+
+impl Display for SuiteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid opening on line {}: {}", self.line, self.text)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpeningSuite {
+    openings: Vec<Opening>,
+}
+
+impl OpeningSuite {
+    /// Parses a suite with one opening per line, empty lines and lines starting with `#` are skipped
+    ///
+    /// A line is either an EPD record (a FEN without clocks followed by opcodes, of which only `id` is used)
+    /// or a list of moves in algebraic notation from the starting position, like `1. e4 c5 2. Nf3`
+    pub fn parse(s: &str) -> Result<Self, SuiteError> {
+        let mut openings = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let opening = if line.contains('/') {
+                parse_epd(line)
+            } else {
+                parse_moves(line)
+            };
+            openings.push(opening.ok_or_else(|| SuiteError {
+                line: i + 1,
+                text: line.to_owned(),
+            })?);
+        }
+        Ok(OpeningSuite { openings })
+    }
+    pub fn openings(&self) -> &[Opening] {
+        &self.openings
+    }
+    pub fn len(&self) -> usize {
+        self.openings.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.openings.is_empty()
+    }
+    /// Every opening twice, first with the first player as white and then as black
+    pub fn pairings(&self) -> impl Iterator<Item = Pairing<'_>> {
+        self.openings.iter().flat_map(|opening| {
+            [Colour::White, Colour::Black].map(|first_plays| Pairing { opening, first_plays })
+        })
+    }
+}
+
+fn parse_epd(line: &str) -> Option<Opening> {
+    let epd = Epd::parse(line).ok()?;
+    let name = match epd.id() {
+        Some(id) => id.to_owned(),
+        None => epd.state.display_fen().to_string(),
+    };
+    Some(Opening { name, game: epd.game() })
+}
+
+fn parse_moves(line: &str) -> Option<Opening> {
+    let mut game = Game::new();
+    for san in line.split_whitespace().filter(|s| !s.ends_with('.')) {
+        let mv = game.check_move(AlgebraicMove::from_str(san).ok()?)?;
+        game.make_move(mv.from, mv.unto, mv.promotion).ok()?;
+    }
+    Some(Opening {
+        name: line.to_owned(),
+        game,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_suite() {
+        let suite = OpeningSuite::parse(
+            "# a comment\n\
+             rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 id \"sicilian\";\n\
+             \n\
+             1. e4 c5\n",
+        )
+        .unwrap();
+        assert_eq!(suite.len(), 2);
+        assert_eq!(suite.openings()[0].name, "sicilian");
+        assert_eq!(suite.openings()[1].name, "1. e4 c5");
+        for opening in suite.openings() {
+            assert_eq!(
+                opening.game.board_state().display_fen().to_string(),
+                "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6"
+            );
+        }
+
+        let colours: Vec<_> = suite.pairings().map(|p| p.first_plays).collect();
+        assert_eq!(colours, [Colour::White, Colour::Black, Colour::White, Colour::Black]);
+
+        assert_eq!(
+            OpeningSuite::parse("1. e4 e5\n1. e4 Ke7 2. Ke2").unwrap_err(),
+            SuiteError {
+                line: 2,
+                text: "1. e4 Ke7 2. Ke2".to_owned()
+            }
+        );
+    }
+}
@@ -0,0 +1,1128 @@
+//! Pieces of the Portable Game Notation
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, BufRead, Write},
+    mem,
+    time::Duration,
+};
+
+use crate::{algebraic::{Annotation, Move}, board::Colour, game::{Clk, Emt, Game, GameResult, MoveAnnotation, ReplayError, TimeControl}, player::Ending};
+
+/// The value of the `Result` tag, which is also the game termination marker at the end of the movetext
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PgnResult {
+    Win(Colour),
+    Draw,
+    /// The game is still going on or the result is unknown
+    Unknown,
+}
+
+impl PgnResult {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "1-0" => Some(PgnResult::Win(Colour::White)),
+            "0-1" => Some(PgnResult::Win(Colour::Black)),
+            "1/2-1/2" => Some(PgnResult::Draw),
+            "*" => Some(PgnResult::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl Display for PgnResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnResult::Win(Colour::White) => write!(f, "1-0"),
+            PgnResult::Win(Colour::Black) => write!(f, "0-1"),
+            PgnResult::Draw => write!(f, "1/2-1/2"),
+            PgnResult::Unknown => write!(f, "*"),
+        }
+    }
+}
+
+impl From<Ending> for PgnResult {
+    fn from(ending: Ending) -> Self {
+        match ending {
+            Ending::Checkmate { winner } | Ending::Resignation { winner } => PgnResult::Win(winner),
+            Ending::Stalemate | Ending::DrawClaimed | Ending::DrawByAgreement => PgnResult::Draw,
+        }
+    }
+}
+
+impl From<GameResult> for PgnResult {
+    fn from(result: GameResult) -> Self {
+        match result {
+            GameResult::Ongoing => PgnResult::Unknown,
+            GameResult::WhiteWins(_) => PgnResult::Win(Colour::White),
+            GameResult::BlackWins(_) => PgnResult::Win(Colour::Black),
+            GameResult::Draw(_) => PgnResult::Draw,
+        }
+    }
+}
+
+/// The value of the `Termination` tag, which tells how the result came about
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Termination {
+    /// The game ended by the rules of chess, including resignations and draw agreements
+    Normal,
+    TimeForfeit,
+    Abandoned,
+    /// The result was decided by a third party, e.g. a match runner stopping a game that went on too long
+    Adjudication,
+    Unterminated,
+}
+
+impl Termination {
+    /// Parses the tag value, ignoring case as PGN files in the wild aren't consistent about it
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match &*s.to_ascii_lowercase() {
+            "normal" => Some(Termination::Normal),
+            "time forfeit" => Some(Termination::TimeForfeit),
+            "abandoned" => Some(Termination::Abandoned),
+            "adjudication" => Some(Termination::Adjudication),
+            "unterminated" => Some(Termination::Unterminated),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Termination::Normal => write!(f, "normal"),
+            Termination::TimeForfeit => write!(f, "time forfeit"),
+            Termination::Abandoned => write!(f, "abandoned"),
+            Termination::Adjudication => write!(f, "adjudication"),
+            Termination::Unterminated => write!(f, "unterminated"),
+        }
+    }
+}
+
+impl From<Option<Ending>> for Termination {
+    fn from(ending: Option<Ending>) -> Self {
+        match ending {
+            Some(_) => Termination::Normal,
+            None => Termination::Unterminated,
+        }
+    }
+}
+
+/// The `Result` and `Termination` tag pair of a game
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResultTags {
+    pub result: PgnResult,
+    pub termination: Termination,
+}
+
+impl ResultTags {
+    /// The tags for a game that ended with `ending`, or one that hasn't ended if it's `None`
+    pub fn from_ending(ending: Option<Ending>) -> Self {
+        ResultTags {
+            result: ending.map_or(PgnResult::Unknown, PgnResult::from),
+            termination: Termination::from(ending),
+        }
+    }
+    /// Reads the tags from their values, a missing `Termination` tag is assumed to be normal if there is a result
+    pub fn parse(result: &str, termination: Option<&str>) -> Option<Self> {
+        let result = PgnResult::from_str(result)?;
+        let termination = match termination {
+            Some(s) => Termination::from_str(s)?,
+            None if result == PgnResult::Unknown => Termination::Unterminated,
+            None => Termination::Normal,
+        };
+        Some(ResultTags { result, termination })
+    }
+}
+
+impl Display for ResultTags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[Result \"{}\"]", self.result)?;
+        writeln!(f, "[Termination \"{}\"]", self.termination)
+    }
+}
+
+/// Numbered movetext like `1. e4 e5 2. Nf3`, optionally wrapped and ended with a result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveList {
+    moves: Vec<Move>,
+    first_fullmove: u64,
+    first_side: Colour,
+    /// Annotations of the moves by index, moves at the end without any may be left out
+    annotations: Vec<MoveAnnotation>,
+    /// How long was spent on each move by index if known, moves at the end without one may be left out
+    think_times: Vec<Option<Duration>>,
+    /// The time left after each move by index if known, moves at the end without one may be left out
+    clocks: Vec<Option<Duration>>,
+    width: Option<usize>,
+    result: Option<PgnResult>,
+}
+
+impl MoveList {
+    /// The moves starting at move number `first_fullmove` with `first_side` to move
+    pub fn new(moves: Vec<Move>, first_fullmove: u64, first_side: Colour) -> Self {
+        MoveList {
+            moves,
+            first_fullmove,
+            first_side,
+            annotations: Vec::new(),
+            think_times: Vec::new(),
+            clocks: Vec::new(),
+            width: None,
+            result: None,
+        }
+    }
+    /// Writes the annotations after their moves
+    pub fn with_annotations(self, annotations: Vec<MoveAnnotation>) -> Self {
+        MoveList { annotations, ..self }
+    }
+    /// Writes how long was spent on each move as a `[%emt]` command after it
+    pub fn with_think_times(self, think_times: Vec<Option<Duration>>) -> Self {
+        MoveList { think_times, ..self }
+    }
+    /// Writes the time left after each move as a `[%clk]` command after it
+    pub fn with_clocks(self, clocks: Vec<Option<Duration>>) -> Self {
+        MoveList { clocks, ..self }
+    }
+    /// Breaks the lines so they are at most `width` characters long, never splitting a move from its number
+    pub fn with_width(self, width: usize) -> Self {
+        MoveList { width: Some(width), ..self }
+    }
+    /// Ends the movetext with the result, as PGN requires
+    pub fn with_result(self, result: PgnResult) -> Self {
+        MoveList { result: Some(result), ..self }
+    }
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+}
+
+impl Display for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let black_first = self.first_side == Colour::Black;
+        let mut words = Vec::with_capacity(self.moves.len() + 1);
+        // Black's moves need their number when they don't directly follow white's
+        let mut needs_number = true;
+        for (i, mv) in self.moves.iter().enumerate() {
+            let ply = i + black_first as usize;
+            let number = self.first_fullmove + ply as u64 / 2;
+            let mut word = match (ply % 2, needs_number) {
+                (0, _) => format!("{number}. {mv}"),
+                (_, true) => format!("{number}... {mv}"),
+                (_, false) => mv.to_string(),
+            };
+            needs_number = false;
+            let annotation = self.annotations.get(i);
+            // The first NAG is written as a suffix when it has one, like in a `Line`
+            let mut nags = annotation.map_or(&[][..], |annotation| &annotation.nags).iter().peekable();
+            if let Some(symbol) = nags.peek().and_then(|nag| nag.symbol()).filter(|_| mv.annotation.is_none()) {
+                word.push_str(symbol);
+                nags.next();
+            }
+            words.push(word);
+            words.extend(nags.map(|nag| nag.to_string()));
+            if let Some(eval) = annotation.and_then(|annotation| annotation.eval) {
+                words.push(format!("{{[%eval {eval}]}}"));
+                needs_number = true;
+            }
+            let emt = self.think_times.get(i).copied().flatten().map(|think_time| Emt(think_time).to_string());
+            let clk = self.clocks.get(i).copied().flatten().map(|clock| Clk(clock).to_string());
+            let commands: Vec<_> = emt.into_iter().chain(clk).collect();
+            if !commands.is_empty() {
+                words.push(format!("{{{}}}", commands.join(" ")));
+                needs_number = true;
+            }
+            for comment in annotation.iter().flat_map(|annotation| &annotation.comments) {
+                words.push(format!("{{{comment}}}"));
+                needs_number = true;
+            }
+        }
+        if let Some(result) = self.result {
+            words.push(result.to_string());
+        }
+
+        let mut line_len = 0;
+        for word in words {
+            let len = word.chars().count();
+            if line_len > 0 {
+                if self.width.is_some_and(|width| line_len + 1 + len > width) {
+                    writeln!(f)?;
+                    line_len = 0;
+                } else {
+                    write!(f, " ")?;
+                    line_len += 1;
+                }
+            }
+            write!(f, "{word}")?;
+            line_len += len;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a game as PGN, with the given tags followed by the setup, result and termination tags
+pub fn write_game<W: Write>(mut w: W, tags: &Tags, game: &Game, ending: Option<Ending>) -> io::Result<()> {
+    let result_tags = ResultTags::from_ending(ending);
+    let mut tags = tags.clone();
+    tags.set("Result", result_tags.result.to_string());
+    tags.set("Termination", result_tags.termination.to_string());
+    write!(w, "{}", game.to_pgn(&tags))
+}
+
+/// The tag pairs of a game, like `[Event "Casual game"]`
+///
+/// They are written with the Seven Tag Roster first, filling in the standard unknown values for missing ones,
+/// followed by the other tags in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+    pairs: Vec<(String, String)>,
+}
+
+impl Tags {
+    /// The tags every exported game must have, in the order they have to come in, with their values for when they're unknown
+    pub const SEVEN_TAG_ROSTER: [(&'static str, &'static str); 7] = [
+        ("Event", "?"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+        ("White", "?"),
+        ("Black", "?"),
+        ("Result", "*"),
+    ];
+
+    pub const fn new() -> Self {
+        Tags { pairs: Vec::new() }
+    }
+    /// Reads a tag section with one tag pair per line, `None` if a line isn't one
+    pub fn parse(section: &str) -> Option<Self> {
+        section.lines().map(str::trim).filter(|line| !line.is_empty()).map(parse_tag).collect()
+    }
+    /// Adds the tag or replaces its value if it's already there
+    pub fn with(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.set(name, value);
+        self
+    }
+    /// Adds the tag at the end or replaces its value where it is if it's already there
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.pairs.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.pairs.push((name.to_owned(), value)),
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs.iter().find(|(n, _)| n == name).map(|(_, v)| &**v)
+    }
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let i = self.pairs.iter().position(|(n, _)| n == name)?;
+        Some(self.pairs.remove(i).1)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(n, v)| (&**n, &**v))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+    /// All tags in the order they're written, see [`Tags`]
+    pub fn ordered(&self) -> impl Iterator<Item = (&str, &str)> {
+        let roster = Self::SEVEN_TAG_ROSTER.iter().map(|&(name, unknown)| (name, self.get(name).unwrap_or(unknown)));
+        let rest = self.iter().filter(|(name, _)| Self::SEVEN_TAG_ROSTER.iter().all(|(roster_name, _)| roster_name != name));
+        roster.chain(rest)
+    }
+}
+
+impl FromIterator<(String, String)> for Tags {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        let mut tags = Tags::new();
+        for (name, value) in iter {
+            tags.set(&name, value);
+        }
+        tags
+    }
+}
+
+/// One line per tag in the order of [`Tags::ordered`], with quotes and backslashes in the values escaped
+impl Display for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.ordered() {
+            writeln!(f, "[{name} \"{}\"]", value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        Ok(())
+    }
+}
+
+/// A game as read from a PGN file, with the moves not yet checked against the rules
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Tags,
+    /// The moves of the main line in algebraic notation, without comments, variations, move numbers or `!` and `?`
+    pub moves: Vec<String>,
+    /// The whole movetext with its comments and variations
+    pub movetext: Line,
+    /// The game termination marker ending the movetext, if there was one
+    pub result: Option<PgnResult>,
+}
+
+/// A sequence of moves in the movetext, with the comments and variations along the way
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Line {
+    /// Comments before the first move
+    pub comments: Vec<String>,
+    pub moves: Vec<MoveNode>,
+}
+
+/// A move in a [`Line`] followed by its comments and the moves that could have been played instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveNode {
+    /// The move as written, without any `!` or `?` which are kept as [`Nag`]s
+    pub san: String,
+    pub nags: Vec<Nag>,
+    pub comments: Vec<String>,
+    /// Lines starting from the position before this move
+    pub variations: Vec<Line>,
+}
+
+impl MoveNode {
+    pub fn new(san: impl Into<String>) -> Self {
+        MoveNode {
+            san: san.into(),
+            nags: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+    /// Reads a move as written in movetext, turning a suffix like `!?` into its NAG
+    fn from_word(word: &str) -> Self {
+        let san = word.trim_end_matches(['!', '?']);
+        let mut node = MoveNode::new(san);
+        node.nags.extend(Nag::from_symbol(&word[san.len()..]));
+        node
+    }
+}
+
+/// A Numeric Annotation Glyph, written `$1`, `$2` and so on, that annotates the move before it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Nag(pub u8);
+
+impl Nag {
+    pub const GOOD_MOVE: Self = Nag(1);
+    pub const MISTAKE: Self = Nag(2);
+    pub const BRILLIANT_MOVE: Self = Nag(3);
+    pub const BLUNDER: Self = Nag(4);
+    pub const INTERESTING_MOVE: Self = Nag(5);
+    pub const DUBIOUS_MOVE: Self = Nag(6);
+
+    /// The move suffix that means the same, for the ones that have one
+    pub const fn symbol(self) -> Option<&'static str> {
+        Some(match self.0 {
+            1 => "!",
+            2 => "?",
+            3 => "!!",
+            4 => "??",
+            5 => "!?",
+            6 => "?!",
+            _ => return None,
+        })
+    }
+    /// The NAG meant by a move suffix like `!?`
+    pub fn from_symbol(s: &str) -> Option<Self> {
+        (1..=6).map(Nag).find(|nag| nag.symbol() == Some(s))
+    }
+    /// Reads a NAG as written in movetext, like `$14`
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        s.strip_prefix('$')?.parse().ok().map(Nag)
+    }
+}
+
+impl From<Annotation> for Nag {
+    fn from(annotation: Annotation) -> Self {
+        Nag(annotation as u8)
+    }
+}
+
+impl Display for Nag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${}", self.0)
+    }
+}
+
+impl Line {
+    /// Reads movetext, returning the line and the game termination marker at the end if there is one
+    ///
+    /// Unbalanced parentheses are forgiven: an unclosed variation ends with the movetext and a stray `)` is ignored.
+    pub fn parse(movetext: &str) -> (Line, Option<PgnResult>) {
+        let mut stack = vec![Line::default()];
+        let mut result = None;
+        for token in movetext_tokens(movetext) {
+            match token {
+                MovetextToken::Comment(comment) => {
+                    let line = stack.last_mut().unwrap();
+                    let comment = comment.trim().to_owned();
+                    match line.moves.last_mut() {
+                        Some(node) => node.comments.push(comment),
+                        None => line.comments.push(comment),
+                    }
+                }
+                MovetextToken::Open => stack.push(Line::default()),
+                MovetextToken::Close if stack.len() > 1 => {
+                    let variation = stack.pop().unwrap();
+                    if let Some(node) = stack.last_mut().unwrap().moves.last_mut() {
+                        node.variations.push(variation);
+                    }
+                }
+                MovetextToken::Close => (),
+                MovetextToken::Nag(nag) => {
+                    if let Some(node) = stack.last_mut().unwrap().moves.last_mut() {
+                        node.nags.push(nag);
+                    }
+                }
+                MovetextToken::Word(word) => match PgnResult::from_str(word) {
+                    Some(r) if stack.len() == 1 => result = Some(r),
+                    Some(_) => (),
+                    None => stack.last_mut().unwrap().moves.push(MoveNode::from_word(word)),
+                },
+            }
+        }
+        while stack.len() > 1 {
+            let variation = stack.pop().unwrap();
+            if let Some(node) = stack.last_mut().unwrap().moves.last_mut() {
+                node.variations.push(variation);
+            }
+        }
+        (stack.pop().unwrap(), result)
+    }
+    /// The moves without the comments and variations
+    pub fn sans(&self) -> impl Iterator<Item = &str> {
+        self.moves.iter().map(|node| &*node.san)
+    }
+    /// Shows the line with move numbers, starting at `first_fullmove` with `first_side` to move
+    pub fn display(&self, first_fullmove: u64, first_side: Colour) -> LineDisplay<'_> {
+        LineDisplay {
+            line: self,
+            first_ply: (first_fullmove.max(1) - 1) * 2 + (first_side == Colour::Black) as u64,
+        }
+    }
+}
+
+/// Movetext of a [`Line`] on a single line of text, see [`Line::display`]
+pub struct LineDisplay<'a> {
+    line: &'a Line,
+    /// Plies from white's first move to the first move of the line
+    first_ply: u64,
+}
+
+impl LineDisplay<'_> {
+    fn write_line(f: &mut fmt::Formatter<'_>, line: &Line, first_ply: u64) -> fmt::Result {
+        let mut first_word = true;
+        let mut space = |f: &mut fmt::Formatter<'_>| if mem::take(&mut first_word) { Ok(()) } else { write!(f, " ") };
+        for comment in &line.comments {
+            space(f)?;
+            write!(f, "{{{comment}}}")?;
+        }
+        // Black's moves need their number when they don't directly follow white's
+        let mut needs_number = true;
+        for (ply, node) in (first_ply..).zip(&line.moves) {
+            space(f)?;
+            let number = ply / 2 + 1;
+            match (ply % 2, needs_number) {
+                (0, _) => write!(f, "{number}. ")?,
+                (_, true) => write!(f, "{number}... ")?,
+                (_, false) => (),
+            }
+            write!(f, "{}", node.san)?;
+            // The first NAG is written as a suffix when it has one, the way people write them
+            let mut nags = node.nags.iter().peekable();
+            if let Some(symbol) = nags.peek().and_then(|nag| nag.symbol()) {
+                write!(f, "{symbol}")?;
+                nags.next();
+            }
+            for nag in nags {
+                write!(f, " {nag}")?;
+            }
+            needs_number = false;
+            for comment in &node.comments {
+                write!(f, " {{{comment}}}")?;
+                needs_number = true;
+            }
+            for variation in &node.variations {
+                write!(f, " (")?;
+                Self::write_line(f, variation, ply)?;
+                write!(f, ")")?;
+                needs_number = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for LineDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Self::write_line(f, self.line, self.first_ply)
+    }
+}
+
+impl PgnGame {
+    /// Reads the first game in `pgn`, `None` if there is none
+    pub fn parse(pgn: &str) -> Option<Self> {
+        PgnReader::new(pgn.as_bytes()).next().map(|game| game.expect("reading from a string can't fail"))
+    }
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.get(name)
+    }
+    /// The move number and side to move the movetext starts at, taken from the `FEN` tag if there is one
+    fn first_move(&self) -> (u64, Colour) {
+        self.tag("FEN")
+            .and_then(Game::from_fen)
+            .map_or((1, Colour::White), |game| (game.fullmove_number(), game.side_to_move()))
+    }
+    /// Plays the moves from the starting position, or the one in the `FEN` tag if there is one
+    ///
+    /// Think times are read from `[%emt]` commands, or worked out from the `[%clk]` commands and the `TimeControl` tag.
+    /// A normal termination with a result the final position doesn't explain is taken as a resignation or an agreed
+    /// draw, so the game ends the same way.
+    pub fn to_game(&self) -> Result<Game, PgnError> {
+        let mut game = match self.tag("FEN") {
+            Some(fen) => Game::from_fen(fen).ok_or(PgnError::InvalidFen)?,
+            None => Game::new(),
+        };
+        game.replay(self.moves.iter().map(|s| &**s))?;
+        let time_control = self.tag("TimeControl").and_then(TimeControl::from_str);
+        game.set_time_control(time_control);
+        // The clock of each side before its latest move, starting from the base time
+        let mut clocks = [time_control.map(|time_control| time_control.base); 2];
+        for (ply, node) in self.movetext.moves.iter().enumerate() {
+            let emt = node.comments.iter().find_map(|comment| Emt::from_comment(comment)).map(|Emt(emt)| emt);
+            let clk = node.comments.iter().find_map(|comment| Clk::from_comment(comment)).map(|Clk(clk)| clk);
+            let spent = match (clocks[ply % 2], clk, time_control) {
+                (Some(before), Some(after), Some(time_control)) => (before + time_control.increment).checked_sub(after),
+                _ => None,
+            };
+            if let Some(think_time) = emt.or(spent) {
+                game.set_think_time(ply, think_time);
+            }
+            clocks[ply % 2] = clk;
+        }
+        // A result the final position doesn't explain came from a resignation or a draw agreement
+        if let Some(ResultTags { result, termination: Termination::Normal }) = self.result_tags() {
+            if !game.result().is_over() {
+                match result {
+                    PgnResult::Win(winner) => game.resign(!winner),
+                    PgnResult::Draw => {
+                        game.offer_draw(game.side_to_move());
+                        game.accept_draw();
+                    }
+                    PgnResult::Unknown => (),
+                }
+            }
+        }
+        Ok(game)
+    }
+    /// The `Result` and `Termination` tags, taking the result from the end of the movetext if there's no tag for it
+    pub fn result_tags(&self) -> Option<ResultTags> {
+        let result = match self.tag("Result") {
+            Some(result) => result.to_owned(),
+            None => self.result?.to_string(),
+        };
+        ResultTags::parse(&result, self.tag("Termination"))
+    }
+}
+
+/// Writes the game back as PGN, keeping the comments and variations
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tags = self.tags.clone();
+        if let (None, Some(result)) = (tags.get("Result"), self.result) {
+            tags.set("Result", result.to_string());
+        }
+        writeln!(f, "{tags}")?;
+        let (first_fullmove, first_side) = self.first_move();
+        write!(f, "{}", self.movetext.display(first_fullmove, first_side))?;
+        match self.result {
+            Some(result) if self.movetext.moves.is_empty() && self.movetext.comments.is_empty() => writeln!(f, "{result}"),
+            Some(result) => writeln!(f, " {result}"),
+            None => writeln!(f),
+        }
+    }
+}
+
+/// Why a game in PGN couldn't be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// There were no tags or movetext
+    NoGame,
+    /// The `FEN` tag isn't a valid position
+    InvalidFen,
+    Replay(ReplayError),
+}
+
+impl From<ReplayError> for PgnError {
+    fn from(e: ReplayError) -> Self {
+        PgnError::Replay(e)
+    }
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::NoGame => write!(f, "no game found"),
+            PgnError::InvalidFen => write!(f, "invalid FEN tag"),
+            PgnError::Replay(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+/// Reads games one at a time from a PGN database, so that large files don't have to fit in memory
+pub struct PgnReader<R> {
+    reader: R,
+    /// A tag line that turned out to belong to the next game
+    pending: Option<String>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        PgnReader { reader, pending: None }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = io::Result<PgnGame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut game = PgnGame::default();
+        let mut movetext = String::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match self.pending.take() {
+                Some(pending) => line = pending,
+                None => match self.reader.read_line(&mut line) {
+                    Ok(0) => break,
+                    Ok(_) => (),
+                    Err(e) => return Some(Err(e)),
+                },
+            }
+
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && !in_comment(&movetext) {
+                if !movetext.trim().is_empty() {
+                    // The previous game was missing its termination marker
+                    self.pending = Some(line.clone());
+                    break;
+                }
+                if let Some(tag) = parse_tag(trimmed) {
+                    game.tags.set(&tag.0, tag.1);
+                }
+            } else if !trimmed.starts_with('%') {
+                movetext.push_str(&line);
+                movetext.push('\n');
+                let ends = trimmed.split_whitespace().last().and_then(PgnResult::from_str).is_some();
+                if ends && !in_comment(&movetext) {
+                    break;
+                }
+            }
+        }
+
+        if game.tags.is_empty() && movetext.trim().is_empty() {
+            return None;
+        }
+        (game.movetext, game.result) = Line::parse(&movetext);
+        game.moves = game.movetext.sans().map(str::to_owned).collect();
+        Some(Ok(game))
+    }
+}
+
+/// Parses a tag pair like `[Event "Casual game"]`
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.to_owned(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+/// Whether the movetext so far ends inside a `{}` comment
+fn in_comment(movetext: &str) -> bool {
+    match (movetext.rfind('{'), movetext.rfind('}')) {
+        (Some(open), Some(close)) => open > close,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+enum MovetextToken<'a> {
+    Comment(&'a str),
+    Open,
+    Close,
+    /// A NAG or an annotation written on its own, like the `!` in `e4 !`
+    Nag(Nag),
+    /// A move or a game termination marker
+    Word(&'a str),
+}
+
+/// Splits movetext into comments, parentheses, NAGs and words, skipping move numbers
+fn movetext_tokens(movetext: &str) -> Vec<MovetextToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = movetext;
+    while let Some(c) = rest.chars().next() {
+        let skip = match c {
+            '{' => {
+                let end = rest.find('}');
+                tokens.push(MovetextToken::Comment(&rest[1..end.unwrap_or(rest.len())]));
+                end.map_or(rest.len(), |i| i + 1)
+            }
+            ';' => {
+                let end = rest.find('\n').unwrap_or(rest.len());
+                tokens.push(MovetextToken::Comment(&rest[1..end]));
+                end
+            }
+            '(' => {
+                tokens.push(MovetextToken::Open);
+                1
+            }
+            ')' => {
+                tokens.push(MovetextToken::Close);
+                1
+            }
+            c if c.is_whitespace() => c.len_utf8(),
+            _ => {
+                let end = rest.find(|c: char| c.is_whitespace() || "{;()".contains(c)).unwrap_or(rest.len());
+                let word = &rest[..end];
+                if PgnResult::from_str(word).is_some() {
+                    tokens.push(MovetextToken::Word(word));
+                } else if let Some(nag) = Nag::from_str(word).or_else(|| Nag::from_symbol(word)) {
+                    tokens.push(MovetextToken::Nag(nag));
+                } else if word == "e.p." {
+                    // Written after en passant captures in some books, the move says as much already
+                } else if !word.starts_with('$') {
+                    // Move numbers can be written right before the move, like `1.e4` or `1...e5`
+                    let mv = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                    if !mv.is_empty() {
+                        tokens.push(MovetextToken::Word(mv));
+                    }
+                }
+                end
+            }
+        };
+        rest = &rest[skip..];
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::{DrawReason, Eval, WinReason}, location::Coords};
+
+    #[test]
+    fn test_result_tags() {
+        let tags = ResultTags::from_ending(Some(Ending::Resignation { winner: Colour::Black }));
+        assert_eq!(tags.to_string(), "[Result \"0-1\"]\n[Termination \"normal\"]\n");
+        assert_eq!(ResultTags::parse("0-1", Some("Normal")), Some(tags));
+        assert_eq!(ResultTags::parse("0-1", None), Some(tags));
+
+        let tags = ResultTags::from_ending(None);
+        assert_eq!(tags.to_string(), "[Result \"*\"]\n[Termination \"unterminated\"]\n");
+        assert_eq!(ResultTags::parse("*", None), Some(tags));
+
+        let forfeit = ResultTags::parse("1-0", Some("time forfeit")).unwrap();
+        assert_eq!(forfeit.termination, Termination::TimeForfeit);
+        assert_eq!(ResultTags::parse("1-0", Some("Termination::TimeForfeit")), None);
+        assert_eq!(ResultTags::parse("2-0", None), None);
+    }
+
+    #[test]
+    fn test_move_list() {
+        let moves: Vec<_> = ["e4", "e5", "Nf3", "Nc6", "Bb5"].into_iter().map(|s| Move::from_str(s).unwrap()).collect();
+        let list = MoveList::new(moves.clone(), 1, Colour::White);
+        assert_eq!(list.to_string(), "1. e4 e5 2. Nf3 Nc6 3. Bb5");
+        assert_eq!(list.with_width(12).with_result(PgnResult::Unknown).to_string(), "1. e4 e5\n2. Nf3 Nc6\n3. Bb5 *");
+
+        let list = MoveList::new(moves[1..].to_vec(), 1, Colour::Black).with_result(PgnResult::Draw);
+        assert_eq!(list.to_string(), "1... e5 2. Nf3 Nc6 3. Bb5 1/2-1/2");
+
+        let mut game = crate::game::Game::new();
+        for mv in &moves {
+            let mv = game.check_move(*mv).unwrap();
+            game.make_move(mv.from, mv.unto, mv.promotion).unwrap();
+        }
+        assert_eq!(game.move_list(), MoveList::new(moves, 1, Colour::White));
+    }
+
+    #[test]
+    fn test_annotations() {
+        let mut game = Game::new();
+        game.replay("e4 e5 Nf3 Nc6 Bb5".split(' ')).unwrap();
+        assert!(game.annotate(5).is_none());
+        let annotation = game.annotate(1).unwrap();
+        annotation.nags.extend([Nag::INTERESTING_MOVE, Nag(14)]);
+        annotation.comments.push("The open game".to_owned());
+        game.annotate(4).unwrap().eval = Some(Eval::Centipawns(32));
+        game.annotate(2).unwrap().eval = Some(Eval::MateIn(-2));
+        // Annotating a move and leaving it empty doesn't give it an annotation
+        game.annotate(3).unwrap();
+        assert_eq!(game.annotation(3), None);
+        assert_eq!(game.annotation(1).unwrap().comments, ["The open game"]);
+
+        let movetext = "1. e4 e5!? $14 {The open game} 2. Nf3 {[%eval #-2]} 2... Nc6 3. Bb5 {[%eval 0.32]} *";
+        assert_eq!(game.move_list().with_result(PgnResult::Unknown).to_string(), movetext);
+        assert!(game.to_pgn(&Tags::new()).replace('\n', " ").contains(movetext));
+        // The movetext reads back as the same line with the same annotations
+        let (line, _) = Line::parse(movetext);
+        assert_eq!(line.moves[1].nags, [Nag::INTERESTING_MOVE, Nag(14)]);
+        assert_eq!(line.display(1, Colour::White).to_string(), &movetext[..movetext.len() - 2]);
+        assert_eq!((Eval::from_str("#-2"), Eval::from_str("0.32"), Eval::from_str("-1.5")), (Some(Eval::MateIn(-2)), Some(Eval::Centipawns(32)), Some(Eval::Centipawns(-150))));
+        assert_eq!(Eval::from_str("inf"), None);
+    }
+
+    #[test]
+    fn test_write_game() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        game.make_move(Coords::from_str("h1").unwrap(), Coords::from_str("h8").unwrap(), None).unwrap();
+        let tags = Tags::new().with("White", "A \"quoted\" name");
+        let mut pgn = Vec::new();
+        write_game(&mut pgn, &tags, &game, None).unwrap();
+
+        let pgn = String::from_utf8(pgn).unwrap();
+        assert_eq!(pgn, r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "A \"quoted\" name"]
+[Black "?"]
+[Result "*"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]
+[Termination "unterminated"]
+
+1. Rh8+ *
+
+"#);
+        let read = PgnReader::new(pgn.as_bytes()).next().unwrap().unwrap();
+        assert_eq!(read.tag("White"), Some("A \"quoted\" name"));
+        assert_eq!(read.moves, ["Rh8+"]);
+    }
+
+    #[test]
+    fn test_variations_and_comments() {
+        let pgn = "[Event \"Analysis\"]
+
+{Opening} 1. e4 e5 {Solid} (1... c5 2. Nf3 (2. c3) 2... d6) (1... e6) 2. Nf3 ; the usual move
+2... Nc6 *
+";
+        let game = PgnGame::parse(pgn).unwrap();
+        assert_eq!(game.moves, ["e4", "e5", "Nf3", "Nc6"]);
+        let line = &game.movetext;
+        assert_eq!(line.comments, ["Opening"]);
+        assert_eq!(line.moves[1].comments, ["Solid"]);
+        assert_eq!(line.moves[1].variations.len(), 2);
+        let sicilian = &line.moves[1].variations[0];
+        assert_eq!(sicilian.sans().collect::<Vec<_>>(), ["c5", "Nf3", "d6"]);
+        assert_eq!(sicilian.moves[1].variations[0].sans().collect::<Vec<_>>(), ["c3"]);
+        assert_eq!(line.moves[2].comments, ["the usual move"]);
+
+        let written = game.to_string();
+        assert_eq!(written, "[Event \"Analysis\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"*\"]
+
+{Opening} 1. e4 e5 {Solid} (1... c5 2. Nf3 (2. c3) 2... d6) (1... e6) 2. Nf3 {the usual move} 2... Nc6 *
+");
+        assert_eq!(PgnGame::parse(&written).unwrap().movetext, game.movetext);
+
+        // A variation that is never closed still keeps its moves
+        let (line, result) = Line::parse("1. d4 (1. c4 c5");
+        assert_eq!(result, None);
+        assert_eq!(line.moves[0].variations[0].sans().collect::<Vec<_>>(), ["c4", "c5"]);
+    }
+
+    #[test]
+    fn test_nags() {
+        let (line, _) = Line::parse("1. e4 $1 e5?! 2. Nf3 ! $14 Nc6 $142");
+        assert_eq!(line.sans().collect::<Vec<_>>(), ["e4", "e5", "Nf3", "Nc6"]);
+        assert_eq!(line.moves[0].nags, [Nag::GOOD_MOVE]);
+        assert_eq!(line.moves[1].nags, [Nag::DUBIOUS_MOVE]);
+        assert_eq!(line.moves[2].nags, [Nag::GOOD_MOVE, Nag(14)]);
+        assert_eq!(line.moves[3].nags, [Nag(142)]);
+        assert_eq!(line.display(1, Colour::White).to_string(), "1. e4! e5?! 2. Nf3! $14 Nc6 $142");
+        assert_eq!(Nag::from_symbol("??"), Some(Nag::BLUNDER));
+        assert_eq!(Nag::BRILLIANT_MOVE.symbol(), Some("!!"));
+
+        let mv = Move::from_str("Qxf7#?!").unwrap();
+        assert_eq!(mv.annotation, Some(Annotation::Dubious));
+        assert_eq!(Nag::from(Annotation::Dubious), Nag::DUBIOUS_MOVE);
+        assert_eq!(mv.to_string(), "Qxf7#?!");
+        assert_eq!(Move::from_str("e8=Q+ !!").unwrap().annotation, Some(Annotation::Brilliant));
+        assert_eq!(Move::from_str("O-O?").unwrap().annotation, Some(Annotation::Mistake));
+    }
+
+    #[test]
+    fn test_to_pgn() {
+        let mut game = Game::new();
+        game.replay("e4 e5 Qh5 Nc6 Bc4 Nf6 Qxf7#".split(' ')).unwrap();
+        let tags = Tags::new().with("Event", "Test").with("White", "Someone").with("Black", "Someone else");
+        assert_eq!(game.to_pgn(&tags), r#"[Event "Test"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "Someone"]
+[Black "Someone else"]
+[Result "1-0"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+"#);
+        let (read, _) = Game::from_pgn(&game.to_pgn(&tags)).unwrap();
+        assert_eq!(read.history(), game.history());
+
+        // A given result wins over the position, e.g. for a game that was resigned
+        let mut game = Game::new();
+        game.replay(["d4"]).unwrap();
+        assert!(game.to_pgn(&Tags::new().with("Result", "1-0")).ends_with("[Result \"1-0\"]\n\n1. d4 1-0\n\n"));
+        assert!(game.to_pgn(&Tags::new()).ends_with("[Result \"*\"]\n\n1. d4 *\n\n"));
+    }
+
+    #[test]
+    fn test_tags() {
+        let tags = Tags::parse("[Annotator \"Me\"]\n[White \"W\"]\n\n[Event \"E\"]\n").unwrap();
+        assert_eq!(tags.get("Annotator"), Some("Me"));
+        assert_eq!(tags.to_string(), r#"[Event "E"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "W"]
+[Black "?"]
+[Result "*"]
+[Annotator "Me"]
+"#);
+        assert_eq!(Tags::parse(&tags.to_string()).unwrap().ordered().collect::<Vec<_>>(), tags.ordered().collect::<Vec<_>>());
+        assert_eq!(Tags::parse("[Event \"E\"]\n1. e4"), None);
+    }
+
+    #[test]
+    fn test_pgn_reader() {
+        let pgn = r#"[Event "Test \"quoted\""]
+[Result "1-0"]
+
+1. e4 {a comment
+spanning lines 1-0} e5 2.Nf3 (2. f4 exf4) 2... Nc6 $1 3. Bb5! a6?! ; rest of line
+4. Ba4 1-0
+
+[Event "Second"]
+1. d4 d5 *
+1. c4
+"#;
+        let games: Vec<_> = PgnReader::new(pgn.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].tag("Event"), Some("Test \"quoted\""));
+        assert_eq!(games[0].tag("Result"), Some("1-0"));
+        assert_eq!(games[0].moves, ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"]);
+        assert_eq!(games[0].result, Some(PgnResult::Win(Colour::White)));
+        assert_eq!(games[1].moves, ["d4", "d5"]);
+        assert_eq!(games[1].result, Some(PgnResult::Unknown));
+        assert_eq!(games[2].moves, ["c4"]);
+        assert_eq!(games[2].result, None);
+    }
+
+    #[test]
+    fn test_think_times() {
+        let mut game = Game::new();
+        game.make_timed_move(Coords::E2, Coords::E4, None, Some(Duration::from_secs(3))).unwrap();
+        game.make_timed_move(Coords::E7, Coords::E5, None, None).unwrap();
+        game.make_timed_move(Coords::G1, Coords::F3, None, Some(Duration::from_secs(3725))).unwrap();
+        game.annotate(2).unwrap().comments.push("Developing".to_owned());
+        let movetext = "1. e4 {[%emt 0:00:03]} 1... e5 2. Nf3 {[%emt 1:02:05]} {Developing} *";
+        assert_eq!(game.move_list().with_result(PgnResult::Unknown).to_string(), movetext);
+
+        // The think times are read back, also from comments with other commands in them
+        let (read, _) = Game::from_pgn(&game.to_pgn(&Tags::new())).unwrap();
+        let think_times: Vec<_> = read.history().iter().map(|entry| entry.think_time).collect();
+        assert_eq!(think_times, [Some(Duration::from_secs(3)), None, Some(Duration::from_secs(3725))]);
+        let (read, _) = Game::from_pgn("1. d4 { [%eval 0.2] [%emt 0:00:01.5] } *").unwrap();
+        assert_eq!(read.history()[0].think_time, Some(Duration::from_millis(1500)));
+        assert_eq!(Emt::from_comment("[%emt 1:00]"), None);
+    }
+
+    #[test]
+    fn test_clocks() {
+        let mut game = Game::new();
+        game.set_time_control(TimeControl::from_str("60+2"));
+        game.make_timed_move(Coords::E2, Coords::E4, None, Some(Duration::from_secs(3))).unwrap();
+        game.make_timed_move(Coords::E7, Coords::E5, None, Some(Duration::from_secs(10))).unwrap();
+        game.make_timed_move(Coords::G1, Coords::F3, None, Some(Duration::from_secs(5))).unwrap();
+        game.make_timed_move(Coords::B8, Coords::C6, None, None).unwrap();
+        assert_eq!((game.clock(2), game.clock(3), game.clock(4)), (Some(Duration::from_secs(56)), None, None));
+        let pgn = game.to_pgn(&Tags::new());
+        assert!(pgn.contains("[TimeControl \"60+2\"]"), "{pgn}");
+        let movetext = "1. e4 {[%emt 0:00:03] [%clk 0:00:59]} 1... e5 {[%emt 0:00:10] [%clk 0:00:52]} 2. Nf3 {[%emt 0:00:05] [%clk 0:00:56]} 2... Nc6 *";
+        assert_eq!(game.move_list().with_result(PgnResult::Unknown).to_string(), movetext);
+        let (read, _) = Game::from_pgn(&pgn).unwrap();
+        assert_eq!(read, game);
+
+        // Without `[%emt]` the think times come from the clocks, like in games from lichess
+        let (read, _) = Game::from_pgn("[TimeControl \"60+2\"]\n\n1. e4 { [%clk 0:00:59] } 1... e5 { [%clk 0:00:52] } 2. Nf3 { [%clk 0:00:56] } *").unwrap();
+        let think_times: Vec<_> = read.history().iter().map(|entry| entry.think_time).collect();
+        assert_eq!(think_times, [3, 10, 5].map(|secs| Some(Duration::from_secs(secs))));
+        assert_eq!(Clk::from_comment("[%eval 0.1] [%clk 1:00:00.5]"), Some(Clk(Duration::from_millis(3_600_500))));
+        assert_eq!(TimeControl::from_str("40/9000:300"), None);
+        assert_eq!(TimeControl::from_str("600").unwrap().to_string(), "600");
+    }
+
+    #[test]
+    fn test_result_round_trip() {
+        let mut game = Game::new();
+        game.replay(["e4", "e5"]).unwrap();
+        game.resign(Colour::White);
+        let (read, _) = Game::from_pgn(&game.to_pgn(&Tags::new())).unwrap();
+        assert_eq!(read.result(), GameResult::BlackWins(WinReason::Resignation));
+
+        game = Game::new();
+        game.replay(["d4"]).unwrap();
+        game.offer_draw(Colour::White);
+        game.accept_draw();
+        let (read, _) = Game::from_pgn(&game.to_pgn(&Tags::new())).unwrap();
+        assert_eq!(read.result(), GameResult::Draw(DrawReason::Agreement));
+
+        // Losing on time isn't resigning, and a result the position explains is kept as it is
+        let (read, _) = Game::from_pgn("[Result \"1-0\"]\n[Termination \"time forfeit\"]\n\n1. e4 1-0").unwrap();
+        assert_eq!(read.result(), GameResult::Ongoing);
+        let (read, _) = Game::from_pgn("1. f3 e5 2. g4 Qh4# 0-1").unwrap();
+        assert_eq!(read.result(), GameResult::BlackWins(WinReason::Checkmate));
+        let (read, _) = Game::from_pgn("1. e4 *").unwrap();
+        assert_eq!(read.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn test_from_pgn() {
+        let pgn = r#"[Event "Rated blitz game"]
+[Site "https://lichess.org/abcdefgh"]
+[Result "0-1"]
+
+1. f3 { [%clk 0:03:00] } 1... e5 { [%clk 0:03:00] } 2. g4?? { (0.00 → #-1) Blunder. } 2... Qh4# 0-1
+"#;
+        let (game, moves) = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.result(), GameResult::BlackWins(WinReason::Checkmate));
+        assert_eq!(moves.to_string(), "1. f3 e5 2. g4 Qh4# 0-1");
+
+        let (game, moves) = Game::from_pgn("[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O Kd7 *").unwrap();
+        assert_eq!(game.display_fen().to_string(), "8/3k4/8/8/8/8/8/5RK1 w - - 2 2");
+        assert_eq!(moves.to_string(), "1. O-O Kd7 *");
+
+        let (_, moves) = Game::from_pgn("1. e4 Nf6 2. e5 d5 3. exd6 e.p. exd6 *").unwrap();
+        assert_eq!(moves.to_string(), "1. e4 Nf6 2. e5 d5 3. exd6 exd6 *");
+
+        assert_eq!(Game::from_pgn("").unwrap_err(), PgnError::NoGame);
+        assert_eq!(Game::from_pgn("[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n1. e4 *").unwrap_err(), PgnError::InvalidFen);
+        let err = Game::from_pgn("1. e4 e5 2. Ke3 *").unwrap_err();
+        assert_eq!(err, PgnError::Replay(ReplayError::Illegal { ply: 2, san: "Ke3".to_owned() }));
+        assert_eq!(err.to_string(), "Ke3 at ply 2 is not a legal move");
+    }
+}
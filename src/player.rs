@@ -0,0 +1,982 @@
+use std::{
+    ffi::OsStr,
+    fmt::{self, Display},
+    io::{self, stdin, stdout, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, TryRecvError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    algebraic::{Move as AlgebraicMove, Notation},
+    board::{Colour, Field, Piece},
+    boardstate::{BoardState, MoveError},
+    bots::{
+        bot1::{Conduct, CurrMove, EvalParams, OpeningBook, Ranking, Score, SearchEvent, SearchOptions, Searcher, Skill},
+        bot2::{self, MctsOptions, Playout, RootMove},
+        rng::Rng,
+    },
+    game::{DrawReason, Game, GameResult, MoveOutcome, WinReason},
+    location::{Coords, Rank},
+    movegen::{get_all_moves, Move, UciMove},
+};
+
+/// One side of a game, asked for moves by a [`GameRunner`]
+pub trait Player {
+    /// Whether the player makes moves through interactions (e.g. with a mouse)
+    fn is_human(&self) -> bool { false }
+    fn start_interaction(&mut self, _bs: &BoardState, _coords: Coords) { }
+    /// The piece currently being moved in an interaction and the square it was picked up from
+    fn get_interaction(&self) -> Option<(Piece, Coords)> { None }
+    fn end_interaction(&mut self, _bs: &BoardState, _coords: Coords) { }
+
+    /// Asks for a move, `None` means the player has not decided yet and will be asked again later
+    fn make_move(&mut self, game: &Game) -> Option<Move>;
+    /// What the player is currently considering, if it reports it
+    fn progress(&self) -> Option<CurrMove> { None }
+    /// What the player found when choosing its last move, if it reports it
+    fn last_move_info(&self) -> Option<MoveInfo> { None }
+    /// Called before the player is used in another game, anything that should be carried over is kept
+    fn new_game(&mut self) { }
+
+    /// The name to record for the player, e.g. in a PGN `White` tag
+    fn name(&self) -> String { "?".to_owned() }
+    /// A description of the settings that affect how the player plays, if any
+    fn settings(&self) -> Option<String> { None }
+
+    /// Whether the player wants to stop the game without it having ended
+    fn interrupts(&self) -> bool { false }
+    /// Whether the player gives up after having made a move
+    fn resigns(&self) -> bool { false }
+    /// Whether the player offers a draw after having made a move
+    fn offers_draw(&self, _repeating: bool) -> bool { false }
+    fn accepts_draw(&self) -> bool { false }
+}
+
+/// What a bot found when choosing a move, for front ends to show
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveInfo {
+    /// The move was taken from an opening book
+    Book,
+    /// A search's evaluation for the side that moved and the line it expects, starting with the best move
+    Search { eval: Score, pv: Vec<Move> },
+    /// How many playouts of a tree search went through the move and their average score from 0 to 1
+    Playouts { visits: u32, score: f64 },
+}
+
+#[derive(Debug, Default)]
+enum InteractionState {
+    #[default]
+    NoInteraction,
+    Started(Piece, Coords),
+    MoveReady(Coords, Coords),
+}
+
+use self::InteractionState::*;
+
+/// A player picking up pieces and putting them down through interactions
+#[derive(Debug, Default)]
+pub struct HumanPlayer {
+    interaction_state: InteractionState,
+}
+
+impl Player for HumanPlayer {
+    fn is_human(&self) -> bool { true }
+    fn name(&self) -> String { "Human".to_owned() }
+    fn start_interaction(&mut self, bs: &BoardState, coords: Coords) {
+        match bs.get(coords) {
+            // Only pieces that can actually move can be picked up
+            Field::Occupied(_, p) if !bs.legal_moves_from(coords).is_empty() => {
+                self.interaction_state = Started(p, coords);
+            }
+            _ => (),
+        }
+    }
+    fn get_interaction(&self) -> Option<(Piece, Coords)> {
+        match self.interaction_state {
+            Started(p, from) => Some((p, from)),
+            _ => None,
+        }
+    }
+    fn end_interaction(&mut self, _bs: &BoardState, coords: Coords) {
+        if let Started(_, start) = self.interaction_state {
+            self.interaction_state = MoveReady(start, coords);
+        }
+    }
+
+    fn make_move(&mut self, game: &Game) -> Option<Move> {
+        match self.interaction_state {
+            MoveReady(a, b) => {
+                // The move is either made or rejected, either way the interaction is over
+                self.interaction_state = NoInteraction;
+                let promotion = if game.board_state().get(a).into_piece() == Some(Piece::Pawn) && (b.r() == Rank::N1 || b.r() == Rank::N8){
+                    // TODO: get a way to specify what to promote to
+                    Some(Piece::Queen)
+                } else {
+                    None
+                };
+                Move::new(game.board_state(), a, b, promotion)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A player typing moves in algebraic notation into the terminal, an empty line interrupts the game
+#[derive(Debug, Default)]
+pub struct ConsolePlayer {
+    interrupted: bool,
+    notation: Notation,
+}
+
+impl ConsolePlayer {
+    /// Reads and writes moves with the piece letters of `notation` instead of the English ones
+    pub fn with_notation(self, notation: Notation) -> Self {
+        ConsolePlayer { notation, ..self }
+    }
+}
+
+impl Player for ConsolePlayer {
+    fn make_move(&mut self, game: &Game) -> Option<Move> {
+        let mut input = String::new();
+        loop {
+            print!("Possible moves: ");
+            for mv in get_all_moves(game.board_state()) {
+                if let Some(letter) = self.notation.letter(mv.piece) {
+                    print!("{letter}");
+                }
+                print!("{}{}", mv.from, mv.unto);
+                if let Some(p) = mv.promotion.and_then(|p| self.notation.letter(p)) {
+                    print!("={p}");
+                }
+                print!(" ");
+            }
+            println!();
+            print!("Move (or \"history\"): ");
+            stdout().flush().unwrap();
+
+            input.clear();
+            stdin().read_line(&mut input).unwrap();
+
+            if input.trim().is_empty() {
+                self.interrupted = true;
+                return None;
+            }
+            if input.trim() == "history" {
+                println!("{}", game.move_list().with_width(80));
+                continue;
+            }
+
+            // Coordinate notation like e7e8q is accepted as well as SAN
+            if let Some(mv) = UciMove::from_str(input.trim()).and_then(|mv| mv.in_position(game.board_state())) {
+                if get_all_moves(game.board_state()).contains(&mv) {
+                    return Some(mv);
+                }
+            }
+            match AlgebraicMove::parse_with(input.trim(), &self.notation) {
+                Ok(mv) => {
+                    println!("Valid {}", mv.display(&self.notation));
+
+                    match game.check_move(mv) {
+                        Some(mv) => return Some(mv),
+                        None => println!("Incorrect {}", mv.display(&self.notation)),
+                    }
+                }
+                Err(e) => println!("Not a move, {e}"),
+            }
+        }
+    }
+    fn interrupts(&self) -> bool {
+        self.interrupted
+    }
+    fn name(&self) -> String {
+        "Human".to_owned()
+    }
+}
+
+/// Plays using [`bot1`](crate::bots::bot1) on a separate thread, so asking for a move never blocks
+pub struct Bot1Player {
+    ongoing: Option<JoinHandle<Ranking>>,
+    /// Stops the ongoing search
+    stop: Arc<AtomicBool>,
+    progress: Arc<Mutex<Option<CurrMove>>>,
+    last_move_info: Option<MoveInfo>,
+    /// Kept for the whole game, so every search starts with what the earlier ones found
+    searcher: Arc<Mutex<Searcher>>,
+    conduct: Conduct,
+    params: EvalParams,
+    max_depth: usize,
+    max_nodes: usize,
+    threads: usize,
+    book: Option<OpeningBook>,
+    skill: Skill,
+    /// The seed every game starts its random choices from, random if `None`
+    seed: Option<u64>,
+    rng: Rng,
+}
+
+impl Default for Bot1Player {
+    fn default() -> Self {
+        Self::new(10, 1_000_000)
+    }
+}
+
+impl Bot1Player {
+    pub fn new(max_depth: usize, max_nodes: usize) -> Self {
+        Self {
+            ongoing: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            progress: Arc::new(Mutex::new(None)),
+            last_move_info: None,
+            searcher: Arc::new(Mutex::new(Searcher::default())),
+            conduct: Conduct::default(),
+            params: EvalParams::default(),
+            max_depth,
+            max_nodes,
+            threads: 1,
+            book: None,
+            skill: Skill::MAX,
+            seed: None,
+            rng: Rng::from_entropy(),
+        }
+    }
+    /// Makes the bot evaluate positions with the given weights
+    pub fn with_params(mut self, params: EvalParams) -> Self {
+        self.params = params;
+        self
+    }
+    /// Makes the bot search with this many threads
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+    /// Makes the bot play from the opening book while it has moves for the position
+    pub fn with_book(mut self, book: OpeningBook) -> Self {
+        self.book = Some(book);
+        self
+    }
+    /// Makes the bot play worse, see [`Skill`]
+    pub fn with_skill(mut self, skill: Skill) -> Self {
+        self.skill = skill;
+        self
+    }
+    /// Makes the bot's random choices the same every game, so it plays the same moves in the same positions
+    ///
+    /// The transposition table is then cleared between games too, as what it remembers changes what the search finds.
+    /// Searching with more than one thread still isn't reproducible, as the threads race each other.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self.rng = Rng::new(seed);
+        self
+    }
+    /// Stops the ongoing search without waiting for it
+    fn cancel(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.ongoing = None;
+    }
+}
+
+impl Drop for Bot1Player {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl Player for Bot1Player {
+    fn make_move(&mut self, game: &Game) -> Option<Move> {
+        let Some(ongoing) = self.ongoing.take() else {
+            if let Some(mv) = self.book.as_ref().and_then(|book| book.pick(game, &mut self.rng)) {
+                self.last_move_info = Some(MoveInfo::Book);
+                return Some(mv);
+            }
+            let bs = *game.board_state();
+            self.stop = Arc::new(AtomicBool::new(false));
+            let mut options = SearchOptions::new(self.max_depth, self.max_nodes).with_threads(self.threads).with_stop(self.stop.clone());
+            if self.skill < Skill::MAX {
+                options = options.with_skill(self.skill, self.rng.next_u64());
+            }
+            let progress = self.progress.clone();
+            let searcher = self.searcher.clone();
+            let params = self.params.clone();
+            self.ongoing = Some(thread::spawn(move || {
+                searcher.lock().unwrap().get_moves_ranked_with_info(&bs, &options, &params, |event| {
+                    if let SearchEvent::CurrMove(curr_move) = event {
+                        *progress.lock().unwrap() = Some(curr_move);
+                    }
+                })
+            }));
+            return None;
+        };
+
+        if ongoing.is_finished() {
+            let ranking = ongoing.join().unwrap();
+            *self.progress.lock().unwrap() = None;
+
+            let pv = ranking.lines.first().map_or(Vec::new(), |line| line.pv.clone());
+            self.last_move_info = Some(MoveInfo::Search { eval: ranking.eval, pv });
+            self.conduct.record_eval(ranking.eval);
+            self.skill.choose(&ranking, &mut self.rng)
+        } else {
+            self.ongoing = Some(ongoing);
+            None
+        }
+    }
+    fn progress(&self) -> Option<CurrMove> {
+        *self.progress.lock().unwrap()
+    }
+    fn last_move_info(&self) -> Option<MoveInfo> {
+        self.last_move_info.clone()
+    }
+    fn new_game(&mut self) {
+        // The weights and limits are kept, but how the last game went shouldn't make it resign this one
+        self.conduct.forget_evals();
+        self.last_move_info = None;
+        // A search from the previous game is stopped and its move never used
+        self.cancel();
+        *self.progress.lock().unwrap() = None;
+        // The table is kept, as positions from the last game often come up again in a rematch
+        if let Some(seed) = self.seed {
+            self.rng = Rng::new(seed);
+            // Waits for the stopped search to let go of the table, which it does within a node
+            self.searcher.lock().unwrap().clear();
+        }
+    }
+    fn name(&self) -> String {
+        "talv bot1".to_owned()
+    }
+    fn settings(&self) -> Option<String> {
+        let mut settings = format!("max depth {}, max nodes {}", self.max_depth, self.max_nodes);
+        if self.threads > 1 {
+            settings.push_str(&format!(", threads {}", self.threads));
+        }
+        if let Some(book) = &self.book {
+            settings.push_str(&format!(", book for {} moves", book.max_moves));
+        }
+        if self.skill < Skill::MAX {
+            settings.push_str(&format!(", skill {}", self.skill.level()));
+        }
+        if let Some(seed) = self.seed {
+            settings.push_str(&format!(", seed {seed}"));
+        }
+        if self.params != EvalParams::default() {
+            for line in self.params.to_toml().lines() {
+                settings.push_str(", ");
+                settings.push_str(line);
+            }
+        }
+        Some(settings)
+    }
+    fn resigns(&self) -> bool {
+        self.conduct.should_resign()
+    }
+    fn offers_draw(&self, repeating: bool) -> bool {
+        self.conduct.should_offer_draw(repeating)
+    }
+    fn accepts_draw(&self) -> bool {
+        self.conduct.accepts_draw()
+    }
+}
+
+/// Plays using [`bot2`]'s tree search on a separate thread, so asking for a move never blocks
+pub struct Bot2Player {
+    ongoing: Option<JoinHandle<Vec<RootMove>>>,
+    /// Stops the ongoing search
+    stop: Arc<AtomicBool>,
+    iterations: usize,
+    playout: Playout,
+    params: EvalParams,
+    last_move_info: Option<MoveInfo>,
+    /// The seed every game starts its random choices from, random if `None`
+    seed: Option<u64>,
+    rng: Rng,
+}
+
+impl Default for Bot2Player {
+    fn default() -> Self {
+        Self::new(20_000)
+    }
+}
+
+impl Bot2Player {
+    pub fn new(iterations: usize) -> Self {
+        Self {
+            ongoing: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            iterations,
+            playout: Playout::Eval,
+            params: EvalParams::default(),
+            last_move_info: None,
+            seed: None,
+            rng: Rng::from_entropy(),
+        }
+    }
+    pub fn with_playout(mut self, playout: Playout) -> Self {
+        self.playout = playout;
+        self
+    }
+    /// Makes the bot evaluate positions with the given weights
+    pub fn with_params(mut self, params: EvalParams) -> Self {
+        self.params = params;
+        self
+    }
+    /// Makes the bot's playouts the same every game, so it plays the same moves in the same positions
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self.rng = Rng::new(seed);
+        self
+    }
+    /// Stops the ongoing search without waiting for it
+    fn cancel(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.ongoing = None;
+    }
+}
+
+impl Drop for Bot2Player {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl Player for Bot2Player {
+    fn make_move(&mut self, game: &Game) -> Option<Move> {
+        let Some(ongoing) = self.ongoing.take() else {
+            let bs = *game.board_state();
+            self.stop = Arc::new(AtomicBool::new(false));
+            let options = MctsOptions::new(self.iterations).with_playout(self.playout).with_stop(self.stop.clone());
+            let params = self.params.clone();
+            // Every search gets its own generator, seeded from the player's
+            let mut rng = Rng::new(self.rng.next_u64());
+            self.ongoing = Some(thread::spawn(move || bot2::search(&bs, &options, &params, &mut rng)));
+            return None;
+        };
+
+        if ongoing.is_finished() {
+            let moves = ongoing.join().unwrap();
+            let best = moves.first()?;
+            self.last_move_info = Some(MoveInfo::Playouts { visits: best.visits, score: best.score });
+            Some(best.mv)
+        } else {
+            self.ongoing = Some(ongoing);
+            None
+        }
+    }
+    fn last_move_info(&self) -> Option<MoveInfo> {
+        self.last_move_info.clone()
+    }
+    fn new_game(&mut self) {
+        self.cancel();
+        self.last_move_info = None;
+        if let Some(seed) = self.seed {
+            self.rng = Rng::new(seed);
+        }
+    }
+    fn name(&self) -> String {
+        "talv bot2".to_owned()
+    }
+    fn settings(&self) -> Option<String> {
+        let mut settings = format!("iterations {}", self.iterations);
+        match self.playout {
+            Playout::Random { max_plies } => settings.push_str(&format!(", random playouts of up to {max_plies} plies")),
+            Playout::Eval => settings.push_str(", evaluated leaves"),
+        }
+        if let Some(seed) = self.seed {
+            settings.push_str(&format!(", seed {seed}"));
+        }
+        if self.params != EvalParams::default() {
+            for line in self.params.to_toml().lines() {
+                settings.push_str(", ");
+                settings.push_str(line);
+            }
+        }
+        Some(settings)
+    }
+}
+
+/// How long an engine gets to answer `uci` and `isready` when it's started
+const ENGINE_STARTUP: Duration = Duration::from_secs(10);
+
+/// Plays using an external engine that speaks UCI, run as a child process
+///
+/// The engine's output is read on a separate thread, so asking for a move never blocks. If the engine quits or
+/// answers with a move that isn't legal, the player interrupts the game.
+pub struct UciEnginePlayer {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    /// The name the engine gave in `id name`
+    name: String,
+    movetime: Duration,
+    depth: Option<usize>,
+    searching: bool,
+    /// Stopped searches whose `bestmove` hasn't come yet, so it must be ignored
+    stale: usize,
+    /// The score and line of the latest `info` of the ongoing search
+    score: Option<Score>,
+    pv: Vec<UciMove>,
+    last_move_info: Option<MoveInfo>,
+    failed: bool,
+}
+
+impl UciEnginePlayer {
+    /// Starts the engine at `path` and waits for it to be ready, it searches for a second per move by default
+    pub fn spawn(path: impl AsRef<OsStr>) -> io::Result<Self> {
+        let mut child = Command::new(path).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (tx, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut player = UciEnginePlayer {
+            child,
+            stdin,
+            lines,
+            name: "UCI engine".to_owned(),
+            movetime: Duration::from_secs(1),
+            depth: None,
+            searching: false,
+            stale: 0,
+            score: None,
+            pv: Vec::new(),
+            last_move_info: None,
+            failed: false,
+        };
+        player.send("uci")?;
+        player.wait_for("uciok")?;
+        player.send("isready")?;
+        player.wait_for("readyok")?;
+        Ok(player)
+    }
+    /// Makes the engine search for this long per move
+    pub fn with_movetime(mut self, movetime: Duration) -> Self {
+        self.movetime = movetime;
+        self
+    }
+    /// Makes the engine stop searching at this depth, if it hasn't run out of time before
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()
+    }
+    /// Reads lines until `reply`, picking up the engine's name along the way
+    fn wait_for(&mut self, reply: &str) -> io::Result<()> {
+        loop {
+            let line = self.lines.recv_timeout(ENGINE_STARTUP).map_err(|e| match e {
+                RecvTimeoutError::Timeout => io::Error::new(io::ErrorKind::TimedOut, format!("the engine didn't send {reply}")),
+                RecvTimeoutError::Disconnected => io::Error::new(io::ErrorKind::UnexpectedEof, "the engine quit"),
+            })?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                self.name = name.trim().to_owned();
+            }
+            if line.trim() == reply {
+                return Ok(());
+            }
+        }
+    }
+    /// Keeps the score and line of an `info` line about the best move
+    fn read_info<'a>(&mut self, mut words: impl Iterator<Item = &'a str>) {
+        while let Some(word) = words.next() {
+            match word {
+                // Only the best line is of interest
+                "multipv" if words.next() != Some("1") => return,
+                "score" => {
+                    if let Some(score) = parse_uci_score(words.next(), words.next()) {
+                        self.score = Some(score);
+                    }
+                }
+                "pv" => self.pv = words.by_ref().map_while(UciMove::from_str).collect(),
+                _ => (),
+            }
+        }
+    }
+    /// Stops the ongoing search, its move will be ignored
+    fn cancel(&mut self) {
+        if self.searching {
+            self.searching = false;
+            self.stale += 1;
+            if self.send("stop").is_err() {
+                self.failed = true;
+            }
+        }
+    }
+}
+
+/// The `position` command for a game, with the moves since its start so the engine knows about repetitions
+fn position_command(game: &Game) -> String {
+    let mut command = format!("position fen {}", game.start().display_fen());
+    if !game.history().is_empty() {
+        command.push_str(" moves");
+    }
+    for entry in game.history() {
+        if entry.is_null() {
+            command.push_str(" 0000");
+        } else {
+            command.push_str(&format!(" {}", UciMove { from: entry.from, unto: entry.unto, promotion: entry.promotion }));
+        }
+    }
+    command
+}
+
+/// Reads a score like `cp 25` or `mate -3` from UCI's `info score`
+fn parse_uci_score(kind: Option<&str>, value: Option<&str>) -> Option<Score> {
+    let value: i32 = value?.parse().ok()?;
+    match kind? {
+        "cp" => Some(Score(value.clamp(-Score::MATE_BOUND.0, Score::MATE_BOUND.0))),
+        // Mates are counted in moves, where the side mating needs one ply less than twice that
+        "mate" if value > 0 => Some(Score(Score::MATE.0 - (2 * value - 1))),
+        "mate" => Some(-Score(Score::MATE.0 + 2 * value)),
+        _ => None,
+    }
+}
+
+impl Drop for UciEnginePlayer {
+    fn drop(&mut self) {
+        self.cancel();
+        let _ = self.send("quit");
+        // Gives the engine a moment to quit by itself
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while matches!(self.child.try_wait(), Ok(None)) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Player for UciEnginePlayer {
+    fn make_move(&mut self, game: &Game) -> Option<Move> {
+        if self.failed {
+            return None;
+        }
+        if !self.searching {
+            self.score = None;
+            self.pv.clear();
+            let mut go = format!("go movetime {}", self.movetime.as_millis());
+            if let Some(depth) = self.depth {
+                go.push_str(&format!(" depth {depth}"));
+            }
+            if self.send(&position_command(game)).and_then(|()| self.send(&go)).is_err() {
+                self.failed = true;
+                return None;
+            }
+            self.searching = true;
+            return None;
+        }
+
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    self.failed = true;
+                    return None;
+                }
+            };
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("info") if self.stale == 0 => self.read_info(words),
+                Some("bestmove") if self.stale > 0 => self.stale -= 1,
+                Some("bestmove") => {
+                    self.searching = false;
+                    let Some(mv) = words.next().and_then(UciMove::from_str).and_then(|mv| mv.in_position(game.board_state())) else {
+                        self.failed = true;
+                        return None;
+                    };
+                    // The line is only kept as far as its moves are legal
+                    let mut state = *game.board_state();
+                    let mut pv = Vec::with_capacity(self.pv.len());
+                    for uci in &self.pv {
+                        let Some(mv) = uci.in_position(&state) else { break };
+                        if state.make_move(mv.from, mv.unto, mv.promotion).is_err() {
+                            break;
+                        }
+                        pv.push(mv);
+                    }
+                    self.last_move_info = self.score.map(|eval| MoveInfo::Search { eval, pv });
+                    return Some(mv);
+                }
+                _ => (),
+            }
+        }
+    }
+    fn last_move_info(&self) -> Option<MoveInfo> {
+        self.last_move_info.clone()
+    }
+    fn new_game(&mut self) {
+        self.cancel();
+        self.last_move_info = None;
+        if self.send("ucinewgame").is_err() {
+            self.failed = true;
+        }
+    }
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+    fn settings(&self) -> Option<String> {
+        let mut settings = format!("movetime {} ms", self.movetime.as_millis());
+        if let Some(depth) = self.depth {
+            settings.push_str(&format!(", depth {depth}"));
+        }
+        Some(settings)
+    }
+    fn interrupts(&self) -> bool {
+        self.failed
+    }
+}
+
+/// How a game run by a [`GameRunner`] ended
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Ending {
+    Checkmate { winner: Colour },
+    Stalemate,
+    /// A draw could be claimed, so it is taken
+    DrawClaimed,
+    Resignation { winner: Colour },
+    DrawByAgreement,
+}
+
+impl Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ending::Checkmate { winner } => write!(f, "Check-mate! {winner:?} wins."),
+            Ending::Stalemate => write!(f, "Draw by stalemate"),
+            Ending::DrawClaimed => write!(f, "Draw"),
+            Ending::Resignation { winner } => write!(f, "{:?} resigns. {winner:?} wins.", !*winner),
+            Ending::DrawByAgreement => write!(f, "Draw by agreement"),
+        }
+    }
+}
+
+/// What happened during a [`GameRunner::step`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// The player to move has not decided on a move yet
+    Waiting,
+    /// The player to move tried to make an illegal move
+    Illegal { mv: Move, error: MoveError },
+    /// A move was made, with what it did and how long it took
+    Moved(MoveOutcome),
+    /// The game is over
+    Ended(Ending),
+    /// The player to move stopped the game
+    Interrupted,
+}
+
+/// Drives a game between two players, shared by all front ends
+pub struct GameRunner {
+    game: Game,
+    white: Box<dyn Player>,
+    black: Box<dyn Player>,
+    turn_start: Instant,
+    ending: Option<Ending>,
+}
+
+impl GameRunner {
+    pub fn new(game: Game, white: Box<dyn Player>, black: Box<dyn Player>) -> Self {
+        let mut runner = GameRunner {
+            game,
+            white,
+            black,
+            turn_start: Instant::now(),
+            ending: None,
+        };
+        runner.ending = runner.check_ending();
+        runner
+    }
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+    /// Gives back the white and black players, e.g. to use them in a new game
+    pub fn into_players(self) -> (Box<dyn Player>, Box<dyn Player>) {
+        (self.white, self.black)
+    }
+    pub fn ending(&self) -> Option<Ending> {
+        self.ending
+    }
+    pub fn player(&self, colour: Colour) -> &dyn Player {
+        match colour {
+            Colour::White => &*self.white,
+            Colour::Black => &*self.black,
+        }
+    }
+    pub fn player_mut(&mut self, colour: Colour) -> &mut dyn Player {
+        match colour {
+            Colour::White => &mut *self.white,
+            Colour::Black => &mut *self.black,
+        }
+    }
+    /// The player whose turn it is
+    pub fn current_player(&self) -> &dyn Player {
+        self.player(self.game.side_to_move())
+    }
+    pub fn current_player_mut(&mut self) -> &mut dyn Player {
+        self.player_mut(self.game.side_to_move())
+    }
+    /// The side whose draw offer is standing, if any
+    pub fn draw_offer(&self) -> Option<Colour> {
+        self.game.draw_offer()
+    }
+    /// Ends the game with `colour` giving up
+    pub fn resign(&mut self, colour: Colour) {
+        if self.ending.is_none() {
+            self.game.resign(colour);
+            self.ending = self.check_ending();
+        }
+    }
+    /// Offers a draw on behalf of `colour`, returns whether the game is drawn because of it
+    ///
+    /// The offer is accepted if the opponent has offered a draw as well or its player accepts,
+    /// otherwise it stands until the opponent has made a move.
+    pub fn offer_draw(&mut self, colour: Colour) -> bool {
+        if self.ending.is_some() {
+            return false;
+        }
+        let drawn = self.game.offer_draw(colour) || (self.player(!colour).accepts_draw() && self.game.accept_draw());
+        self.ending = self.check_ending();
+        drawn
+    }
+    fn check_ending(&self) -> Option<Ending> {
+        match self.game.result() {
+            GameResult::Ongoing => None,
+            GameResult::WhiteWins(WinReason::Checkmate) => Some(Ending::Checkmate { winner: Colour::White }),
+            GameResult::BlackWins(WinReason::Checkmate) => Some(Ending::Checkmate { winner: Colour::Black }),
+            GameResult::WhiteWins(WinReason::Resignation) => Some(Ending::Resignation { winner: Colour::White }),
+            GameResult::BlackWins(WinReason::Resignation) => Some(Ending::Resignation { winner: Colour::Black }),
+            GameResult::Draw(DrawReason::Stalemate) => Some(Ending::Stalemate),
+            GameResult::Draw(DrawReason::Agreement) => Some(Ending::DrawByAgreement),
+            GameResult::Draw(_) => Some(Ending::DrawClaimed),
+        }
+    }
+    /// Asks the player to move for a move once and makes it if it's legal
+    pub fn step(&mut self) -> Step {
+        if let Some(ending) = self.ending {
+            return Step::Ended(ending);
+        }
+
+        let mover = self.game.side_to_move();
+        let player = match mover {
+            Colour::White => &mut self.white,
+            Colour::Black => &mut self.black,
+        };
+        let Some(mv) = player.make_move(&self.game) else {
+            if self.player(mover).interrupts() {
+                return Step::Interrupted;
+            }
+            return Step::Waiting;
+        };
+        let think_time = self.turn_start.elapsed();
+        let outcome = match self.game.make_timed_move(mv.from, mv.unto, mv.promotion, Some(think_time)) {
+            Ok(outcome) => outcome,
+            Err(error) => return Step::Illegal { mv, error },
+        };
+        self.turn_start = Instant::now();
+
+        self.ending = self.check_ending();
+        if self.player(mover).resigns() {
+            self.resign(mover);
+        } else if self.player(mover).offers_draw(self.game.position_repeated()) {
+            self.offer_draw(mover);
+        }
+
+        Step::Moved(outcome)
+    }
+    /// Keeps stepping until the game ends or is interrupted, calling `on_step` with every step other than waiting
+    pub fn run<F: FnMut(&GameRunner, Step)>(&mut self, mut on_step: F) -> Step {
+        loop {
+            match self.step() {
+                Step::Waiting => thread::sleep(Duration::from_millis(10)),
+                step @ (Step::Ended(_) | Step::Interrupted) => {
+                    on_step(self, step);
+                    return step;
+                }
+                step => on_step(self, step),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_offers_and_resignation() {
+        let new_runner = || GameRunner::new(Game::new(), Box::<HumanPlayer>::default(), Box::<HumanPlayer>::default());
+
+        let mut runner = new_runner();
+        assert!(!runner.offer_draw(Colour::White));
+        assert_eq!(runner.draw_offer(), Some(Colour::White));
+        assert!(runner.offer_draw(Colour::Black));
+        assert_eq!(runner.step(), Step::Ended(Ending::DrawByAgreement));
+
+        let mut runner = new_runner();
+        runner.resign(Colour::Black);
+        assert_eq!(runner.step(), Step::Ended(Ending::Resignation { winner: Colour::White }));
+        assert!(!runner.offer_draw(Colour::White));
+    }
+
+    #[test]
+    fn test_uci_helpers() {
+        assert_eq!(parse_uci_score(Some("cp"), Some("-25")), Some(Score(-25)));
+        assert_eq!(parse_uci_score(Some("mate"), Some("2")).and_then(Score::mate_in), Some(3));
+        assert_eq!(parse_uci_score(Some("mate"), Some("-2")).and_then(Score::mate_in), Some(-4));
+        assert_eq!(parse_uci_score(Some("wdl"), Some("2")), None);
+
+        let mut game = Game::new();
+        assert_eq!(position_command(&game), "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        game.replay(["e4", "e5"]).unwrap();
+        game.make_null_move().unwrap();
+        assert!(position_command(&game).ends_with("KQkq - 0 1 moves e2e4 e7e5 0000"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_uci_engine_player() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in engine that always wants to play e4, and reports a worse line for its second choice
+        let script = "#!/bin/sh
+while read command; do
+    case \"$command\" in
+        uci) echo 'id name Fake engine'; echo uciok ;;
+        isready) echo readyok ;;
+        go*) echo 'info depth 1 multipv 1 score cp 25 pv e2e4 e7e5'; echo 'info depth 1 multipv 2 score cp 5 pv d2d4'; echo 'bestmove e2e4' ;;
+        quit) exit ;;
+    esac
+done
+";
+        let path = std::env::temp_dir().join(format!("talv_fake_engine_{}", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut engine = UciEnginePlayer::spawn(&path).unwrap();
+        assert_eq!(engine.name(), "Fake engine");
+        let game = Game::new();
+        let mv = loop {
+            if let Some(mv) = engine.make_move(&game) {
+                break mv;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!((mv.from, mv.unto), (Coords::E2, Coords::E4));
+        let Some(MoveInfo::Search { eval, pv }) = engine.last_move_info() else { panic!("the engine reported a line") };
+        assert_eq!((eval, pv.len()), (Score(25), 2));
+        drop(engine);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,11 @@
+//! Re-exports of the most commonly used items, so that `use talv::prelude::*;` is enough for most programs
+
+pub use crate::{
+    algebraic::Move as AlgebraicMove,
+    board::{Colour, Field, Piece},
+    boardstate::{BoardState, PositionBuilder},
+    location::{Coords, File, Rank},
+    movegen::{any_legal_moves, gen_legal_moves, gen_legal_moves_ordered, get_all_moves, get_all_moves_ordered, legal_moves, GenMode, Move, UciMove},
+};
+#[cfg(feature = "std")]
+pub use crate::game::Game;
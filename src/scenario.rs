@@ -0,0 +1,184 @@
+//! Helpers for writing tests as a sequence of moves and expectations about the resulting positions
+//!
+//! ```
+//! use talv::scenario::{Scenario, Status};
+//!
+//! Scenario::start()
+//!     .play("1. f3 e5 2. g4 Qh4#")
+//!     .expect_status(Status::Checkmate);
+//! ```
+
+use crate::{
+    algebraic::Move as AlgebraicMove,
+    board::Colour,
+    boardstate::BoardState,
+    game::{DrawReason, Game, GameResult, WinReason},
+    movegen::get_all_moves,
+};
+
+/// The state of the game from the side to move's perspective
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// Not in check and not drawn
+    Normal,
+    Check,
+    Checkmate,
+    Stalemate,
+    /// A draw can be claimed or has happened automatically (repetition, move count rules or insufficient material)
+    DrawClaimable,
+    /// The game was ended by a resignation or a draw agreement
+    Decided,
+}
+
+impl Status {
+    pub fn of(game: &Game) -> Self {
+        match game.result() {
+            GameResult::WhiteWins(WinReason::Checkmate) | GameResult::BlackWins(WinReason::Checkmate) => Status::Checkmate,
+            GameResult::WhiteWins(WinReason::Resignation) | GameResult::BlackWins(WinReason::Resignation) => Status::Decided,
+            GameResult::Draw(DrawReason::Agreement) => Status::Decided,
+            GameResult::Draw(DrawReason::Stalemate) => Status::Stalemate,
+            _ if game.is_checked(game.side_to_move()) => Status::Check,
+            GameResult::Draw(_) => Status::DrawClaimable,
+            GameResult::Ongoing => Status::Normal,
+        }
+    }
+}
+
+/// A game being played through for testing, every method panics if its expectation is not met
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    game: Game,
+    played: Vec<String>,
+}
+
+impl Scenario {
+    /// Starts from the standard starting position
+    pub fn start() -> Self {
+        Scenario {
+            game: Game::new(),
+            played: Vec::new(),
+        }
+    }
+    /// Starts from a full FEN string
+    #[track_caller]
+    pub fn new(fen: &str) -> Self {
+        let Some(game) = Game::from_fen(fen) else {
+            panic!("invalid FEN: {fen}")
+        };
+        Scenario {
+            game,
+            played: Vec::new(),
+        }
+    }
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+    /// Gives up the game to keep playing it by other means
+    pub fn into_game(self) -> Game {
+        self.game
+    }
+    /// Plays moves in algebraic notation separated by whitespace, move numbers like `1.` are skipped
+    #[track_caller]
+    pub fn play(mut self, moves: &str) -> Self {
+        for san in moves.split_whitespace().filter(|s| !s.ends_with('.')) {
+            let mv = match AlgebraicMove::from_str(san) {
+                Ok(mv) => mv,
+                Err(e) => panic!("could not parse {san} after {:?}: {e}", self.played),
+            };
+            let Some(mv) = self.game.check_move(mv) else {
+                panic!("{san} does not fit the position after {:?}", self.played)
+            };
+            if let Err(e) = self.game.make_move(mv.from, mv.unto, mv.promotion) {
+                panic!("{san} is illegal after {:?}: {e}", self.played)
+            }
+            self.played.push(san.to_owned());
+        }
+        self
+    }
+    /// Has `colour` resign the game
+    pub fn resign(mut self, colour: Colour) -> Self {
+        self.game.resign(colour);
+        self
+    }
+    /// Expects the given move to be rejected in the current position
+    #[track_caller]
+    pub fn expect_illegal(self, san: &str) -> Self {
+        let legal = AlgebraicMove::from_str(san)
+            .ok()
+            .and_then(|mv| self.game.check_move(mv))
+            .is_some_and(|mv| self.game.clone().make_move(mv.from, mv.unto, mv.promotion).is_ok());
+        assert!(!legal, "{san} was legal after {:?}", self.played);
+        self
+    }
+    /// Expects the full FEN of the game, including clocks
+    #[track_caller]
+    pub fn expect_fen(self, fen: &str) -> Self {
+        assert_eq!(self.game.display_fen().to_string(), fen, "after {:?}", self.played);
+        self
+    }
+    /// Expects the position given by the first four fields of a FEN string, ignoring the clocks
+    #[track_caller]
+    pub fn expect_position(self, fen: &str) -> Self {
+        let Some(expected) = BoardState::from_fen(fen) else {
+            panic!("invalid FEN: {fen}")
+        };
+        assert_eq!(
+            self.game.board_state().display_fen().to_string(),
+            expected.display_fen().to_string(),
+            "after {:?}",
+            self.played
+        );
+        self
+    }
+    #[track_caller]
+    pub fn expect_status(self, status: Status) -> Self {
+        assert_eq!(Status::of(&self.game), status, "after {:?}", self.played);
+        self
+    }
+    #[track_caller]
+    pub fn expect_legal_moves(self, count: usize) -> Self {
+        assert_eq!(get_all_moves(self.game.board_state()).len(), count, "after {:?}", self.played);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scenarios() {
+        Scenario::start()
+            .expect_legal_moves(20)
+            .play("e4 e5 Nf3")
+            .expect_position("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq -")
+            .expect_status(Status::Normal)
+            .play("Nc6 Bb5 a6")
+            .expect_illegal("O-O-O")
+            .play("Bxc6 dxc6 O-O")
+            .expect_position("r1bqkbnr/1pp2ppp/p1p5/4p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq -");
+
+        Scenario::new("4k3/8/8/8/8/8/8/4K2R w K - 0 1")
+            .play("Rh8+")
+            .expect_status(Status::Check)
+            .play("Kd7 Rh7+ Ke6")
+            .expect_status(Status::Normal)
+            .expect_legal_moves(19);
+
+        Scenario::new("7k/5Q2/6K1/8/8/8/8/8 w - - 0 1")
+            .expect_illegal("Kg7")
+            .play("Qg7#")
+            .expect_status(Status::Checkmate);
+
+        Scenario::new("7k/8/6K1/8/8/8/8/5Q2 w - - 0 1")
+            .play("Qf7")
+            .expect_status(Status::Stalemate);
+
+        // Resigning in check isn't being checkmated
+        Scenario::start()
+            .play("e4 f5 Qh5+")
+            .resign(Colour::Black)
+            .expect_status(Status::Decided)
+            .expect_illegal("g6");
+    }
+}
@@ -0,0 +1,306 @@
+//! Perfect play for a king and a queen, rook or pawn against a lone king, worked out by retrograde analysis
+//!
+//! The tables are generated the first time a position from them is probed, which takes a moment for the pawn one
+//! since it needs the queen and rook tables for promotions.
+
+use std::sync::OnceLock;
+
+use crate::{
+    board::{Colour, MaterialCount, Piece},
+    boardstate::BoardState,
+    location::{squares_in, Coords, Rank},
+    movegen::attacks_from,
+};
+
+/// The result of a position with perfect play, from the side to move's perspective
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// The result of a position with perfect play along with the number of plies until mate, from the side to move's perspective
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Dtm {
+    Win(u8),
+    Draw,
+    Loss(u8),
+}
+
+impl Dtm {
+    pub fn wdl(self) -> Wdl {
+        match self {
+            Dtm::Win(_) => Wdl::Win,
+            Dtm::Draw => Wdl::Draw,
+            Dtm::Loss(_) => Wdl::Loss,
+        }
+    }
+}
+
+/// The result of a king and a queen, rook or pawn against a lone king, `None` for any other material
+///
+/// Positions where castling is still allowed aren't covered, and the fifty-move rule isn't taken into account.
+pub fn probe(state: &BoardState) -> Option<Dtm> {
+    if state.white_castling.short || state.white_castling.long || state.black_castling.short || state.black_castling.long {
+        return None;
+    }
+    let lone = MaterialCount::default();
+    // The tables have white as the stronger side
+    let (state, strong) = if state.material(Colour::Black) == lone {
+        (*state, state.material(Colour::White))
+    } else if state.material(Colour::White) == lone {
+        (state.flipped(), state.material(Colour::Black))
+    } else {
+        return None;
+    };
+    let piece = [Piece::Queen, Piece::Rook, Piece::Pawn]
+        .into_iter()
+        .find(|&piece| strong.count(piece) == 1 && strong.points() == piece.points())?;
+
+    let pos = Position {
+        white_king: state.piece_squares(Colour::White, Piece::King).next()?,
+        black_king: state.piece_squares(Colour::Black, Piece::King).next()?,
+        piece: state.piece_squares(Colour::White, piece).next()?,
+    };
+    Some(match (table(piece).plies[pos.index(state.side_to_move)], state.side_to_move) {
+        (DRAW, _) => Dtm::Draw,
+        (plies, Colour::White) => Dtm::Win(plies),
+        (plies, Colour::Black) => Dtm::Loss(plies),
+    })
+}
+
+/// Marks positions that aren't won for white, which includes the impossible ones
+const DRAW: u8 = u8::MAX;
+const POSITIONS: usize = 64 * 64 * 64;
+
+/// The plies until mate of every position with white's piece, with the black to move positions after the white to move ones
+struct Table {
+    plies: Vec<u8>,
+}
+
+fn table(piece: Piece) -> &'static Table {
+    static KQK: OnceLock<Table> = OnceLock::new();
+    static KRK: OnceLock<Table> = OnceLock::new();
+    static KPK: OnceLock<Table> = OnceLock::new();
+    let cell = match piece {
+        Piece::Queen => &KQK,
+        Piece::Rook => &KRK,
+        Piece::Pawn => &KPK,
+        _ => unreachable!("there's no table for {piece:?}"),
+    };
+    cell.get_or_init(|| Table::generate(piece))
+}
+
+impl Table {
+    fn generate(piece: Piece) -> Self {
+        let mut plies = vec![DRAW; 2 * POSITIONS];
+        // How many of black's moves aren't known to lose yet
+        let mut black_moves = vec![0u8; POSITIONS];
+        let mut lost = Vec::new();
+        // Positions won by promoting, with the plies until mate
+        let mut promotions = Vec::new();
+
+        for pos in Position::all().filter(|pos| pos.is_valid(piece)) {
+            let moves = pos.black_moves(piece);
+            black_moves[pos.index(Colour::Black) - POSITIONS] = moves.count_ones() as u8;
+            let in_check = pos.black_in_check(piece);
+            if moves == 0 && in_check {
+                plies[pos.index(Colour::Black)] = 0;
+                lost.push(pos);
+            }
+
+            if piece == Piece::Pawn && pos.piece.r() == Rank::N7 && !in_check {
+                let Some(unto) = pos.piece.add(0, 1) else { continue };
+                if unto == pos.white_king || unto == pos.black_king {
+                    continue;
+                }
+                let promoted = Position { piece: unto, ..pos };
+                let best = [Piece::Queen, Piece::Rook]
+                    .into_iter()
+                    .map(|promotion| table(promotion).plies[promoted.index(Colour::Black)])
+                    .min()
+                    .unwrap();
+                if best != DRAW {
+                    promotions.push((best + 1, pos));
+                }
+            }
+        }
+
+        // Every round finds the positions white wins one ply later than the lost ones found in the last round,
+        // and then the positions where every black move leads to one of those
+        let mut level = 0;
+        loop {
+            let mut won = Vec::new();
+            let unmoved = lost.iter().flat_map(|pos| pos.white_unmoves(piece));
+            let promoted = promotions.iter().filter(|&&(plies, _)| plies == level + 1).map(|&(_, pos)| pos);
+            for pos in unmoved.chain(promoted) {
+                let i = pos.index(Colour::White);
+                if plies[i] == DRAW && pos.is_valid(piece) && !pos.black_in_check(piece) {
+                    plies[i] = level + 1;
+                    won.push(pos);
+                }
+            }
+
+            lost.clear();
+            for pos in won.iter().flat_map(|pos| pos.black_unmoves(piece)) {
+                let i = pos.index(Colour::Black);
+                if plies[i] != DRAW {
+                    continue;
+                }
+                black_moves[i - POSITIONS] -= 1;
+                if black_moves[i - POSITIONS] == 0 {
+                    plies[i] = level + 2;
+                    lost.push(pos);
+                }
+            }
+
+            if lost.is_empty() && promotions.iter().all(|&(plies, _)| plies <= level + 1) {
+                break;
+            }
+            level += 2;
+        }
+
+        Table { plies }
+    }
+}
+
+/// The squares of the kings and white's piece
+#[derive(Debug, Copy, Clone)]
+struct Position {
+    white_king: Coords,
+    black_king: Coords,
+    piece: Coords,
+}
+
+fn mask(cs: Coords) -> u64 {
+    1 << cs.into_u8()
+}
+
+impl Position {
+    fn all() -> impl Iterator<Item = Position> {
+        Coords::full_range().flat_map(|white_king| {
+            Coords::full_range().flat_map(move |black_king| {
+                Coords::full_range().map(move |piece| Position {
+                    white_king,
+                    black_king,
+                    piece,
+                })
+            })
+        })
+    }
+    fn index(self, side_to_move: Colour) -> usize {
+        let i = (self.white_king.into_u8() as usize * 64 + self.black_king.into_u8() as usize) * 64 + self.piece.into_u8() as usize;
+        match side_to_move {
+            Colour::White => i,
+            Colour::Black => POSITIONS + i,
+        }
+    }
+    /// Whether the pieces can stand like this, ignoring whose move it is
+    fn is_valid(self, piece: Piece) -> bool {
+        self.white_king != self.black_king
+            && self.piece != self.white_king
+            && self.piece != self.black_king
+            && self.white_king.king_distance(self.black_king) > 1
+            && (piece != Piece::Pawn || (self.piece.r() != Rank::N1 && self.piece.r() != Rank::N8))
+    }
+    /// Whether white's piece attacks `target`, seeing through the black king
+    ///
+    /// This is checked for every position a few times over, so it only walks the line when the squares are on one.
+    fn piece_attacks(self, piece: Piece, target: Coords) -> bool {
+        let (dl, dn) = target.sub(self.piece);
+        let lined_up = match piece {
+            Piece::Pawn => return dn == 1 && dl.abs() == 1,
+            Piece::Rook => dl == 0 || dn == 0,
+            _ => dl == 0 || dn == 0 || dl.abs() == dn.abs(),
+        };
+        // Walks the squares between them by their numbers, since the squares in a line are evenly spaced
+        let step = dn.signum() * 8 + dl.signum();
+        let (from, unto, king) = (self.piece.into_u8() as i8, target.into_u8() as i8, self.white_king.into_u8() as i8);
+        lined_up && step != 0 && (1..).map(|i| from + i * step).take_while(|&i| i != unto).all(|i| i != king)
+    }
+    fn black_in_check(self, piece: Piece) -> bool {
+        self.piece_attacks(piece, self.black_king)
+    }
+    /// The squares the black king can move to, which includes taking white's piece if it's undefended
+    fn black_moves(self, piece: Piece) -> u64 {
+        let king_moves = attacks_from(Piece::King, Colour::Black, self.black_king, 0);
+        squares_in(king_moves)
+            .filter(|&cs| cs.king_distance(self.white_king) > 1 && !self.piece_attacks(piece, cs))
+            .fold(0, |moves, cs| moves | mask(cs))
+    }
+    /// The white to move positions that can reach this one with a white move, though they may not be valid
+    fn white_unmoves(self, piece: Piece) -> impl Iterator<Item = Position> {
+        let occupied = mask(self.white_king) | mask(self.black_king) | mask(self.piece);
+        let king_from = attacks_from(Piece::King, Colour::White, self.white_king, 0) & !occupied;
+        let piece_from = match piece {
+            Piece::Pawn => {
+                let one_back = self.piece.add(0, -1).filter(|&cs| occupied & mask(cs) == 0);
+                let two_back = one_back.filter(|_| self.piece.r() == Rank::N4).and_then(|cs| cs.add(0, -1)).filter(|&cs| occupied & mask(cs) == 0);
+                one_back.filter(|cs| cs.r() != Rank::N1).map_or(0, mask) | two_back.map_or(0, mask)
+            }
+            _ => attacks_from(piece, Colour::White, self.piece, occupied) & !occupied,
+        };
+        let kings = squares_in(king_from).map(move |white_king| Position { white_king, ..self });
+        let pieces = squares_in(piece_from).map(move |square| Position { piece: square, ..self });
+        kings.chain(pieces)
+    }
+    /// The black to move positions that can reach this one with a black move
+    fn black_unmoves(self, piece: Piece) -> impl Iterator<Item = Position> {
+        let occupied = mask(self.white_king) | mask(self.black_king) | mask(self.piece);
+        let from = attacks_from(Piece::King, Colour::Black, self.black_king, 0) & !occupied;
+        squares_in(from)
+            .map(move |black_king| Position { black_king, ..self })
+            .filter(move |pos| pos.is_valid(piece))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::get_all_moves;
+
+    fn probe_fen(fen: &str) -> Option<Dtm> {
+        probe(&BoardState::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn test_probe() {
+        assert_eq!(probe_fen("k7/7Q/1K6/8/8/8/8/8 w - -"), Some(Dtm::Win(1)));
+        assert_eq!(probe_fen("6q1/8/8/8/8/1k6/8/K7 b - -"), Some(Dtm::Win(1)));
+        assert_eq!(probe_fen("4k3/8/4K3/4P3/8/8/8/8 b - -").map(Dtm::wdl), Some(Wdl::Loss));
+        assert_eq!(probe_fen("4k3/4P3/4K3/8/8/8/8/8 b - -"), Some(Dtm::Draw));
+        assert_eq!(probe_fen("8/8/8/8/8/2k5/8/K6r w - -").map(Dtm::wdl), Some(Wdl::Loss));
+        assert_eq!(probe_fen("8/8/8/8/8/2k5/8/K5qr w - -"), None);
+        assert_eq!(probe_fen("4k3/8/8/8/8/8/8/4K2R w K -"), None);
+
+        // The longest mates are known to be 10 moves with a queen and 16 with a rook
+        let longest = |piece| table(piece).plies[..POSITIONS].iter().filter(|&&plies| plies != DRAW).max().copied();
+        assert_eq!(longest(Piece::Queen), Some(19));
+        assert_eq!(longest(Piece::Rook), Some(31));
+    }
+
+    #[test]
+    fn test_probe_agrees_with_moves() {
+        for fen in ["8/8/3k4/8/8/4K3/4P3/8 w - -", "8/8/3k4/8/8/4K3/4P3/8 b - -", "8/1k6/8/8/3K4/8/8/6R1 w - -", "8/P7/8/8/8/8/2k5/K7 w - -"] {
+            let state = BoardState::from_fen(fen).unwrap();
+            let best = get_all_moves(&state)
+                .into_iter()
+                .map(|mv| {
+                    let mut after = state;
+                    after.make_move(mv.from, mv.unto, mv.promotion).unwrap();
+                    match probe(&after) {
+                        Some(Dtm::Loss(plies)) => Dtm::Win(plies + 1),
+                        Some(Dtm::Win(plies)) => Dtm::Loss(plies + 1),
+                        _ => Dtm::Draw,
+                    }
+                })
+                .max_by_key(|&dtm| match dtm {
+                    Dtm::Win(plies) => 1000 - plies as i32,
+                    Dtm::Draw => 0,
+                    Dtm::Loss(plies) => plies as i32 - 1000,
+                });
+            assert_eq!(probe(&state), best, "{fen}");
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! A drill for learning the names of the squares
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::location::Coords;
+
+/// One square asked for and what the user answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attempt {
+    pub target: Coords,
+    pub answer: Coords,
+    pub time: Duration,
+}
+
+impl Attempt {
+    pub fn is_correct(&self) -> bool {
+        self.target == self.answer
+    }
+}
+
+/// Asks for random squares and keeps track of how quickly and correctly they are found
+#[derive(Debug, Clone)]
+pub struct CoordinateTrainer {
+    rng: u64,
+    target: Coords,
+    asked_at: Instant,
+    attempts: Vec<Attempt>,
+}
+
+impl Default for CoordinateTrainer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CoordinateTrainer {
+    /// Starts with a seed taken from the clock
+    pub fn new() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        Self::with_seed(nanos)
+    }
+    /// Starts with a given seed, so the squares asked for can be repeated
+    pub fn with_seed(seed: u64) -> Self {
+        let mut trainer = CoordinateTrainer {
+            // xorshift gets stuck on zero
+            rng: seed | 1,
+            target: Coords::from_str("a1").unwrap(),
+            asked_at: Instant::now(),
+            attempts: Vec::new(),
+        };
+        trainer.next_target();
+        trainer
+    }
+    fn next_target(&mut self) {
+        let previous = self.target;
+        while self.target == previous {
+            self.rng ^= self.rng << 13;
+            self.rng ^= self.rng >> 7;
+            self.rng ^= self.rng << 17;
+            self.target = Coords::full_range().nth((self.rng % 64) as usize).unwrap();
+        }
+        self.asked_at = Instant::now();
+    }
+    /// The square the user should find
+    pub fn target(&self) -> Coords {
+        self.target
+    }
+    /// Records the user's answer to the current square and moves on to the next, returns whether it was right
+    pub fn answer(&mut self, answer: Coords) -> bool {
+        let attempt = Attempt {
+            target: self.target,
+            answer,
+            time: self.asked_at.elapsed(),
+        };
+        self.attempts.push(attempt);
+        self.next_target();
+        attempt.is_correct()
+    }
+    pub fn attempts(&self) -> &[Attempt] {
+        &self.attempts
+    }
+    pub fn correct(&self) -> usize {
+        self.attempts.iter().filter(|a| a.is_correct()).count()
+    }
+    /// The average time taken to find a square, counting only right answers
+    pub fn average_time(&self) -> Option<Duration> {
+        let correct = self.correct();
+        if correct == 0 {
+            return None;
+        }
+        let total: Duration = self.attempts.iter().filter(|a| a.is_correct()).map(|a| a.time).sum();
+        Some(total / correct as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trainer() {
+        let mut trainer = CoordinateTrainer::with_seed(42);
+        let mut seen = Vec::new();
+        for _ in 0..100 {
+            let target = trainer.target();
+            assert!(seen.last() != Some(&target));
+            seen.push(target);
+            assert!(trainer.answer(target));
+        }
+        assert_eq!(trainer.correct(), 100);
+        assert!(seen.iter().any(|&cs| cs != seen[0]));
+
+        let target = trainer.target();
+        let wrong = Coords::full_range().find(|&cs| cs != target).unwrap();
+        assert!(!trainer.answer(wrong));
+        assert_eq!(trainer.attempts().len(), 101);
+        assert_eq!(trainer.correct(), 100);
+        assert!(trainer.average_time().is_some());
+
+        let same_seed: Vec<_> = (0..10)
+            .scan(CoordinateTrainer::with_seed(42), |t, _| {
+                let target = t.target();
+                t.answer(target);
+                Some(target)
+            })
+            .collect();
+        assert_eq!(same_seed, seen[..10]);
+    }
+}
@@ -0,0 +1,569 @@
+//! The Universal Chess Interface, so the bot can play in GUIs and tools like cutechess, Arena and lichess-bot
+//!
+//! [`run`] reads commands on one thread while the bot searches on another, so `stop` and `isready` are answered
+//! right away. `stop` answers with the best move of the last finished iteration, and the search itself is aborted
+//! cooperatively: each `RunningSearch` hands its `abort` flag to the search with [`SearchOptions::with_stop`] and sets
+//! it when dropped, so a search that is stopped, replaced or out of time gives up instead of running on.
+
+use std::{
+    fmt::{self, Display},
+    fs,
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::Colour,
+    bots::{
+        bot1::{CurrMove, EvalParams, OpeningBook, RankedLine, Score, SearchEvent, SearchOptions, Searcher, Skill, TtStats},
+        rng::Rng,
+    },
+    config::BookConfig,
+    game::Game,
+    movegen::{get_all_moves_ordered, GenMode, Move, UciMove},
+};
+
+/// A command from the GUI, the ones talv doesn't use are left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption { name: String, value: Option<String> },
+    UciNewGame,
+    /// The position to search from as a FEN string, `None` for the starting position, and the moves played since
+    Position { fen: Option<String>, moves: Vec<UciMove> },
+    Go(Go),
+    Stop,
+    PonderHit,
+    Quit,
+}
+
+/// The limits of a `go` command, all of them optional
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Go {
+    pub depth: Option<usize>,
+    pub nodes: Option<usize>,
+    pub movetime: Option<Duration>,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    /// Search until `stop`, even if the search is done before that
+    pub infinite: bool,
+}
+
+impl Command {
+    /// Parses a line from the GUI, `None` if it isn't a command talv knows
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        Some(match words.next()? {
+            "uci" => Command::Uci,
+            "debug" => Command::Debug(words.next()? == "on"),
+            "isready" => Command::IsReady,
+            "setoption" => {
+                let words: Vec<_> = words.collect();
+                if words.first() != Some(&"name") {
+                    return None;
+                }
+                let value_at = words.iter().position(|&word| word == "value");
+                let name = words[1..value_at.unwrap_or(words.len())].join(" ");
+                let value = value_at.map(|i| words[i + 1..].join(" "));
+                Command::SetOption { name, value }
+            }
+            "ucinewgame" => Command::UciNewGame,
+            "position" => {
+                let words: Vec<_> = words.collect();
+                let moves_at = words.iter().position(|&word| word == "moves").unwrap_or(words.len());
+                let fen = match *words.first()? {
+                    "startpos" => None,
+                    "fen" => Some(words[1..moves_at].join(" ")),
+                    _ => return None,
+                };
+                let moves = words.get(moves_at + 1..).unwrap_or_default().iter().map(|s| UciMove::from_str(s)).collect::<Option<_>>()?;
+                Command::Position { fen, moves }
+            }
+            "go" => Command::Go(Go::parse(words)?),
+            "stop" => Command::Stop,
+            "ponderhit" => Command::PonderHit,
+            "quit" => Command::Quit,
+            _ => return None,
+        })
+    }
+}
+
+impl Go {
+    fn parse<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<Self> {
+        // GUIs sometimes send a negative time left when a player has just run out
+        let millis = |word: Option<&str>| word?.parse::<i64>().ok().map(|ms| Duration::from_millis(ms.max(0) as u64));
+        let mut go = Go::default();
+        while let Some(word) = words.next() {
+            match word {
+                "depth" => go.depth = Some(words.next()?.parse().ok()?),
+                "nodes" => go.nodes = Some(words.next()?.parse().ok()?),
+                "movetime" => go.movetime = Some(millis(words.next())?),
+                "wtime" => go.wtime = Some(millis(words.next())?),
+                "btime" => go.btime = Some(millis(words.next())?),
+                "winc" => go.winc = Some(millis(words.next())?),
+                "binc" => go.binc = Some(millis(words.next())?),
+                "movestogo" => go.movestogo = Some(words.next()?.parse().ok()?),
+                "infinite" => go.infinite = true,
+                _ => (),
+            }
+        }
+        Some(go)
+    }
+    /// How long to think with `side` to move, `None` if there's no time limit
+    ///
+    /// With a clock this is an even share of the time left over the moves until the next time control, or 30 if
+    /// there isn't one, plus most of the increment.
+    pub fn time_budget(&self, side: Colour) -> Option<Duration> {
+        if self.movetime.is_some() {
+            return self.movetime;
+        }
+        let (time, inc) = match side {
+            Colour::White => (self.wtime?, self.winc.unwrap_or_default()),
+            Colour::Black => (self.btime?, self.binc.unwrap_or_default()),
+        };
+        let share = time / self.movestogo.unwrap_or(30).max(1) + inc * 3 / 4;
+        // Some time is kept back for sending the move
+        Some(share.min(time.saturating_sub(Duration::from_millis(50))))
+    }
+}
+
+/// The settings that can be changed with `setoption`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub max_depth: usize,
+    pub max_nodes: usize,
+    /// How many of the best moves to report lines for
+    pub multipv: usize,
+    pub threads: usize,
+    pub params: EvalParams,
+    /// Played from instead of searching when it has a move, except in infinite searches
+    pub book: Option<OpeningBook>,
+    pub skill: Skill,
+    /// Makes the random choices the same every game, random if `None`
+    pub seed: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_depth: 10,
+            max_nodes: 1_000_000,
+            multipv: 1,
+            threads: 1,
+            params: EvalParams::default(),
+            book: None,
+            skill: Skill::MAX,
+            seed: None,
+        }
+    }
+}
+
+/// Speaks UCI over `input` and `output` until `quit` or the end of the input, after which a running search is finished
+pub fn run<R: BufRead + Send + 'static, W: Write>(input: R, output: W) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let input_tx = tx.clone();
+    thread::spawn(move || {
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            if input_tx.send(Message::Input(line)).is_err() {
+                return;
+            }
+        }
+        let _ = input_tx.send(Message::InputClosed);
+    });
+
+    let mut session = Session {
+        out: output,
+        game: Game::new(),
+        options: Options::default(),
+        search: None,
+        searches: 0,
+        tx,
+        rng: Rng::from_entropy(),
+        searcher: Arc::new(Mutex::new(Searcher::default())),
+    };
+    let mut input_closed = false;
+    loop {
+        let deadline = session.search.as_ref().and_then(|search| search.deadline);
+        let message = match deadline {
+            Some(deadline) => match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match rx.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            },
+        };
+        match message {
+            Some(Message::Input(line)) => match Command::parse(&line) {
+                Some(Command::Quit) => break,
+                Some(command) => session.handle(command)?,
+                None if line.trim().is_empty() => (),
+                None => writeln!(session.out, "info string unknown command {}", line.trim())?,
+            },
+            Some(Message::InputClosed) => input_closed = true,
+            Some(Message::Search(id, progress)) => session.progress(id, progress)?,
+            // Out of time
+            None => session.stop()?,
+        }
+        session.out.flush()?;
+        if input_closed && session.search.is_none() {
+            break;
+        }
+    }
+    session.out.flush()
+}
+
+enum Message {
+    Input(String),
+    InputClosed,
+    /// Progress of the search with the number
+    Search(u64, Progress),
+}
+
+enum Progress {
+    CurrMove(CurrMove),
+    Iteration { depth: usize, lines: Vec<RankedLine>, nodes: usize, time: Duration, nps: Option<u64>, tt_stats: TtStats },
+    Done { best: Option<Move> },
+}
+
+struct Session<W> {
+    out: W,
+    game: Game,
+    options: Options,
+    search: Option<RunningSearch>,
+    /// How many searches have been started, used to tell the progress of the current one from old ones
+    searches: u64,
+    tx: Sender<Message>,
+    /// For picking book moves and the mistakes of lower skill levels
+    rng: Rng,
+    /// Kept until `ucinewgame`, so every search starts with what the earlier ones found
+    searcher: Arc<Mutex<Searcher>>,
+}
+
+struct RunningSearch {
+    id: u64,
+    started: Instant,
+    deadline: Option<Instant>,
+    infinite: bool,
+    /// The best move of the last finished iteration, or the first legal move before that
+    best: Option<Move>,
+    /// Makes the search thread give up once the search is forgotten
+    abort: Arc<AtomicBool>,
+}
+
+impl Drop for RunningSearch {
+    fn drop(&mut self) {
+        self.abort.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<W: Write> Session<W> {
+    fn handle(&mut self, command: Command) -> io::Result<()> {
+        match command {
+            Command::Uci => {
+                writeln!(self.out, "id name talv {}", env!("CARGO_PKG_VERSION"))?;
+                writeln!(self.out, "id author the talv developers")?;
+                let defaults = Options::default();
+                writeln!(self.out, "option name MaxDepth type spin default {} min 1 max 100", defaults.max_depth)?;
+                writeln!(self.out, "option name MaxNodes type spin default {} min 1 max 1000000000", defaults.max_nodes)?;
+                writeln!(self.out, "option name MultiPV type spin default {} min 1 max 256", defaults.multipv)?;
+                writeln!(self.out, "option name Threads type spin default {} min 1 max 256", defaults.threads)?;
+                writeln!(self.out, "option name EvalParams type string default <empty>")?;
+                writeln!(self.out, "option name BookFile type string default <empty>")?;
+                writeln!(self.out, "option name Skill Level type spin default {} min 0 max {}", defaults.skill.level(), Skill::MAX.level())?;
+                writeln!(self.out, "option name Seed type string default <empty>")?;
+                writeln!(self.out, "uciok")?;
+            }
+            Command::IsReady => writeln!(self.out, "readyok")?,
+            Command::SetOption { name, value } => self.set_option(&name, value.as_deref())?,
+            Command::UciNewGame => {
+                self.game = Game::new();
+                self.reseed();
+                // A new table rather than clearing the old one, which a search might still be holding on to
+                self.searcher = Arc::new(Mutex::new(Searcher::default()));
+            }
+            Command::Position { fen, moves } => {
+                let game = match fen {
+                    Some(fen) => Game::from_fen(&fen),
+                    None => Some(Game::new()),
+                };
+                let Some(mut game) = game else {
+                    return writeln!(self.out, "info string invalid position");
+                };
+                for mv in moves {
+                    let made = mv.in_position(game.board_state()).map(|mv| game.make_move(mv.from, mv.unto, mv.promotion));
+                    if !matches!(made, Some(Ok(_))) {
+                        return writeln!(self.out, "info string illegal move {mv}");
+                    }
+                }
+                self.game = game;
+            }
+            Command::Go(go) => self.go(go),
+            Command::Stop => self.stop()?,
+            // Pondering isn't offered, so there's nothing to switch over from
+            Command::Debug(_) | Command::PonderHit | Command::Quit => (),
+        }
+        Ok(())
+    }
+    fn set_option(&mut self, name: &str, value: Option<&str>) -> io::Result<()> {
+        let value = value.unwrap_or_default();
+        match name {
+            "MaxDepth" => match value.parse() {
+                Ok(depth) if depth > 0 => self.options.max_depth = depth,
+                _ => writeln!(self.out, "info string invalid MaxDepth {value}")?,
+            },
+            "MaxNodes" => match value.parse() {
+                Ok(nodes) if nodes > 0 => self.options.max_nodes = nodes,
+                _ => writeln!(self.out, "info string invalid MaxNodes {value}")?,
+            },
+            "MultiPV" => match value.parse() {
+                Ok(multipv) if multipv > 0 => self.options.multipv = multipv,
+                _ => writeln!(self.out, "info string invalid MultiPV {value}")?,
+            },
+            "Threads" => match value.parse() {
+                Ok(threads) if threads > 0 => self.options.threads = threads,
+                _ => writeln!(self.out, "info string invalid Threads {value}")?,
+            },
+            "EvalParams" if value.is_empty() || value == "<empty>" => self.options.params = EvalParams::default(),
+            "EvalParams" => match fs::read_to_string(value).map(|s| EvalParams::from_toml(&s)) {
+                Ok(Ok(params)) => self.options.params = params,
+                Ok(Err(e)) => writeln!(self.out, "info string invalid parameters in {value}: {e}")?,
+                Err(e) => writeln!(self.out, "info string could not read {value}: {e}")?,
+            },
+            "Skill Level" => match value.parse() {
+                Ok(level) if level <= Skill::MAX.level() => self.options.skill = Skill::new(level),
+                _ => writeln!(self.out, "info string invalid Skill Level {value}")?,
+            },
+            "Seed" if value.is_empty() || value == "<empty>" => {
+                self.options.seed = None;
+                self.reseed();
+            }
+            "Seed" => match value.parse() {
+                Ok(seed) => {
+                    self.options.seed = Some(seed);
+                    self.reseed();
+                }
+                Err(_) => writeln!(self.out, "info string invalid Seed {value}")?,
+            },
+            "BookFile" if value.is_empty() || value == "<empty>" => self.options.book = None,
+            "BookFile" => {
+                let config = BookConfig { path: Some(value.into()), ..BookConfig::default() };
+                match config.load() {
+                    Ok(book) => self.options.book = book,
+                    Err(e) => writeln!(self.out, "info string {e}")?,
+                }
+            }
+            _ => writeln!(self.out, "info string unknown option {name}")?,
+        }
+        Ok(())
+    }
+    /// Starts the random choices over from the seed
+    fn reseed(&mut self) {
+        self.rng = self.options.seed.map_or_else(Rng::from_entropy, Rng::new);
+    }
+    fn go(&mut self, go: Go) {
+        self.searches += 1;
+        let id = self.searches;
+        let state = *self.game.board_state();
+        let started = Instant::now();
+        let abort = Arc::new(AtomicBool::new(false));
+        self.search = Some(RunningSearch {
+            id,
+            started,
+            deadline: go.time_budget(state.side_to_move).map(|budget| started + budget),
+            infinite: go.infinite,
+            best: get_all_moves_ordered(&state, GenMode::Stable).first().copied(),
+            abort: abort.clone(),
+        });
+
+        let book_move = match &self.options.book {
+            Some(book) if !go.infinite => book.pick(&self.game, &mut self.rng),
+            _ => None,
+        };
+        if let Some(mv) = book_move {
+            let _ = self.tx.send(Message::Search(id, Progress::Done { best: Some(mv) }));
+            return;
+        }
+
+        let mut search_options = SearchOptions::new(go.depth.unwrap_or(self.options.max_depth), go.nodes.unwrap_or(self.options.max_nodes))
+            .with_multipv(self.options.multipv)
+            .with_threads(self.options.threads)
+            .with_stop(abort);
+        let skill = self.options.skill;
+        if skill < Skill::MAX {
+            search_options = search_options.with_skill(skill, self.rng.next_u64());
+        }
+        let mut rng = Rng::new(self.rng.next_u64());
+        let params = self.options.params.clone();
+        let searcher = self.searcher.clone();
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let ranking = searcher.lock().unwrap().get_moves_ranked_with_info(&state, &search_options, &params, |event| {
+                let progress = match event {
+                    SearchEvent::CurrMove(curr_move) => Progress::CurrMove(curr_move),
+                    SearchEvent::Iteration(info) => Progress::Iteration {
+                        depth: info.depth,
+                        lines: info.lines.to_vec(),
+                        nodes: info.nodes,
+                        time: info.elapsed,
+                        nps: info.nps(),
+                        tt_stats: info.tt_stats.clone(),
+                    },
+                };
+                let _ = tx.send(Message::Search(id, progress));
+            });
+            let _ = tx.send(Message::Search(id, Progress::Done { best: skill.choose(&ranking, &mut rng) }));
+        });
+    }
+    fn progress(&mut self, id: u64, progress: Progress) -> io::Result<()> {
+        let Some(search) = self.search.as_mut().filter(|search| search.id == id) else {
+            return Ok(());
+        };
+        let elapsed = search.started.elapsed();
+        match progress {
+            // Only reported for long searches, like most engines do, so short ones don't flood the GUI
+            Progress::CurrMove(curr_move) if elapsed >= Duration::from_secs(1) => {
+                let CurrMove { depth, mv, number, .. } = curr_move;
+                writeln!(self.out, "info depth {depth} currmove {} currmovenumber {number}", mv.uci())?;
+            }
+            Progress::CurrMove(_) => (),
+            Progress::Iteration { depth, lines, nodes, time, nps, tt_stats } => {
+                if let Some(&best) = lines.first().and_then(|line| line.pv.first()) {
+                    search.best = Some(best);
+                }
+                // A lower skill searches more lines than asked for, to pick its mistakes from
+                for (i, line) in lines.iter().take(self.options.multipv).enumerate() {
+                    write!(self.out, "info depth {depth}")?;
+                    // GUIs only expect the line number once they have asked for more than one
+                    if self.options.multipv > 1 {
+                        write!(self.out, " multipv {}", i + 1)?;
+                    }
+                    write!(self.out, " score {} nodes {nodes} time {}", UciScore(line.eval), time.as_millis())?;
+                    if let Some(nps) = nps {
+                        write!(self.out, " nps {nps}")?;
+                    }
+                    write!(self.out, " hashfull {} pv", tt_stats.hashfull())?;
+                    for mv in &line.pv {
+                        write!(self.out, " {}", mv.uci())?;
+                    }
+                    writeln!(self.out)?;
+                }
+                let TtStats { hits, misses, overwrites, ages: [this, previous, older], .. } = tt_stats;
+                writeln!(self.out, "info string tt hits {hits} misses {misses} overwrites {overwrites} ages {this} {previous} {older}")?;
+            }
+            Progress::Done { best } => {
+                search.best = best.or(search.best);
+                // An infinite search waits for `stop` before sending its move
+                if !search.infinite {
+                    self.stop()?;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Sends the best move found so far and forgets about the search, which stops it
+    fn stop(&mut self) -> io::Result<()> {
+        if let Some(search) = self.search.take() {
+            match search.best {
+                Some(best) => writeln!(self.out, "bestmove {}", best.uci())?,
+                // UCI's null move, for positions without any legal moves
+                None => writeln!(self.out, "bestmove 0000")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The score as UCI wants it, either `cp` with centipawns or `mate` with the number of moves, negative if getting mated
+struct UciScore(Score);
+
+impl Display for UciScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.mate_in() {
+            Some(plies) if plies > 0 => write!(f, "mate {}", (plies + 1) / 2),
+            Some(plies) => write!(f, "mate {}", plies / 2),
+            None => write!(f, "cp {}", self.0 .0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            Command::parse("position startpos moves e2e4 e7e5"),
+            Some(Command::Position {
+                fen: None,
+                moves: vec![UciMove::from_str("e2e4").unwrap(), UciMove::from_str("e7e5").unwrap()],
+            })
+        );
+        assert_eq!(
+            Command::parse("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1"),
+            Some(Command::Position { fen: Some("4k3/8/8/8/8/8/8/4K2R w K - 0 1".to_owned()), moves: Vec::new() })
+        );
+        assert_eq!(
+            Command::parse("setoption name EvalParams value my params.toml"),
+            Some(Command::SetOption { name: "EvalParams".to_owned(), value: Some("my params.toml".to_owned()) })
+        );
+        assert_eq!(Command::parse("position startpos moves e2e9"), None);
+        assert_eq!(Command::parse("register later"), None);
+
+        let Some(Command::Go(go)) = Command::parse("go wtime 60000 btime -20 winc 1000 movestogo 20") else { panic!() };
+        assert_eq!(go.btime, Some(Duration::ZERO));
+        assert_eq!(go.time_budget(Colour::White), Some(Duration::from_millis(3750)));
+        assert_eq!(go.time_budget(Colour::Black), Some(Duration::ZERO));
+        let Some(Command::Go(go)) = Command::parse("go depth 3 movetime 500") else { panic!() };
+        assert_eq!((go.depth, go.time_budget(Colour::Black)), (Some(3), Some(Duration::from_millis(500))));
+    }
+
+    #[test]
+    fn test_session() {
+        let input = "uci\nisready\nsetoption name MaxDepth value 0\nsetoption name MultiPV value 2\nposition startpos moves e2e4 e7e5 d1h5 b8c6 f1c4 g8f6\ngo depth 2\n";
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert!(lines.contains(&"uciok"));
+        assert!(lines.contains(&"readyok"));
+        assert!(lines.contains(&"info string invalid MaxDepth 0"));
+        assert!(lines.iter().any(|line| line.starts_with("info depth 2 multipv 1 score") && line.ends_with(" pv h5f7")));
+        assert!(lines.iter().any(|line| line.starts_with("info depth 2 multipv 2 score")));
+        assert!(lines.iter().any(|line| line.starts_with("info string tt hits ") && line.contains(" ages ")));
+        assert_eq!(lines.last(), Some(&"bestmove h5f7"));
+    }
+
+    #[test]
+    fn test_seed() {
+        // A weak bot with the same seed makes the same mistakes
+        let input = "setoption name Seed value 42\nsetoption name Skill Level value 0\nposition startpos moves e2e4\ngo\n\
+            setoption name Seed value -1\n";
+        let best_moves: Vec<_> = (0..2)
+            .map(|_| {
+                let mut output = Vec::new();
+                run(input.as_bytes(), &mut output).unwrap();
+                let output = String::from_utf8(output).unwrap();
+                assert!(output.lines().any(|line| line == "info string invalid Seed -1"));
+                output.lines().filter(|line| line.starts_with("bestmove")).map(str::to_owned).collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(best_moves[0].len(), 1);
+        assert_eq!(best_moves[0], best_moves[1]);
+    }
+}